@@ -6,6 +6,38 @@ module "media-rag" {
 
   pinecone_api_key = var.pinecone_api_key
 
+  jwt_issuer   = var.jwt_issuer
+  jwt_audience = var.jwt_audience
+  admin_group  = var.admin_group
+  route_scopes = var.route_scopes
+
+  query_burst          = var.query_burst
+  queries_per_minute   = var.queries_per_minute
+  monthly_token_budget = var.monthly_token_budget
+
+  knowledge_base_name = var.knowledge_base_name
+  knowledge_bases     = var.knowledge_bases
+  max_passages        = var.max_passages
+  number_of_results   = var.number_of_results
+  prompt_template     = var.prompt_template
+
+  answer_cache_retention_days = var.answer_cache_retention_days
+
+  ingestion_mode = var.ingestion_mode
+
+  transcribe_output_retention_days = var.transcribe_output_retention_days
+
+  transcript_cleaning = var.transcript_cleaning
+
+  enrichment_model_id = var.enrichment_model_id
+
+  ffmpeg_layer_arn           = var.ffmpeg_layer_arn
+  normalize_media            = var.normalize_media
+  max_media_duration_seconds = var.max_media_duration_seconds
+  visual_model_id            = var.visual_model_id
+  frame_interval_seconds     = var.frame_interval_seconds
+  max_frames                 = var.max_frames
+
   start_transcription_job_lambda = {
     dist_dir = "../src/target/lambda/start-transcription-job"
     name     = "start-transcription-job"
@@ -18,6 +50,36 @@ module "media-rag" {
     handler  = "bootstrap"
   }
 
+  start_ingestion_job_lambda = {
+    dist_dir = "../src/target/lambda/start-ingestion-job"
+    name     = "start-ingestion-job"
+    handler  = "bootstrap"
+  }
+
+  track_ingestion_jobs_lambda = {
+    dist_dir = "../src/target/lambda/track-ingestion-jobs"
+    name     = "track-ingestion-jobs"
+    handler  = "bootstrap"
+  }
+
+  normalize_media_lambda = {
+    dist_dir = "../src/target/lambda/normalize-media"
+    name     = "normalize-media"
+    handler  = "bootstrap"
+  }
+
+  extract_video_frames_lambda = {
+    dist_dir = "../src/target/lambda/extract-video-frames"
+    name     = "extract-video-frames"
+    handler  = "bootstrap"
+  }
+
+  notify_webhook_lambda = {
+    dist_dir = "../src/target/lambda/notify-webhook"
+    name     = "notify-webhook"
+    handler  = "bootstrap"
+  }
+
   create_media_upload_link_lambda = {
     dist_dir = "../src/target/lambda/create-media-upload-link"
     name     = "create-media-upload-link"
@@ -30,4 +92,28 @@ module "media-rag" {
     handler  = "bootstrap"
   }
 
+  update_media_metadata_lambda = {
+    dist_dir = "../src/target/lambda/update-media-metadata"
+    name     = "update-media-metadata"
+    handler  = "bootstrap"
+  }
+
+  delete_media_lambda = {
+    dist_dir = "../src/target/lambda/delete-media"
+    name     = "delete-media"
+    handler  = "bootstrap"
+  }
+
+  list_media_lambda = {
+    dist_dir = "../src/target/lambda/list-media"
+    name     = "list-media"
+    handler  = "bootstrap"
+  }
+
+  submit_feedback_lambda = {
+    dist_dir = "../src/target/lambda/submit-feedback"
+    name     = "submit-feedback"
+    handler  = "bootstrap"
+  }
+
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+resource "aws_glue_catalog_database" "analytics" {
+  name = replace("${var.application}_${var.environment}_analytics", "-", "_")
+}
+
+# Partitions are projected, queries must filter on tenant_id and may filter on dt.
+resource "aws_glue_catalog_table" "queries" {
+  name          = "queries"
+  database_name = aws_glue_catalog_database.analytics.name
+  table_type    = "EXTERNAL_TABLE"
+
+  parameters = {
+    "classification"            = "json"
+    "projection.enabled"        = "true"
+    "projection.tenant_id.type" = "injected"
+    "projection.dt.type"        = "date"
+    "projection.dt.format"      = "yyyy-MM-dd"
+    "projection.dt.range"       = "2024-01-01,NOW"
+    "storage.location.template" = "s3://${aws_s3_bucket.media_bucket.id}/analytics/queries/tenant_id=$${tenant_id}/dt=$${dt}/"
+  }
+
+  partition_keys {
+    name = "tenant_id"
+    type = "string"
+  }
+
+  partition_keys {
+    name = "dt"
+    type = "string"
+  }
+
+  storage_descriptor {
+    location      = "s3://${aws_s3_bucket.media_bucket.id}/analytics/queries/"
+    input_format  = "org.apache.hadoop.mapred.TextInputFormat"
+    output_format = "org.apache.hadoop.hive.ql.io.HiveIgnoreKeyTextOutputFormat"
+
+    ser_de_info {
+      serialization_library = "org.openx.data.jsonserde.JsonSerDe"
+    }
+
+    columns {
+      name = "request_id"
+      type = "string"
+    }
+    columns {
+      name = "subject"
+      type = "string"
+    }
+    columns {
+      name = "timestamp"
+      type = "string"
+    }
+    columns {
+      name = "input"
+      type = "string"
+    }
+    columns {
+      name = "topic"
+      type = "string"
+    }
+    columns {
+      name = "filters"
+      type = "map<string,string>"
+    }
+    columns {
+      name = "knowledge_bases"
+      type = "array<string>"
+    }
+    columns {
+      name = "model"
+      type = "string"
+    }
+    columns {
+      name = "latency_ms"
+      type = "bigint"
+    }
+    columns {
+      name = "cached"
+      type = "boolean"
+    }
+    columns {
+      name = "answered"
+      type = "boolean"
+    }
+    columns {
+      name = "citation_count"
+      type = "int"
+    }
+    columns {
+      name = "sources"
+      type = "array<string>"
+    }
+  }
+}
+
+resource "aws_glue_catalog_table" "feedback" {
+  name          = "feedback"
+  database_name = aws_glue_catalog_database.analytics.name
+  table_type    = "EXTERNAL_TABLE"
+
+  parameters = {
+    "classification"            = "json"
+    "projection.enabled"        = "true"
+    "projection.tenant_id.type" = "injected"
+    "projection.dt.type"        = "date"
+    "projection.dt.format"      = "yyyy-MM-dd"
+    "projection.dt.range"       = "2024-01-01,NOW"
+    "storage.location.template" = "s3://${aws_s3_bucket.media_bucket.id}/analytics/feedback/tenant_id=$${tenant_id}/dt=$${dt}/"
+  }
+
+  partition_keys {
+    name = "tenant_id"
+    type = "string"
+  }
+
+  partition_keys {
+    name = "dt"
+    type = "string"
+  }
+
+  storage_descriptor {
+    location      = "s3://${aws_s3_bucket.media_bucket.id}/analytics/feedback/"
+    input_format  = "org.apache.hadoop.mapred.TextInputFormat"
+    output_format = "org.apache.hadoop.hive.ql.io.HiveIgnoreKeyTextOutputFormat"
+
+    ser_de_info {
+      serialization_library = "org.openx.data.jsonserde.JsonSerDe"
+    }
+
+    columns {
+      name = "response_id"
+      type = "string"
+    }
+    columns {
+      name = "subject"
+      type = "string"
+    }
+    columns {
+      name = "timestamp"
+      type = "string"
+    }
+    columns {
+      name = "rating"
+      type = "string"
+    }
+    columns {
+      name = "comment"
+      type = "string"
+    }
+  }
+}
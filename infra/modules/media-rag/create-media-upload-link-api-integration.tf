@@ -7,9 +7,11 @@ resource "aws_apigatewayv2_integration" "create_media_upload_link" {
 }
 
 resource "aws_apigatewayv2_route" "create_media_upload_link" {
-  api_id    = aws_apigatewayv2_api.http_api.id
-  route_key = "POST /media"
-  target    = "integrations/${aws_apigatewayv2_integration.create_media_upload_link.id}"
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "POST /media"
+  target             = "integrations/${aws_apigatewayv2_integration.create_media_upload_link.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
 }
 
 resource "aws_lambda_permission" "create_media_upload_link" {
@@ -67,7 +67,9 @@ resource "aws_lambda_function" "create_media_upload_link" {
 
   environment {
     variables = {
-      MEDIA_BUCKET = aws_s3_bucket.media_bucket.id
+      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      REQUIRED_SCOPE = var.route_scopes.upload
+      ADMIN_GROUP    = var.admin_group
     }
   }
 }
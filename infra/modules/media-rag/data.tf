@@ -1,3 +1,7 @@
 data "aws_region" "current" {}
 data "aws_partition" "current" {}
-data "aws_caller_identity" "current" {}
\ No newline at end of file
+data "aws_caller_identity" "current" {}
+
+data "aws_cloudwatch_event_bus" "default" {
+  name = "default"
+}
@@ -0,0 +1,118 @@
+resource "aws_iam_role" "delete_media" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "delete_media" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+          "s3:DeleteObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/media-uploads/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-normalized/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-metadata/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-index/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-callbacks/*",
+          "${aws_s3_bucket.media_bucket.arn}/transcribe-output/*",
+          "${aws_s3_bucket.kb_bucket.arn}/transcripts/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = "${aws_s3_bucket.media_bucket.arn}/answer-cache/*"
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:ListBucket",
+        ]
+        Resource = [
+          aws_s3_bucket.media_bucket.arn,
+          aws_s3_bucket.kb_bucket.arn
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "sqs:SendMessage",
+        ]
+        Resource = [
+          aws_sqs_queue.ingestion.arn
+        ]
+      }
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "delete_media" {
+  role       = aws_iam_role.delete_media.name
+  policy_arn = aws_iam_policy.delete_media.arn
+}
+
+data "archive_file" "delete_media" {
+  type        = "zip"
+  source_dir  = var.delete_media_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.delete_media_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "delete_media" {
+  function_name = "${var.application}-${var.environment}-${var.delete_media_lambda.name}"
+  filename      = data.archive_file.delete_media.output_path
+  role          = aws_iam_role.delete_media.arn
+  handler       = var.delete_media_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.delete_media.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET        = aws_s3_bucket.media_bucket.id
+      KB_BUCKET           = aws_s3_bucket.kb_bucket.id
+      INGESTION_QUEUE_URL = aws_sqs_queue.ingestion.url
+      REQUIRED_SCOPE      = var.route_scopes.delete
+      ADMIN_GROUP         = var.admin_group
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "delete_media_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.delete_media.function_name}"
+  retention_in_days = "3"
+}
+
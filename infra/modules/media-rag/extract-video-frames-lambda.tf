@@ -0,0 +1,159 @@
+resource "aws_iam_role" "extract_video_frames" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "extract_video_frames" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/media-uploads/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-metadata/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.kb_bucket.arn}/transcripts/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "sqs:SendMessage",
+        ]
+        Resource = [
+          aws_sqs_queue.ingestion.arn
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:InvokeModel",
+        ]
+        Resource = ["arn:aws:bedrock:${data.aws_region.current.id}::foundation-model/*"]
+      },
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "extract_video_frames" {
+  role       = aws_iam_role.extract_video_frames.name
+  policy_arn = aws_iam_policy.extract_video_frames.arn
+}
+
+data "archive_file" "extract_video_frames" {
+  type        = "zip"
+  source_dir  = var.extract_video_frames_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.extract_video_frames_lambda.name}.zip"
+}
+
+# ffmpeg and ffprobe come from a layer so the lambda package stays small. Without a layer the
+# function is deployed but not wired to uploads.
+resource "aws_lambda_function" "extract_video_frames" {
+  function_name = "${var.application}-${var.environment}-${var.extract_video_frames_lambda.name}"
+  filename      = data.archive_file.extract_video_frames.output_path
+  role          = aws_iam_role.extract_video_frames.arn
+  handler       = var.extract_video_frames_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.extract_video_frames.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "2048"
+  architectures = ["arm64"]
+  timeout       = 900
+  layers        = compact([var.ffmpeg_layer_arn])
+
+  ephemeral_storage {
+    size = 4096
+  }
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      KB_BUCKET              = aws_s3_bucket.kb_bucket.id
+      INGESTION_QUEUE_URL    = aws_sqs_queue.ingestion.url
+      VISUAL_MODEL_ID        = var.visual_model_id
+      FRAME_INTERVAL_SECONDS = var.frame_interval_seconds
+      MAX_FRAMES             = var.max_frames
+    }
+  }
+}
+
+resource "aws_lambda_function_event_invoke_config" "extract_video_frames" {
+  function_name          = aws_lambda_function.extract_video_frames.function_name
+  maximum_retry_attempts = 0
+}
+
+resource "aws_cloudwatch_log_group" "extract_video_frames_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.extract_video_frames.function_name}"
+  retention_in_days = "3"
+}
+
+resource "aws_cloudwatch_event_rule" "extract_video_frames" {
+  count = var.ffmpeg_layer_arn == "" ? 0 : 1
+
+  name = "${var.application}-${var.environment}-extract-video-frames"
+  event_pattern = jsonencode({
+    source      = ["aws.s3"]
+    detail-type = ["Object Created"]
+    detail = {
+      bucket = {
+        name = [aws_s3_bucket.media_bucket.id]
+      }
+      object = {
+        key = [{ prefix = "media-uploads/" }]
+      }
+    }
+  })
+}
+
+resource "aws_cloudwatch_event_target" "extract_video_frames" {
+  count = var.ffmpeg_layer_arn == "" ? 0 : 1
+
+  rule      = aws_cloudwatch_event_rule.extract_video_frames[0].name
+  target_id = "extractVideoFrames"
+  arn       = aws_lambda_function.extract_video_frames.arn
+}
+
+resource "aws_lambda_permission" "extract_video_frames" {
+  count = var.ffmpeg_layer_arn == "" ? 0 : 1
+
+  statement_id  = "AllowEventBridgeInvoke"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.extract_video_frames.function_name
+  principal     = "events.amazonaws.com"
+  source_arn    = aws_cloudwatch_event_rule.extract_video_frames[0].arn
+}
@@ -50,7 +50,9 @@ resource "aws_iam_policy" "handle_successful_transcription" {
           "s3:PutObject",
         ]
         Resource = [
-          "${aws_s3_bucket.kb_bucket.arn}/*"
+          "${aws_s3_bucket.kb_bucket.arn}/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-index/*",
+          "${aws_s3_bucket.media_bucket.arn}/ingestion-jobs/*"
         ]
       },
       {
@@ -65,12 +67,35 @@ resource "aws_iam_policy" "handle_successful_transcription" {
       {
         Effect = "Allow"
         Action = [
-          "bedrock:StartIngestionJob",
+          "sqs:SendMessage",
+        ]
+        Resource = [
+          aws_sqs_queue.ingestion.arn
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:IngestKnowledgeBaseDocuments",
         ]
         Resource = [
           aws_bedrockagent_knowledge_base.this.arn
         ]
       },
+      {
+        Effect = "Allow"
+        Action = [
+          "events:PutEvents",
+        ]
+        Resource = [data.aws_cloudwatch_event_bus.default.arn]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:InvokeModel",
+        ]
+        Resource = ["arn:aws:bedrock:${data.aws_region.current.id}::foundation-model/*"]
+      },
       {
         Effect = "Allow"
         Action = [
@@ -116,10 +141,15 @@ resource "aws_lambda_function" "handle_successful_transcription" {
 
   environment {
     variables = {
-      KB_BUCKET      = aws_s3_bucket.kb_bucket.id
-      KB_ID          = aws_bedrockagent_knowledge_base.this.id
-      DATA_SOURCE_ID = aws_bedrockagent_data_source.this.data_source_id
-      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      KB_BUCKET           = aws_s3_bucket.kb_bucket.id
+      INGESTION_MODE      = var.ingestion_mode
+      INGESTION_QUEUE_URL = aws_sqs_queue.ingestion.url
+      KB_ID               = aws_bedrockagent_knowledge_base.this.id
+      DATA_SOURCE_ID      = aws_bedrockagent_data_source.this.data_source_id
+      MEDIA_BUCKET        = aws_s3_bucket.media_bucket.id
+      EVENT_BUS_NAME      = data.aws_cloudwatch_event_bus.default.name
+      TRANSCRIPT_CLEANING = jsonencode(var.transcript_cleaning)
+      ENRICHMENT_MODEL_ID = var.enrichment_model_id
     }
   }
 }
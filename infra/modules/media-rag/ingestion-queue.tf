@@ -0,0 +1,22 @@
+resource "aws_sqs_queue" "ingestion_dlq" {
+  name = "${var.application}-${var.environment}-ingestion-dlq"
+}
+
+resource "aws_sqs_queue" "ingestion" {
+  name = "${var.application}-${var.environment}-ingestion"
+
+  visibility_timeout_seconds = 6 * aws_lambda_function.start_ingestion_job.timeout
+
+  redrive_policy = jsonencode({
+    deadLetterTargetArn = aws_sqs_queue.ingestion_dlq.arn
+    maxReceiveCount     = 5
+  })
+}
+
+resource "aws_lambda_event_source_mapping" "ingestion" {
+  event_source_arn                   = aws_sqs_queue.ingestion.arn
+  function_name                      = aws_lambda_function.start_ingestion_job.arn
+  batch_size                         = 100
+  maximum_batching_window_in_seconds = 60
+  function_response_types            = ["ReportBatchItemFailures"]
+}
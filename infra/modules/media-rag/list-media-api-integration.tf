@@ -0,0 +1,31 @@
+resource "aws_apigatewayv2_integration" "list_media" {
+  api_id                 = aws_apigatewayv2_api.http_api.id
+  integration_type       = "AWS_PROXY"
+  integration_uri        = aws_lambda_function.list_media.invoke_arn
+  integration_method     = "POST"
+  payload_format_version = "2.0"
+}
+
+resource "aws_apigatewayv2_route" "list_media" {
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "GET /media"
+  target             = "integrations/${aws_apigatewayv2_integration.list_media.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
+}
+
+resource "aws_apigatewayv2_route" "get_media" {
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "GET /media/{task_id}"
+  target             = "integrations/${aws_apigatewayv2_integration.list_media.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
+}
+
+resource "aws_lambda_permission" "list_media" {
+  statement_id  = "AllowAPIGatewaySample"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.list_media.arn
+  principal     = "apigateway.amazonaws.com"
+  source_arn    = "${aws_apigatewayv2_api.http_api.execution_arn}/*/*"
+}
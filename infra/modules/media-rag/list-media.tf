@@ -0,0 +1,88 @@
+resource "aws_iam_role" "list_media" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "list_media" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+        ]
+        Resource = "${aws_s3_bucket.media_bucket.arn}/media-index/*"
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:ListBucket",
+        ]
+        Resource = aws_s3_bucket.media_bucket.arn
+      }
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "list_media" {
+  role       = aws_iam_role.list_media.name
+  policy_arn = aws_iam_policy.list_media.arn
+}
+
+data "archive_file" "list_media" {
+  type        = "zip"
+  source_dir  = var.list_media_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.list_media_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "list_media" {
+  function_name = "${var.application}-${var.environment}-${var.list_media_lambda.name}"
+  filename      = data.archive_file.list_media.output_path
+  role          = aws_iam_role.list_media.arn
+  handler       = var.list_media_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.list_media.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      REQUIRED_SCOPE = var.route_scopes.list
+      ADMIN_GROUP    = var.admin_group
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "list_media_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.list_media.function_name}"
+  retention_in_days = "3"
+}
+
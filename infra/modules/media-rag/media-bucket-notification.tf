@@ -5,14 +5,36 @@ resource "aws_lambda_permission" "allow_bucket" {
   source_arn    = aws_s3_bucket.media_bucket.arn
 }
 
+resource "aws_lambda_permission" "allow_bucket_normalize_media" {
+  count = var.normalize_media ? 1 : 0
+
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.normalize_media.arn
+  principal     = "s3.amazonaws.com"
+  source_arn    = aws_s3_bucket.media_bucket.arn
+}
+
 resource "aws_s3_bucket_notification" "bucket_notification" {
   bucket = aws_s3_bucket.media_bucket.id
 
+  # Also sends the bucket events to EventBridge, where the video frame extraction picks up uploads.
+  eventbridge = true
+
+  # With normalization, uploads go through normalize-media and its audio tracks are transcribed.
+  dynamic "lambda_function" {
+    for_each = var.normalize_media ? [1] : []
+    content {
+      lambda_function_arn = aws_lambda_function.normalize_media.arn
+      events              = ["s3:ObjectCreated:*"]
+      filter_prefix       = "media-uploads/"
+    }
+  }
+
   lambda_function {
     lambda_function_arn = aws_lambda_function.start_transcription_job.arn
     events = ["s3:ObjectCreated:*"]
-    filter_prefix       = "media-uploads/"
+    filter_prefix       = var.normalize_media ? "media-normalized/" : "media-uploads/"
   }
 
-  depends_on = [aws_lambda_permission.allow_bucket]
+  depends_on = [aws_lambda_permission.allow_bucket, aws_lambda_permission.allow_bucket_normalize_media]
 }
\ No newline at end of file
@@ -2,6 +2,37 @@ resource "aws_s3_bucket" "media_bucket" {
   bucket = "${var.application}-${var.environment}-medias-${random_pet.this.id}"
 }
 
+resource "aws_s3_bucket_lifecycle_configuration" "media_bucket" {
+  bucket = aws_s3_bucket.media_bucket.id
+
+  rule {
+    id     = "expire-transcribe-output"
+    status = "Enabled"
+
+    filter {
+      prefix = "transcribe-output/"
+    }
+
+    expiration {
+      days = var.transcribe_output_retention_days
+    }
+  }
+
+  # Cached answers of older topic versions are never read again.
+  rule {
+    id     = "expire-answer-cache"
+    status = "Enabled"
+
+    filter {
+      prefix = "answer-cache/"
+    }
+
+    expiration {
+      days = var.answer_cache_retention_days
+    }
+  }
+}
+
 resource "aws_s3_bucket_policy" "allow_transcribe" {
   bucket = aws_s3_bucket.media_bucket.id
   policy = data.aws_iam_policy_document.media_bucket.json
@@ -0,0 +1,106 @@
+resource "aws_iam_role" "normalize_media" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "normalize_media" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/media-uploads/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-metadata/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/media-normalized/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-index/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "events:PutEvents",
+        ]
+        Resource = [data.aws_cloudwatch_event_bus.default.arn]
+      },
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "normalize_media" {
+  role       = aws_iam_role.normalize_media.name
+  policy_arn = aws_iam_policy.normalize_media.arn
+}
+
+data "archive_file" "normalize_media" {
+  type        = "zip"
+  source_dir  = var.normalize_media_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.normalize_media_lambda.name}.zip"
+}
+
+# Only wired to uploads when var.normalize_media is set, see media-bucket-notification.tf.
+resource "aws_lambda_function" "normalize_media" {
+  function_name = "${var.application}-${var.environment}-${var.normalize_media_lambda.name}"
+  filename      = data.archive_file.normalize_media.output_path
+  role          = aws_iam_role.normalize_media.arn
+  handler       = var.normalize_media_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.normalize_media.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "3008"
+  architectures = ["arm64"]
+  timeout       = 900
+  layers        = compact([var.ffmpeg_layer_arn])
+
+  ephemeral_storage {
+    size = 10240
+  }
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      EVENT_BUS_NAME       = data.aws_cloudwatch_event_bus.default.name
+      MAX_DURATION_SECONDS = var.max_media_duration_seconds
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "normalize_media_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.normalize_media.function_name}"
+  retention_in_days = "3"
+}
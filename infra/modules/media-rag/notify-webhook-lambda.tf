@@ -0,0 +1,116 @@
+resource "aws_iam_role" "notify_webhook" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "notify_webhook" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/media-callbacks/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:ListBucket",
+        ]
+        Resource = aws_s3_bucket.media_bucket.arn
+      },
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "notify_webhook" {
+  role       = aws_iam_role.notify_webhook.name
+  policy_arn = aws_iam_policy.notify_webhook.arn
+}
+
+data "archive_file" "notify_webhook" {
+  type        = "zip"
+  source_dir  = var.notify_webhook_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.notify_webhook_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "notify_webhook" {
+  function_name = "${var.application}-${var.environment}-${var.notify_webhook_lambda.name}"
+  filename      = data.archive_file.notify_webhook.output_path
+  role          = aws_iam_role.notify_webhook.arn
+  handler       = var.notify_webhook_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.notify_webhook.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+  timeout       = 90
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET = aws_s3_bucket.media_bucket.id
+    }
+  }
+}
+
+resource "aws_lambda_function_event_invoke_config" "notify_webhook" {
+  function_name                = aws_lambda_function.notify_webhook.function_name
+  maximum_retry_attempts       = 2
+  maximum_event_age_in_seconds = 3600
+}
+
+resource "aws_cloudwatch_log_group" "notify_webhook_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.notify_webhook.function_name}"
+  retention_in_days = "3"
+}
+
+resource "aws_cloudwatch_event_rule" "notify_webhook" {
+  name = "${var.application}-${var.environment}-notify-webhook"
+  event_pattern = jsonencode({
+    source      = ["media-rag"]
+    detail-type = ["Task Status Changed"]
+  })
+}
+
+resource "aws_cloudwatch_event_target" "notify_webhook" {
+  rule      = aws_cloudwatch_event_rule.notify_webhook.name
+  target_id = "notifyWebhook"
+  arn       = aws_lambda_function.notify_webhook.arn
+}
+
+resource "aws_lambda_permission" "notify_webhook" {
+  statement_id  = "AllowEventBridgeInvoke"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.notify_webhook.function_name
+  principal     = "events.amazonaws.com"
+  source_arn    = aws_cloudwatch_event_rule.notify_webhook.arn
+}
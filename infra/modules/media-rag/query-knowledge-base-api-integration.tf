@@ -8,9 +8,11 @@ resource "aws_apigatewayv2_integration" "query_knowledge_base" {
 
 
 resource "aws_apigatewayv2_route" "query_knowledge_base" {
-  api_id    = aws_apigatewayv2_api.http_api.id
-  route_key = "POST /query"
-  target    = "integrations/${aws_apigatewayv2_integration.query_knowledge_base.id}"
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "POST /query"
+  target             = "integrations/${aws_apigatewayv2_integration.query_knowledge_base.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
 }
 
 resource "aws_lambda_permission" "query_knowledge_base" {
@@ -38,7 +38,13 @@ resource "aws_iam_policy" "query_knowledge_base" {
           "bedrock:Retrieve",
           "bedrock:RetrieveAndGenerate"
         ]
-        Resource = [aws_bedrockagent_knowledge_base.this.arn]
+        Resource = concat(
+          [aws_bedrockagent_knowledge_base.this.arn],
+          [
+            for id in values(var.knowledge_bases) :
+            "arn:${data.aws_partition.current.partition}:bedrock:${data.aws_region.current.id}:${data.aws_caller_identity.current.account_id}:knowledge-base/${id}"
+          ]
+        )
       },
 
 
@@ -48,6 +54,18 @@ resource "aws_iam_policy" "query_knowledge_base" {
           "bedrock:InvokeModel",
         ]
         Resource = [local.model_id]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+          "s3:PutObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/quotas/*",
+          "${aws_s3_bucket.media_bucket.arn}/answer-cache/*",
+          "${aws_s3_bucket.media_bucket.arn}/analytics/queries/*"
+        ]
       }
 
     ]
@@ -84,9 +102,20 @@ resource "aws_lambda_function" "query_knowledge_base" {
 
   environment {
     variables = {
-      KB_BUCKET = aws_s3_bucket.kb_bucket.id
-      KB_ID     = aws_bedrockagent_knowledge_base.this.id
-      MODEL_ARN = local.model_id
+      KB_BUCKET            = aws_s3_bucket.kb_bucket.id
+      MEDIA_BUCKET         = aws_s3_bucket.media_bucket.id
+      KB_ID                = aws_bedrockagent_knowledge_base.this.id
+      KB_NAME              = var.knowledge_base_name
+      KNOWLEDGE_BASES      = jsonencode(var.knowledge_bases)
+      MAX_PASSAGES         = var.max_passages
+      MODEL_ARN            = local.model_id
+      REQUIRED_SCOPE       = var.route_scopes.query
+      ADMIN_GROUP          = var.admin_group
+      QUERY_BURST          = var.query_burst
+      QUERIES_PER_MINUTE   = var.queries_per_minute
+      MONTHLY_TOKEN_BUDGET = var.monthly_token_budget == null ? "" : var.monthly_token_budget
+      NUMBER_OF_RESULTS    = var.number_of_results == null ? "" : var.number_of_results
+      PROMPT_TEMPLATE      = var.prompt_template == null ? "" : var.prompt_template
 
     }
   }
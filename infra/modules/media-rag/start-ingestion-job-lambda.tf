@@ -0,0 +1,117 @@
+resource "aws_iam_role" "start_ingestion_job" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "start_ingestion_job" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "sqs:ReceiveMessage",
+          "sqs:DeleteMessage",
+          "sqs:GetQueueAttributes",
+        ]
+        Resource = [aws_sqs_queue.ingestion.arn]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:StartIngestionJob",
+          "bedrock:ListIngestionJobs",
+        ]
+        Resource = [
+          aws_bedrockagent_knowledge_base.this.arn
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:AssociateThirdPartyKnowledgeBase",
+        ]
+        Resource = "*"
+        "Condition" = {
+          "StringEquals" = {
+            "bedrock:ThirdPartyKnowledgeBaseCredentialsSecretArn" : aws_secretsmanager_secret.pinecone_api_key.arn
+          }
+        }
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/ingestion-jobs/*"
+        ]
+      },
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "start_ingestion_job" {
+  role       = aws_iam_role.start_ingestion_job.name
+  policy_arn = aws_iam_policy.start_ingestion_job.arn
+}
+
+data "archive_file" "start_ingestion_job" {
+  type        = "zip"
+  source_dir  = var.start_ingestion_job_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.start_ingestion_job_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "start_ingestion_job" {
+  function_name = "${var.application}-${var.environment}-${var.start_ingestion_job_lambda.name}"
+  filename      = data.archive_file.start_ingestion_job.output_path
+  role          = aws_iam_role.start_ingestion_job.arn
+  handler       = var.start_ingestion_job_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.start_ingestion_job.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+  timeout       = 900
+
+  # A single concurrent execution keeps at most one ingestion job in flight.
+  reserved_concurrent_executions = 1
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      KB_ID          = aws_bedrockagent_knowledge_base.this.id
+      DATA_SOURCE_ID = aws_bedrockagent_data_source.this.data_source_id
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "start_ingestion_job_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.start_ingestion_job.function_name}"
+  retention_in_days = "3"
+}
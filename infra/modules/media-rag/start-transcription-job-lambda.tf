@@ -44,6 +44,16 @@ resource "aws_iam_policy" "start_transcription_job" {
           aws_s3_bucket.media_bucket.arn,
           "${aws_s3_bucket.media_bucket.arn}/*"
         ]
+      },
+      {
+        # Transcribe writes the job output with the caller's permissions.
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/transcribe-output/*"
+        ]
       }
     ]
   })
@@ -0,0 +1,23 @@
+resource "aws_apigatewayv2_integration" "submit_feedback" {
+  api_id                 = aws_apigatewayv2_api.http_api.id
+  integration_type       = "AWS_PROXY"
+  integration_uri        = aws_lambda_function.submit_feedback.invoke_arn
+  integration_method     = "POST"
+  payload_format_version = "2.0"
+}
+
+resource "aws_apigatewayv2_route" "submit_feedback" {
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "POST /feedback"
+  target             = "integrations/${aws_apigatewayv2_integration.submit_feedback.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
+}
+
+resource "aws_lambda_permission" "submit_feedback" {
+  statement_id  = "AllowAPIGatewaySample"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.submit_feedback.arn
+  principal     = "apigateway.amazonaws.com"
+  source_arn    = "${aws_apigatewayv2_api.http_api.execution_arn}/*/*"
+}
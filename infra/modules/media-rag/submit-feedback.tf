@@ -0,0 +1,81 @@
+resource "aws_iam_role" "submit_feedback" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "submit_feedback" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:PutObject",
+        ]
+        Resource = "${aws_s3_bucket.media_bucket.arn}/analytics/feedback/*"
+      }
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "submit_feedback" {
+  role       = aws_iam_role.submit_feedback.name
+  policy_arn = aws_iam_policy.submit_feedback.arn
+}
+
+data "archive_file" "submit_feedback" {
+  type        = "zip"
+  source_dir  = var.submit_feedback_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.submit_feedback_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "submit_feedback" {
+  function_name = "${var.application}-${var.environment}-${var.submit_feedback_lambda.name}"
+  filename      = data.archive_file.submit_feedback.output_path
+  role          = aws_iam_role.submit_feedback.arn
+  handler       = var.submit_feedback_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.submit_feedback.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      REQUIRED_SCOPE = var.route_scopes.feedback
+      ADMIN_GROUP    = var.admin_group
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "submit_feedback_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.submit_feedback.function_name}"
+  retention_in_days = "3"
+}
+
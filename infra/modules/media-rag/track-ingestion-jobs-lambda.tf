@@ -0,0 +1,134 @@
+resource "aws_iam_role" "track_ingestion_jobs" {
+  assume_role_policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Action = "sts:AssumeRole"
+        Effect = "Allow"
+        Sid    = ""
+        Principal = {
+          Service = "lambda.amazonaws.com"
+        }
+      },
+    ]
+  })
+}
+
+resource "aws_iam_policy" "track_ingestion_jobs" {
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect = "Allow"
+        Action = [
+          "logs:CreateLogGroup",
+          "logs:CreateLogStream",
+          "logs:PutLogEvents"
+        ]
+        Resource = ["arn:aws:logs:*:*:*"]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "bedrock:GetIngestionJob",
+          "bedrock:GetKnowledgeBaseDocuments",
+        ]
+        Resource = [
+          aws_bedrockagent_knowledge_base.this.arn
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:GetObject",
+          "s3:PutObject",
+          "s3:DeleteObject",
+        ]
+        Resource = [
+          "${aws_s3_bucket.media_bucket.arn}/ingestion-jobs/*",
+          "${aws_s3_bucket.media_bucket.arn}/media-index/*",
+          "${aws_s3_bucket.media_bucket.arn}/answer-cache/*"
+        ]
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "s3:ListBucket",
+        ]
+        Resource = aws_s3_bucket.media_bucket.arn
+      },
+      {
+        Effect = "Allow"
+        Action = [
+          "events:PutEvents",
+        ]
+        Resource = [data.aws_cloudwatch_event_bus.default.arn]
+      },
+    ]
+  })
+}
+
+resource "aws_iam_role_policy_attachment" "track_ingestion_jobs" {
+  role       = aws_iam_role.track_ingestion_jobs.name
+  policy_arn = aws_iam_policy.track_ingestion_jobs.arn
+}
+
+data "archive_file" "track_ingestion_jobs" {
+  type        = "zip"
+  source_dir  = var.track_ingestion_jobs_lambda.dist_dir
+  output_path = "${path.root}/.terraform/tmp/lambda-zips/${var.track_ingestion_jobs_lambda.name}.zip"
+}
+
+resource "aws_lambda_function" "track_ingestion_jobs" {
+  function_name = "${var.application}-${var.environment}-${var.track_ingestion_jobs_lambda.name}"
+  filename      = data.archive_file.track_ingestion_jobs.output_path
+  role          = aws_iam_role.track_ingestion_jobs.arn
+  handler       = var.track_ingestion_jobs_lambda.handler
+  source_code_hash = filebase64sha256(data.archive_file.track_ingestion_jobs.output_path)
+  runtime       = "provided.al2023"
+  memory_size   = "128"
+  architectures = ["arm64"]
+  timeout       = 300
+
+  reserved_concurrent_executions = 1
+
+  logging_config {
+    system_log_level      = "WARN"
+    application_log_level = "INFO"
+    log_format            = "JSON"
+  }
+
+  environment {
+    variables = {
+      MEDIA_BUCKET   = aws_s3_bucket.media_bucket.id
+      KB_BUCKET      = aws_s3_bucket.kb_bucket.id
+      KB_ID          = aws_bedrockagent_knowledge_base.this.id
+      DATA_SOURCE_ID = aws_bedrockagent_data_source.this.data_source_id
+      EVENT_BUS_NAME = data.aws_cloudwatch_event_bus.default.name
+    }
+  }
+}
+
+resource "aws_cloudwatch_log_group" "track_ingestion_jobs_log_group" {
+  name              = "/aws/lambda/${aws_lambda_function.track_ingestion_jobs.function_name}"
+  retention_in_days = "3"
+}
+
+resource "aws_cloudwatch_event_rule" "track_ingestion_jobs" {
+  name                = "${var.application}-${var.environment}-track-ingestion-jobs"
+  schedule_expression = "rate(2 minutes)"
+}
+
+resource "aws_cloudwatch_event_target" "track_ingestion_jobs" {
+  rule      = aws_cloudwatch_event_rule.track_ingestion_jobs.name
+  target_id = "trackIngestionJobs"
+  arn       = aws_lambda_function.track_ingestion_jobs.arn
+}
+
+resource "aws_lambda_permission" "track_ingestion_jobs" {
+  statement_id  = "AllowEventBridgeInvoke"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.track_ingestion_jobs.function_name
+  principal     = "events.amazonaws.com"
+  source_arn    = aws_cloudwatch_event_rule.track_ingestion_jobs.arn
+}
@@ -7,7 +7,7 @@ resource "aws_lambda_permission" "allow_eventbridge_invoke" {
   action        = "lambda:InvokeFunction"
   function_name = aws_lambda_function.handle_successful_transcription.function_name
   principal     = "events.amazonaws.com"
-  source_arn    = aws_cloudwatch_event_rule.transcription_success.arn
+  source_arn    = aws_cloudwatch_event_rule.transcription_state_change.arn
 }
 
 resource "aws_iam_role" "eb_role" {
@@ -52,19 +52,19 @@ resource "aws_iam_role_policy_attachment" "eb_role_attachment" {
   policy_arn = aws_iam_policy.eb_policy.arn
 }
 
-resource "aws_cloudwatch_event_rule" "transcription_success" {
-  name = "transcription-success"
+resource "aws_cloudwatch_event_rule" "transcription_state_change" {
+  name = "transcription-state-change"
 
   event_pattern = jsonencode({
     "source" : ["aws.transcribe"], "detail" : {
-      "TranscriptionJobStatus" : ["COMPLETED"]
+      "TranscriptionJobStatus" : ["COMPLETED", "FAILED"]
     }
   })
 }
 
-resource "aws_cloudwatch_event_target" "transcription_success" {
-  rule      = aws_cloudwatch_event_rule.transcription_success.name
-  target_id = "handleTranscriptionSuccess"
+resource "aws_cloudwatch_event_target" "transcription_state_change" {
+  rule      = aws_cloudwatch_event_rule.transcription_state_change.name
+  target_id = "handleTranscriptionStateChange"
   arn       = aws_lambda_function.handle_successful_transcription.arn
   dead_letter_config {
     arn = aws_sqs_queue.transcription_dlq.arn
@@ -74,35 +74,6 @@ resource "aws_cloudwatch_event_target" "transcription_success" {
     maximum_event_age_in_seconds = 60 * 60
     maximum_retry_attempts       = 10
   }
-
-  input_transformer {
-    input_paths = {
-      transcriptionJob : "$.detail.TranscriptionJobName"
-    }
-
-    input_template = <<TEMPLATE
-{
-  "transcriptionJob":"<transcriptionJob>"
-}
-TEMPLATE
-  }
-}
-
-
-resource "aws_cloudwatch_event_rule" "transcription_failure" {
-  name = "transcription-failure"
-
-  event_pattern = jsonencode({
-    "source" : ["aws.transcribe"], "detail" : {
-      "TranscriptionJobStatus" : ["FAILED"]
-    }
-  })
-}
-
-resource "aws_cloudwatch_event_target" "dlq" {
-  rule      = aws_cloudwatch_event_rule.transcription_failure.name
-  target_id = "transcriptionErrorsDlq"
-  arn       = aws_sqs_queue.transcription_dlq.arn
 }
 
 resource "aws_iam_role" "kb_sync" {
@@ -135,7 +106,7 @@ resource "aws_sqs_queue_policy" "queue_policy" {
         Resource = aws_sqs_queue.transcription_dlq.arn
         Condition = {
           ArnEquals = {
-            "aws:SourceArn" : aws_cloudwatch_event_rule.transcription_failure.arn
+            "aws:SourceArn" : aws_cloudwatch_event_rule.transcription_state_change.arn
           }
         }
       }
@@ -0,0 +1,23 @@
+resource "aws_apigatewayv2_integration" "update_media_metadata" {
+  api_id                 = aws_apigatewayv2_api.http_api.id
+  integration_type       = "AWS_PROXY"
+  integration_uri        = aws_lambda_function.update_media_metadata.invoke_arn
+  integration_method     = "POST"
+  payload_format_version = "2.0"
+}
+
+resource "aws_apigatewayv2_route" "update_media_metadata" {
+  api_id             = aws_apigatewayv2_api.http_api.id
+  route_key          = "PATCH /media/{task_id}"
+  target             = "integrations/${aws_apigatewayv2_integration.update_media_metadata.id}"
+  authorization_type = "JWT"
+  authorizer_id      = aws_apigatewayv2_authorizer.jwt.id
+}
+
+resource "aws_lambda_permission" "update_media_metadata" {
+  statement_id  = "AllowAPIGatewaySample"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.update_media_metadata.arn
+  principal     = "apigateway.amazonaws.com"
+  source_arn    = "${aws_apigatewayv2_api.http_api.execution_arn}/*/*"
+}
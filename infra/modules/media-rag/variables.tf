@@ -15,6 +15,46 @@ variable "handle_successful_transcription_lambda" {
   })
 }
 
+variable "start_ingestion_job_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "track_ingestion_jobs_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "normalize_media_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "extract_video_frames_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "notify_webhook_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
 variable "create_media_upload_link_lambda" {
   type = object({
     dist_dir = string
@@ -31,6 +71,38 @@ variable "query_knowledge_base_lambda" {
   })
 }
 
+variable "update_media_metadata_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "submit_feedback_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "delete_media_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
+variable "list_media_lambda" {
+  type = object({
+    dist_dir = string
+    name     = string
+    handler  = string
+  })
+}
+
 variable "application" {
   type = string
 }
@@ -41,4 +113,173 @@ variable "environment" {
 
 variable "pinecone_api_key" {
   type = string
-}
\ No newline at end of file
+}
+
+variable "ingestion_mode" {
+  type        = string
+  default     = "sync"
+  description = "sync re-syncs the whole data source through the ingestion queue, documents pushes each new transcript with IngestKnowledgeBaseDocuments"
+
+  validation {
+    condition     = contains(["sync", "documents"], var.ingestion_mode)
+    error_message = "ingestion_mode must be sync or documents."
+  }
+}
+
+variable "transcribe_output_retention_days" {
+  type        = number
+  default     = 30
+  description = "Days the raw Transcribe output is kept under transcribe-output/ in the media bucket"
+}
+
+variable "transcript_cleaning" {
+  type = object({
+    minConfidence          = optional(number)
+    lowConfidence          = optional(string)
+    removeFillers          = optional(bool)
+    mergeTurnsUnderSeconds = optional(number)
+    normalizePunctuation   = optional(bool)
+  })
+  default     = {}
+  description = "Transcript cleaning applied before ingestion, uploads can override single options"
+
+  validation {
+    condition     = contains(["mark", "drop"], coalesce(var.transcript_cleaning.lowConfidence, "mark"))
+    error_message = "transcript_cleaning.lowConfidence must be mark or drop."
+  }
+}
+
+variable "enrichment_model_id" {
+  type        = string
+  default     = "anthropic.claude-3-haiku-20240307-v1:0"
+  description = "Bedrock model generating transcript summaries, chapters and keywords, empty to disable"
+}
+
+variable "ffmpeg_layer_arn" {
+  type        = string
+  default     = ""
+  description = "Lambda layer with ffmpeg and ffprobe under /opt/bin, empty to disable video frame extraction"
+}
+
+variable "normalize_media" {
+  type        = bool
+  default     = false
+  description = "Extract the audio track of uploads before transcription and reject unusable files, requires ffmpeg_layer_arn"
+
+  validation {
+    condition     = !var.normalize_media || var.ffmpeg_layer_arn != ""
+    error_message = "normalize_media requires ffmpeg_layer_arn."
+  }
+}
+
+variable "max_media_duration_seconds" {
+  type        = number
+  default     = 14400
+  description = "Longer uploads are rejected by the normalization, Transcribe accepts up to 4 hours"
+}
+
+variable "visual_model_id" {
+  type        = string
+  default     = "anthropic.claude-3-haiku-20240307-v1:0"
+  description = "Bedrock model with image input describing the on-screen content of video frames"
+}
+
+variable "frame_interval_seconds" {
+  type        = number
+  default     = 30
+  description = "Seconds between two video frames sent to the visual model"
+}
+
+variable "max_frames" {
+  type        = number
+  default     = 60
+  description = "Video frames described per upload at most"
+}
+
+variable "jwt_issuer" {
+  type        = string
+  description = "Issuer of the tokens accepted by the API, e.g. https://cognito-idp.<region>.amazonaws.com/<user pool id>. Tokens carry the caller's tenant in a custom:tenant_id or tenant_id claim"
+}
+
+variable "jwt_audience" {
+  type        = list(string)
+  description = "Accepted token audiences, the app client ids for a Cognito user pool"
+}
+
+variable "admin_group" {
+  type        = string
+  default     = "admin"
+  description = "Members of this group (cognito:groups claim) manage every task of their tenant, other users only their own uploads"
+}
+
+variable "route_scopes" {
+  type = object({
+    upload   = optional(string, "")
+    update   = optional(string, "")
+    delete   = optional(string, "")
+    list     = optional(string, "")
+    query    = optional(string, "")
+    feedback = optional(string, "")
+  })
+  default     = {}
+  description = "Scope the access token must grant per route, e.g. media:write for upload. Empty accepts any token of the audience"
+}
+
+variable "query_burst" {
+  type        = number
+  default     = 10
+  description = "Queries a caller can send in a burst before being rate limited"
+}
+
+variable "queries_per_minute" {
+  type        = number
+  default     = 30
+  description = "Rate at which the query burst of a caller is refilled, 0 disables the rate limit"
+
+  validation {
+    condition     = var.queries_per_minute >= 0
+    error_message = "queries_per_minute must not be negative."
+  }
+}
+
+variable "monthly_token_budget" {
+  type        = number
+  default     = null
+  description = "Estimated model tokens a caller can spend per calendar month, unlimited when null"
+}
+
+variable "knowledge_base_name" {
+  type        = string
+  default     = "media"
+  description = "Name queries use for the media knowledge base"
+}
+
+variable "knowledge_bases" {
+  type        = map(string)
+  default     = {}
+  description = "Other knowledge bases queries can target, ids by name"
+}
+
+variable "max_passages" {
+  type        = number
+  default     = 10
+  description = "Passages an answer over several knowledge bases is generated from"
+}
+
+variable "number_of_results" {
+  type        = number
+  default     = null
+  description = "Passages retrieved per query, the Bedrock default when null"
+}
+
+variable "prompt_template" {
+  type        = string
+  default     = null
+  description = "Answer generation prompt with the $search_results$ placeholder, the Bedrock default when null"
+}
+
+variable "answer_cache_retention_days" {
+  type        = number
+  default     = 7
+  description = "Days cached query answers are kept under answer-cache/ in the media bucket"
+}
@@ -14,3 +14,129 @@ variable "pinecone_api_key" {
 
 
 
+
+
+variable "ingestion_mode" {
+  type    = string
+  default = "sync"
+}
+
+variable "transcribe_output_retention_days" {
+  type    = number
+  default = 30
+}
+
+variable "transcript_cleaning" {
+  type = object({
+    minConfidence          = optional(number)
+    lowConfidence          = optional(string)
+    removeFillers          = optional(bool)
+    mergeTurnsUnderSeconds = optional(number)
+    normalizePunctuation   = optional(bool)
+  })
+  default = {}
+}
+
+variable "enrichment_model_id" {
+  type    = string
+  default = "anthropic.claude-3-haiku-20240307-v1:0"
+}
+
+variable "ffmpeg_layer_arn" {
+  type    = string
+  default = ""
+}
+
+variable "normalize_media" {
+  type    = bool
+  default = false
+}
+
+variable "max_media_duration_seconds" {
+  type    = number
+  default = 14400
+}
+
+variable "visual_model_id" {
+  type    = string
+  default = "anthropic.claude-3-haiku-20240307-v1:0"
+}
+
+variable "frame_interval_seconds" {
+  type    = number
+  default = 30
+}
+
+variable "max_frames" {
+  type    = number
+  default = 60
+}
+
+variable "jwt_issuer" {
+  type = string
+}
+
+variable "jwt_audience" {
+  type = list(string)
+}
+
+variable "admin_group" {
+  type    = string
+  default = "admin"
+}
+
+variable "route_scopes" {
+  type = object({
+    upload   = optional(string, "")
+    update   = optional(string, "")
+    list     = optional(string, "")
+    query    = optional(string, "")
+    feedback = optional(string, "")
+  })
+  default = {}
+}
+
+variable "query_burst" {
+  type    = number
+  default = 10
+}
+
+variable "queries_per_minute" {
+  type    = number
+  default = 30
+}
+
+variable "monthly_token_budget" {
+  type    = number
+  default = null
+}
+
+variable "knowledge_base_name" {
+  type    = string
+  default = "media"
+}
+
+variable "knowledge_bases" {
+  type    = map(string)
+  default = {}
+}
+
+variable "max_passages" {
+  type    = number
+  default = 10
+}
+
+variable "number_of_results" {
+  type    = number
+  default = null
+}
+
+variable "prompt_template" {
+  type    = string
+  default = null
+}
+
+variable "answer_cache_retention_days" {
+  type    = number
+  default = 7
+}
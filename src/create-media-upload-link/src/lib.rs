@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use lambda_http::{Body, Error, Request, Response};
+use nanoid::nanoid;
+use serde_json::json;
+use serde_valid::Validate;
+
+use shared::auth::{authorize, AccessPolicy};
+use shared::error::ApiError;
+use shared::models::{media_upload_key, staging_metadata_key, MediaMetadata};
+use shared::storage::{put_json, ObjectStorage};
+use shared::validation::parse_json;
+use shared::webhook::webhook_callback_key;
+
+pub async fn create_media_upload_link(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, Error> {
+    create_upload_link(event, storage, media_bucket_name, policy)
+        .await
+        .or_else(ApiError::into_response)
+}
+
+async fn create_upload_link(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, ApiError> {
+    let caller = authorize(&event, policy)?;
+    let tenant_id = caller.tenant_id;
+
+    let mut request: MediaMetadata = parse_json(event.body())?;
+
+    request.validate()?;
+
+    request.owner = Some(caller.subject);
+
+    let task_id = nanoid!();
+
+    put_json(
+        storage,
+        media_bucket_name,
+        &staging_metadata_key(&tenant_id, &task_id),
+        &request,
+    )
+        .await?;
+
+    if let Some(callback) = request.webhook() {
+        put_json(
+            storage,
+            media_bucket_name,
+            &webhook_callback_key(&task_id),
+            &callback,
+        )
+            .await?;
+    }
+
+    let presigned_request_uri = storage
+        .presign_put(
+            media_bucket_name,
+            &media_upload_key(&tenant_id, &task_id),
+            &task_id,
+            Duration::from_secs(15 * 60),
+        )
+        .await?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(
+            json!({
+                "upload_url": presigned_request_uri,
+                "task_id":  task_id
+            })
+                .to_string()
+                .into(),
+        )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use shared::auth::with_claims;
+    use shared::storage::{get_json, InMemoryStorage};
+    use shared::webhook::WebhookCallback;
+
+    use super::*;
+
+    fn request(body: Value) -> Request {
+        let request = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/media")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        with_claims(
+            request,
+            &[
+                ("sub", "user-1"),
+                ("custom:tenant_id", "acme"),
+                ("scope", "media:write"),
+            ],
+        )
+    }
+
+    fn policy() -> AccessPolicy {
+        AccessPolicy {
+            required_scope: Some("media:write".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn response_json(response: &Response<Body>) -> Value {
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_staging_metadata_and_returns_upload_link() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01"
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        let body = response_json(&response);
+        let task_id = body["task_id"].as_str().unwrap();
+        assert_eq!(
+            body["upload_url"],
+            format!("memory://media/media-uploads/acme/{}", task_id)
+        );
+
+        let metadata: MediaMetadata =
+            get_json(&storage, "media", &staging_metadata_key("acme", task_id))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(metadata.topic, "serverless");
+        assert_eq!(metadata.owner.as_deref(), Some("user-1"));
+        assert_eq!(storage.keys("media"), vec![staging_metadata_key("acme", task_id)]);
+    }
+
+    #[tokio::test]
+    async fn stores_webhook_callback_apart_from_metadata() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "callbackUrl": "https://example.com/hooks/media",
+                "callbackSecret": "0123456789abcdef"
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        let task_id = response_json(&response)["task_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let callback: WebhookCallback = get_json(&storage, "media", &webhook_callback_key(&task_id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(callback.url, "https://example.com/hooks/media");
+
+        let staging = storage
+            .get_object("media", &staging_metadata_key("acme", &task_id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!String::from_utf8(staging).unwrap().contains("callbackSecret"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_body() {
+        let storage = InMemoryStorage::new();
+
+        let response =
+            create_media_upload_link(request(json!({ "topic": 1 })), &storage, "media", &policy())
+                .await
+                .unwrap();
+
+        assert_eq!(response.status(), 400);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/problem+json"
+        );
+        let problem = response_json(&response);
+        assert_eq!(problem["code"], "validation-failed");
+        assert_eq!(problem["errors"][0]["field"], "topic");
+        assert_eq!(problem["errors"][0]["rule"], "type");
+        assert!(storage.keys("media").is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_metadata() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "01/07/2024"
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400);
+        assert_eq!(
+            response_json(&response)["errors"],
+            json!([{
+                "field": "date",
+                "rule": "date_format",
+                "message": "Invalid date format 01/07/2024. Expected format is yyyy-MM-dd."
+            }])
+        );
+        assert!(storage.keys("media").is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_cleaning_options() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "cleaning": { "minConfidence": 1.5, "removeFillers": true }
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400);
+        let errors = &response_json(&response)["errors"];
+        assert_eq!(errors[0]["field"], "cleaning.minConfidence");
+        assert_eq!(errors[0]["rule"], "maximum");
+        assert!(storage.keys("media").is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_callback_without_secret() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "callbackUrl": "https://example.com/hooks/media"
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400);
+        let problem: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "callbackSecret");
+        assert_eq!(problem["errors"][0]["rule"], "required_together");
+    }
+
+    #[tokio::test]
+    async fn reports_internal_callback_urls_at_their_field() {
+        let storage = InMemoryStorage::new();
+
+        let response = create_media_upload_link(
+            request(json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "callbackUrl": "https://169.254.169.254/latest/meta-data",
+                "callbackSecret": "0123456789abcdef"
+            })),
+            &storage,
+            "media",
+            &policy(),
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400);
+        let problem: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "callbackUrl");
+        assert_eq!(problem["errors"][0]["rule"], "https_url");
+    }
+
+    #[tokio::test]
+    async fn rejects_callers_without_a_tenant_or_the_write_scope() {
+        let storage = InMemoryStorage::new();
+        let body = json!({
+            "topic": "serverless",
+            "sourceUrl": "https://example.com/episode-1",
+            "date": "2024-07-01"
+        });
+
+        for claims in [
+            vec![("sub", "user-1"), ("scope", "media:write")],
+            vec![("sub", "user-1"), ("custom:tenant_id", "acme"), ("scope", "media:read")],
+        ] {
+            let request = lambda_http::http::Request::builder()
+                .method("POST")
+                .uri("/media")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+
+            let response = create_media_upload_link(
+                with_claims(request, &claims),
+                &storage,
+                "media",
+                &policy(),
+            )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), 403);
+        }
+        assert!(storage.keys("media").is_empty());
+    }
+}
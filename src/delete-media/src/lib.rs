@@ -0,0 +1,326 @@
+use lambda_http::tracing::info;
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+
+use shared::auth::{authorize, AccessPolicy};
+use shared::cache::AnswerCache;
+use shared::error::ApiError;
+use shared::ingestion::{IngestionQueue, IngestionRequest};
+use shared::models::{
+    media_upload_key, normalized_media_key, staging_metadata_key, DocType, MediaMetadata,
+};
+use shared::storage::{get_json, ObjectStorage};
+use shared::task::{task_record_key, TaskRecord};
+use shared::transcription::transcribe_output_key;
+use shared::webhook::webhook_callback_key;
+
+/// Serves `DELETE /media/{task_id}`.
+pub async fn delete_media(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    ingestion_queue: &dyn IngestionQueue,
+    answer_cache: &dyn AnswerCache,
+    media_bucket_name: &str,
+    kb_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, Error> {
+    delete(
+        event,
+        storage,
+        ingestion_queue,
+        answer_cache,
+        media_bucket_name,
+        kb_bucket_name,
+        policy,
+    )
+        .await
+        .or_else(ApiError::into_response)
+}
+
+/// Removes the upload, everything derived from it and its knowledge base documents, then
+/// queues a sync so the knowledge base drops them too.
+async fn delete(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    ingestion_queue: &dyn IngestionQueue,
+    answer_cache: &dyn AnswerCache,
+    media_bucket_name: &str,
+    kb_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, ApiError> {
+    let caller = authorize(&event, policy)?;
+    let tenant_id = caller.tenant_id.as_str();
+
+    let task_id = event
+        .path_parameters_ref()
+        .and_then(|p| p.first("task_id"))
+        .ok_or_else(|| ApiError::BadRequest("Missing task_id".to_string()))?
+        .to_string();
+
+    // Both are kept per tenant, other tenants' tasks are simply not found. The staging
+    // metadata outlives a failed task record and the other way around.
+    let staging_key = staging_metadata_key(tenant_id, &task_id);
+    let record_key = task_record_key(tenant_id, &task_id);
+
+    let task_record = get_json::<TaskRecord>(storage, media_bucket_name, &record_key).await?;
+
+    let metadata = match get_json::<MediaMetadata>(storage, media_bucket_name, &staging_key)
+        .await?
+    {
+        Some(metadata) => metadata,
+        None => task_record
+            .as_ref()
+            .map(|task_record| task_record.metadata.clone())
+            .ok_or_else(|| ApiError::NotFound(format!("No media for task {}", task_id)))?,
+    };
+
+    if !caller.can_manage(metadata.owner.as_deref()) {
+        return Err(ApiError::AccessDenied(format!(
+            "Only the owner of task {} or an admin can delete it",
+            task_id
+        )));
+    }
+
+    let mut documents = 0;
+
+    for doc_type in DocType::ALL {
+        let key = doc_type.kb_key(tenant_id, &task_id);
+        if !storage.object_exists(kb_bucket_name, &key).await? {
+            continue;
+        }
+
+        storage.delete_object(kb_bucket_name, &key).await?;
+        storage
+            .delete_object(kb_bucket_name, &format!("{}.metadata.json", key))
+            .await?;
+        documents += 1;
+    }
+
+    for key in [
+        media_upload_key(tenant_id, &task_id),
+        normalized_media_key(tenant_id, &task_id),
+        transcribe_output_key(&task_id),
+        webhook_callback_key(&task_id),
+        staging_key,
+        record_key,
+    ] {
+        storage.delete_object(media_bucket_name, &key).await?;
+    }
+
+    if documents > 0 {
+        ingestion_queue
+            .enqueue(&IngestionRequest {
+                task_id: task_id.clone(),
+                tenant_id: tenant_id.to_string(),
+                deleted: true,
+            })
+            .await?;
+
+        // Cached answers about the topic may cite the media, and so may those of the topics it
+        // was moved away from until it was indexed again.
+        let stale_topics = task_record
+            .map(|task_record| task_record.stale_topics)
+            .unwrap_or_default();
+        for topic in std::iter::once(&metadata.topic).chain(&stale_topics) {
+            answer_cache.invalidate_topic(tenant_id, topic).await?;
+        }
+    }
+
+    info!(task_id, documents, "deleted media");
+
+    Ok(Response::builder().status(204).body(Body::Empty)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use shared::auth::with_claims;
+    use shared::cache::{AnswerCacheKey, CachedAnswer, InMemoryAnswerCache};
+    use shared::ingestion::InMemoryIngestionQueue;
+    use shared::storage::{put_json, InMemoryStorage};
+
+    use super::*;
+
+    const OWNER: &[(&str, &str)] = &[("sub", "user-1"), ("custom:tenant_id", "acme")];
+
+    struct Fixture {
+        storage: InMemoryStorage,
+        queue: InMemoryIngestionQueue,
+        answer_cache: InMemoryAnswerCache,
+    }
+
+    impl Fixture {
+        /// A transcribed task of `user-1` with its transcript and summary ingested.
+        async fn new() -> Self {
+            let storage = InMemoryStorage::new();
+            let metadata = json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "owner": "user-1"
+            });
+            put_json(&storage, "media", &staging_metadata_key("acme", "task-1"), &metadata)
+                .await
+                .unwrap();
+            put_json(
+                &storage,
+                "media",
+                &task_record_key("acme", "task-1"),
+                &json!({
+                    "taskId": "task-1",
+                    "tenantId": "acme",
+                    "metadata": metadata,
+                    "status": "indexed",
+                    "updatedAt": "2024-07-01T12:00:00Z"
+                }),
+            )
+                .await
+                .unwrap();
+
+            for key in [media_upload_key("acme", "task-1"), transcribe_output_key("task-1")] {
+                storage
+                    .put_object("media", &key, b"{}".to_vec(), "application/json")
+                    .await
+                    .unwrap();
+            }
+
+            for doc_type in [DocType::Transcript, DocType::Summary] {
+                let key = doc_type.kb_key("acme", "task-1");
+                for key in [key.clone(), format!("{}.metadata.json", key)] {
+                    storage
+                        .put_object("kb", &key, b"hello".to_vec(), "text/plain")
+                        .await
+                        .unwrap();
+                }
+            }
+
+            let fixture = Fixture {
+                storage,
+                queue: InMemoryIngestionQueue::new(),
+                answer_cache: InMemoryAnswerCache::new(),
+            };
+            fixture.cache_answer("serverless").await;
+            fixture
+        }
+
+        async fn cache_answer(&self, topic: &str) {
+            let key = AnswerCacheKey::new("acme", topic, "What is new?", &[], &[], "claude");
+            let answer = CachedAnswer {
+                output: "Episode 1".to_string(),
+                sources: vec!["https://example.com/episode-1".to_string()],
+                citation_count: 1,
+                sources_by_knowledge_base: Default::default(),
+            };
+            self.answer_cache.put(&key, "0", &answer).await.unwrap();
+        }
+
+        async fn delete_as(&self, claims: &[(&str, &str)], task_id: &str) -> Response<Body> {
+            let request = lambda_http::http::Request::builder()
+                .method("DELETE")
+                .uri("/media")
+                .body(Body::Empty)
+                .unwrap();
+            let request = with_claims(request, claims).with_path_parameters(HashMap::from([(
+                "task_id".to_string(),
+                task_id.to_string(),
+            )]));
+            let policy = AccessPolicy {
+                required_scope: None,
+                admin_group: "admin".to_string(),
+            };
+
+            delete_media(
+                request,
+                &self.storage,
+                &self.queue,
+                &self.answer_cache,
+                "media",
+                "kb",
+                &policy,
+            )
+                .await
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn deletes_the_media_and_its_documents() {
+        let fixture = Fixture::new().await;
+
+        let response = fixture.delete_as(OWNER, "task-1").await;
+
+        assert_eq!(response.status(), 204);
+        assert!(fixture.storage.keys("media").is_empty());
+        assert!(fixture.storage.keys("kb").is_empty());
+        assert_eq!(
+            fixture.queue.drain(),
+            vec![IngestionRequest {
+                task_id: "task-1".to_string(),
+                tenant_id: "acme".to_string(),
+                deleted: true,
+            }]
+        );
+        assert!(fixture.answer_cache.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalidates_the_topics_the_media_was_moved_away_from() {
+        let fixture = Fixture::new().await;
+        let record_key = task_record_key("acme", "task-1");
+        let mut record: serde_json::Value = get_json(&fixture.storage, "media", &record_key)
+            .await
+            .unwrap()
+            .unwrap();
+        record["staleTopics"] = json!(["aws"]);
+        put_json(&fixture.storage, "media", &record_key, &record)
+            .await
+            .unwrap();
+        fixture.cache_answer("aws").await;
+        fixture.cache_answer("rustlang").await;
+
+        assert_eq!(fixture.delete_as(OWNER, "task-1").await.status(), 204);
+
+        let topics: Vec<String> = fixture
+            .answer_cache
+            .keys()
+            .into_iter()
+            .map(|key| key.topic)
+            .collect();
+        assert_eq!(topics, vec!["rustlang".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_or_an_admin_can_delete() {
+        let fixture = Fixture::new().await;
+
+        let other_user = &[("sub", "user-2"), ("custom:tenant_id", "acme")];
+        assert_eq!(fixture.delete_as(other_user, "task-1").await.status(), 403);
+        let other_tenant = &[("sub", "user-1"), ("custom:tenant_id", "globex")];
+        assert_eq!(fixture.delete_as(other_tenant, "task-1").await.status(), 404);
+        assert_eq!(fixture.storage.keys("kb").len(), 4);
+
+        let admin = &[
+            ("sub", "user-3"),
+            ("custom:tenant_id", "acme"),
+            ("cognito:groups", "[admin]"),
+        ];
+        assert_eq!(fixture.delete_as(admin, "task-1").await.status(), 204);
+        assert!(fixture.storage.keys("kb").is_empty());
+    }
+
+    #[tokio::test]
+    async fn does_not_sync_media_without_documents() {
+        let fixture = Fixture::new().await;
+        for key in fixture.storage.keys("kb") {
+            fixture.storage.delete_object("kb", &key).await.unwrap();
+        }
+
+        assert_eq!(fixture.delete_as(OWNER, "task-1").await.status(), 204);
+
+        assert!(fixture.storage.keys("media").is_empty());
+        assert!(fixture.queue.drain().is_empty());
+        assert_eq!(fixture.answer_cache.keys().len(), 1);
+    }
+}
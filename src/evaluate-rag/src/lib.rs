@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+
+use query_knowledge_base::query::Query;
+use query_knowledge_base::{to_retrieval_query, unwrap_answer};
+use shared::retrieval::{Retrieval, RetrievalSettings};
+use shared::Error;
+
+pub mod recorded;
+
+/// Questions asked as a tenant, with the answers and sources they are expected to get.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationSet {
+    pub tenant_id: String,
+    pub cases: Vec<EvaluationCase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationCase {
+    /// The query as the query route accepts it.
+    #[serde(flatten)]
+    pub query: Query,
+    pub expected_answer: String,
+    /// `source_url` of the media the answer should cite, citing any of them is a hit.
+    pub expected_sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseResult {
+    pub input: String,
+    pub topic: String,
+    pub output: Option<String>,
+    pub sources: Vec<String>,
+    pub source_hit: bool,
+    pub answer_similarity: f64,
+    /// Why no answer was generated, if retrieval failed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationReport {
+    pub model: String,
+    pub settings: RetrievalSettings,
+    /// Share of the cases citing one of their expected sources.
+    pub source_hit_rate: f64,
+    /// Mean answer similarity of the cases, unanswered ones scoring 0.
+    pub answer_similarity: f64,
+    pub results: Vec<CaseResult>,
+}
+
+/// Runs every case through retrieval the way the query route does, a failing case is scored 0
+/// and does not stop the evaluation.
+pub async fn evaluate(
+    retrieval: &dyn Retrieval,
+    settings: &RetrievalSettings,
+    set: &EvaluationSet,
+) -> Result<EvaluationReport, Error> {
+    let mut results = Vec::with_capacity(set.cases.len());
+
+    for case in &set.cases {
+        case.query.validate()?;
+
+        let query = to_retrieval_query(&set.tenant_id, case.query.clone());
+        let (answer, error) = match retrieval.retrieve_and_generate(&query).await {
+            Ok(answer) => (answer.map(unwrap_answer), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        let (output, sources) = match answer {
+            Some(answer) => (Some(answer.output), answer.sources),
+            None => (None, Vec::new()),
+        };
+
+        results.push(CaseResult {
+            input: case.query.input.clone(),
+            topic: case.query.topic.clone(),
+            source_hit: case
+                .expected_sources
+                .iter()
+                .any(|expected| sources.contains(expected)),
+            answer_similarity: output
+                .as_deref()
+                .map_or(0.0, |output| answer_similarity(&case.expected_answer, output)),
+            output,
+            sources,
+            error,
+        });
+    }
+
+    Ok(EvaluationReport {
+        model: retrieval.model().to_string(),
+        settings: settings.clone(),
+        source_hit_rate: mean(results.iter().map(|result| f64::from(result.source_hit as u8))),
+        answer_similarity: mean(results.iter().map(|result| result.answer_similarity)),
+        results,
+    })
+}
+
+/// F1 score of the words of the answer against the expected one, ignoring case and
+/// punctuation.
+pub fn answer_similarity(expected: &str, output: &str) -> f64 {
+    let expected = words(expected);
+    let output = words(output);
+
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for word in &expected {
+        *remaining.entry(word.as_str()).or_default() += 1;
+    }
+
+    let common = output
+        .iter()
+        .filter(|word| match remaining.get_mut(word.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        })
+        .count();
+
+    if common == 0 {
+        return 0.0;
+    }
+
+    let precision = common as f64 / output.len() as f64;
+    let recall = common as f64 / expected.len() as f64;
+    2.0 * precision * recall / (precision + recall)
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn mean(scores: impl ExactSizeIterator<Item = f64>) -> f64 {
+    match scores.len() {
+        0 => 0.0,
+        len => scores.sum::<f64>() / len as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use shared::retrieval::BedrockRetrieval;
+
+    use crate::recorded::{RecordedBedrock, Recording};
+
+    use super::*;
+
+    const CASES: &str = include_str!("../fixtures/cases.json");
+    const RECORDINGS: &str = include_str!("../fixtures/recordings.json");
+
+    #[test]
+    fn scores_the_words_shared_with_the_expected_answer() {
+        assert_eq!(answer_similarity("Lambda scales out.", "lambda SCALES out"), 1.0);
+        assert_eq!(answer_similarity("Lambda scales out", "It does not"), 0.0);
+        assert_eq!(answer_similarity("Lambda scales out", "Lambda scales"), 0.8);
+        assert_eq!(answer_similarity("Lambda scales", ""), 0.0);
+    }
+
+    #[tokio::test]
+    async fn evaluates_the_cases_against_recorded_responses() {
+        let set: EvaluationSet = serde_json::from_str(CASES).unwrap();
+        let recordings: Vec<Recording> = serde_json::from_str(RECORDINGS).unwrap();
+        let bedrock = RecordedBedrock::new(recordings);
+        let settings = RetrievalSettings {
+            number_of_results: Some(3),
+            prompt_template: None,
+        };
+        let retrieval = BedrockRetrieval::new(
+            bedrock.client(),
+            "recorded".to_string(),
+            "claude".to_string(),
+            settings.clone(),
+        );
+
+        let report = evaluate(&retrieval, &settings, &set).await.unwrap();
+
+        assert_eq!(report.model, "claude");
+        assert_eq!(report.results.len(), 3);
+        assert!(report.results[0].source_hit);
+        assert!(report.results[0].answer_similarity > 0.5);
+        assert!(!report.results[1].source_hit);
+        assert_eq!(report.results[1].sources, vec!["https://example.com/episode-3"]);
+        assert_eq!(report.results[2].output, None);
+        assert!(report.results[2].error.is_some());
+        assert!((report.source_hit_rate - 1.0 / 3.0).abs() < 1e-9);
+
+        let configuration = &bedrock.requests()[0]["retrieveAndGenerateConfiguration"];
+        let vector_search = &configuration["knowledgeBaseConfiguration"]["retrievalConfiguration"]
+            ["vectorSearchConfiguration"];
+        assert_eq!(vector_search["numberOfResults"], json!(3));
+        assert_eq!(
+            vector_search["filter"]["andAll"][0]["equals"],
+            json!({ "key": "tenant_id", "value": "acme" })
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::env;
+use std::process::ExitCode;
+
+use aws_config::BehaviorVersion;
+
+use evaluate_rag::recorded::{RecordedBedrock, Recording};
+use evaluate_rag::{evaluate, EvaluationSet};
+use shared::retrieval::{BedrockRetrieval, RetrievalSettings};
+use shared::Error;
+
+struct Options {
+    cases: String,
+    report: String,
+    recordings: Option<String>,
+    settings: RetrievalSettings,
+    min_source_hit_rate: f64,
+    min_answer_similarity: f64,
+}
+
+impl Options {
+    async fn from_args() -> Result<Self, Error> {
+        let mut options = Options {
+            cases: String::new(),
+            report: "evaluation-report.json".to_string(),
+            recordings: None,
+            settings: RetrievalSettings::default(),
+            min_source_hit_rate: 0.0,
+            min_answer_similarity: 0.0,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--cases" => options.cases = value()?,
+                "--report" => options.report = value()?,
+                "--recordings" => options.recordings = Some(value()?),
+                "--number-of-results" => {
+                    options.settings.number_of_results = Some(value()?.parse()?)
+                }
+                "--prompt-template" => {
+                    options.settings.prompt_template =
+                        Some(tokio::fs::read_to_string(value()?).await?)
+                }
+                "--min-source-hit-rate" => options.min_source_hit_rate = value()?.parse()?,
+                "--min-answer-similarity" => options.min_answer_similarity = value()?.parse()?,
+                other => return Err(Error::from(format!("Unknown argument {}", other))),
+            }
+        }
+
+        if options.cases.is_empty() {
+            return Err(Error::from("Missing --cases"));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Scores the answers to a set of questions, against the knowledge base of `KB_ID` with the
+/// model of `MODEL_ARN`, or against recorded Bedrock responses with `--recordings`. Exits with
+/// an error when the scores are below the `--min-*` thresholds.
+#[tokio::main]
+async fn main() -> Result<ExitCode, Error> {
+    let options = Options::from_args().await?;
+
+    let set: EvaluationSet = serde_json::from_slice(&tokio::fs::read(&options.cases).await?)?;
+
+    let retrieval = match &options.recordings {
+        Some(path) => {
+            let recordings: Vec<Recording> = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+            BedrockRetrieval::new(
+                RecordedBedrock::new(recordings).client(),
+                "recorded".to_string(),
+                env::var("MODEL_ARN").unwrap_or_else(|_| "recorded".to_string()),
+                options.settings.clone(),
+            )
+        }
+        None => BedrockRetrieval::new(
+            aws_sdk_bedrockagentruntime::Client::new(
+                &aws_config::load_defaults(BehaviorVersion::latest()).await,
+            ),
+            env::var("KB_ID").expect("KB_ID not set"),
+            env::var("MODEL_ARN").expect("MODEL_ARN not set"),
+            options.settings.clone(),
+        ),
+    };
+
+    let report = evaluate(&retrieval, &options.settings, &set).await?;
+    tokio::fs::write(&options.report, serde_json::to_vec_pretty(&report)?).await?;
+
+    for result in &report.results {
+        println!(
+            "{} {:.2} {}",
+            if result.source_hit { "hit " } else { "miss" },
+            result.answer_similarity,
+            result.input
+        );
+    }
+    println!(
+        "source hit rate {:.2}, answer similarity {:.2}, report written to {}",
+        report.source_hit_rate, report.answer_similarity, options.report
+    );
+
+    if report.source_hit_rate < options.min_source_hit_rate
+        || report.answer_similarity < options.min_answer_similarity
+    {
+        eprintln!("scores below the minimum");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
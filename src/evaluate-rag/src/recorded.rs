@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use aws_config::BehaviorVersion;
+use aws_sdk_bedrockagentruntime::config::http::{HttpRequest, HttpResponse};
+use aws_sdk_bedrockagentruntime::config::{Credentials, Region};
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::body::SdkBody;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use shared::cache::normalize_input;
+
+/// A `RetrieveAndGenerate` response body as Bedrock returned it for a question, e.g. the output
+/// of `aws bedrock-agent-runtime retrieve-and-generate`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub input: String,
+    pub response: Value,
+}
+
+/// Stands in for the Bedrock agent runtime endpoint, replaying the recorded response of each
+/// question. Requests go through the real client, so the configuration the query route builds
+/// is serialized and kept for inspection.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedBedrock {
+    responses: Arc<HashMap<String, Value>>,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl RecordedBedrock {
+    pub fn new(recordings: Vec<Recording>) -> Self {
+        RecordedBedrock {
+            responses: Arc::new(
+                recordings
+                    .into_iter()
+                    .map(|recording| (normalize_input(&recording.input), recording.response))
+                    .collect(),
+            ),
+            requests: Arc::default(),
+        }
+    }
+
+    pub fn client(&self) -> aws_sdk_bedrockagentruntime::Client {
+        let config = aws_sdk_bedrockagentruntime::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("recorded", "recorded", None, None, "recorded"))
+            .http_client(self.clone())
+            .build();
+
+        aws_sdk_bedrockagentruntime::Client::from_conf(config)
+    }
+
+    /// Bodies of the requests received so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Questions without a recording get the error Bedrock returns for an unknown resource.
+    fn respond(&self, request: &HttpRequest) -> HttpResponse {
+        let body: Value = request
+            .body()
+            .bytes()
+            .and_then(|bytes| serde_json::from_slice(bytes).ok())
+            .unwrap_or(Value::Null);
+        let input = body["input"]["text"].as_str().unwrap_or_default().to_string();
+        self.requests.lock().unwrap().push(body);
+
+        let (status, response) = match self.responses.get(&normalize_input(&input)) {
+            Some(response) => (200, response.clone()),
+            None => (
+                404,
+                json!({ "message": format!("No recorded response for {}", input) }),
+            ),
+        };
+
+        let mut response = HttpResponse::new(
+            status.try_into().expect("valid status code"),
+            SdkBody::from(response.to_string()),
+        );
+        response.headers_mut().insert("content-type", "application/json");
+        if status == 404 {
+            response
+                .headers_mut()
+                .insert("x-amzn-errortype", "ResourceNotFoundException");
+        }
+        response
+    }
+}
+
+impl HttpConnector for RecordedBedrock {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        HttpConnectorFuture::ready(Ok(self.respond(&request)))
+    }
+}
+
+impl HttpClient for RecordedBedrock {
+    fn http_connector(
+        &self,
+        _settings: &HttpConnectorSettings,
+        _components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
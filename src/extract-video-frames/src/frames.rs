@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use lambda_runtime::Error;
+use serde::Deserialize;
+use tokio::process::Command;
+
+/// A still image taken from the video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub timestamp_seconds: f64,
+    pub jpeg: Vec<u8>,
+}
+
+/// Samples still images from a media file.
+#[async_trait]
+pub trait FrameExtraction: Send + Sync {
+    /// Returns one frame every `interval_seconds`, at most `max_frames`, in order. Media without
+    /// a video stream has no frames. The frames are written to `directory` on the way.
+    async fn extract_frames(
+        &self,
+        media: &Path,
+        directory: &Path,
+        interval_seconds: u32,
+        max_frames: usize,
+    ) -> Result<Vec<Frame>, Error>;
+}
+
+/// Runs the `ffmpeg` and `ffprobe` binaries of the ffmpeg lambda layer.
+pub struct FfmpegFrameExtraction {
+    ffmpeg_path: String,
+    ffprobe_path: String,
+}
+
+impl FfmpegFrameExtraction {
+    pub fn new(ffmpeg_path: String, ffprobe_path: String) -> Self {
+        FfmpegFrameExtraction {
+            ffmpeg_path,
+            ffprobe_path,
+        }
+    }
+
+    async fn has_video_stream(&self, input: &Path) -> Result<bool, Error> {
+        let output = Command::new(&self.ffprobe_path)
+            .args(["-v", "error", "-select_streams", "v", "-show_streams", "-of", "json"])
+            .arg(input)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(Error::from(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let probe: Probe = serde_json::from_slice(&output.stdout)?;
+
+        // Audio files carry their cover art as a single frame video stream.
+        Ok(probe
+            .streams
+            .iter()
+            .any(|stream| stream.disposition.attached_pic == 0))
+    }
+}
+
+#[async_trait]
+impl FrameExtraction for FfmpegFrameExtraction {
+    async fn extract_frames(
+        &self,
+        media: &Path,
+        directory: &Path,
+        interval_seconds: u32,
+        max_frames: usize,
+    ) -> Result<Vec<Frame>, Error> {
+        if !self.has_video_stream(media).await? {
+            return Ok(Vec::new());
+        }
+
+        let output = Command::new(&self.ffmpeg_path)
+            .args(["-v", "error", "-i"])
+            .arg(media)
+            .args([
+                "-vf",
+                &format!("fps=1/{},scale='min(1280,iw)':-2", interval_seconds),
+                "-frames:v",
+                &max_frames.to_string(),
+                "-q:v",
+                "3",
+            ])
+            .arg(directory.join("frame-%05d.jpg"))
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(Error::from(format!(
+                "ffmpeg failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let mut frames = Vec::new();
+
+        // The fps filter emits the first frame at the start, then one per interval.
+        for index in 0..max_frames {
+            let path = directory.join(format!("frame-{:05}.jpg", index + 1));
+            if !tokio::fs::try_exists(&path).await? {
+                break;
+            }
+
+            frames.push(Frame {
+                timestamp_seconds: (index as u32 * interval_seconds) as f64,
+                jpeg: tokio::fs::read(&path).await?,
+            });
+        }
+
+        Ok(frames)
+    }
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<Stream>,
+}
+
+#[derive(Deserialize)]
+struct Stream {
+    #[serde(default)]
+    disposition: Disposition,
+}
+
+#[derive(Default, Deserialize)]
+struct Disposition {
+    #[serde(default)]
+    attached_pic: i64,
+}
@@ -0,0 +1,382 @@
+use std::path::PathBuf;
+
+use aws_lambda_events::event::eventbridge::EventBridgeEvent;
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::info;
+use serde::{Deserialize, Serialize};
+
+use shared::auth::tenant_and_task;
+use shared::generation::TextGeneration;
+use shared::ingestion::{IngestionQueue, IngestionRequest};
+use shared::models::{media_timestamp, staging_metadata_key, DocType, MediaMetadata};
+use shared::storage::{get_json, put_json, ObjectStorage};
+use shared::task::{task_record_key, TaskRecord};
+
+use crate::frames::{Frame, FrameExtraction};
+
+pub mod frames;
+
+const SYSTEM_PROMPT: &str = "You index the on-screen content of videos for a search engine. \
+Answer in plain text without any preamble.";
+
+const FRAME_PROMPT: &str = "Transcribe any text visible in this video frame verbatim, such as \
+slide titles, bullet points, code or captions. Then describe diagrams, charts and screenshots in \
+one or two sentences. Reply with NONE when the frame only shows people or scenery.";
+
+/// Reply of the model for frames without anything worth indexing.
+const NOTHING_ON_SCREEN: &str = "NONE";
+
+/// Detail of the S3 `Object Created` event delivered through EventBridge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectCreated {
+    pub bucket: Bucket,
+    pub object: Object,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bucket {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Object {
+    pub key: String,
+}
+
+/// Deployment settings of the frame extraction.
+#[derive(Debug, Clone)]
+pub struct FrameConfig {
+    pub kb_bucket_name: String,
+    /// Seconds between two sampled frames.
+    pub interval_seconds: u32,
+    /// Caps the model calls for long videos, frames past it are not described.
+    pub max_frames: usize,
+    /// Where uploads are downloaded to and frames extracted, `/tmp` on Lambda.
+    pub work_directory: PathBuf,
+}
+
+/// Describes keyframes of an uploaded video and stores them as the task's visuals document,
+/// next to the transcript. Uploads without a video stream are skipped.
+pub async fn extract_video_frames(
+    event: LambdaEvent<EventBridgeEvent<ObjectCreated>>,
+    storage: &dyn ObjectStorage,
+    frames: &dyn FrameExtraction,
+    generation: &dyn TextGeneration,
+    ingestion_queue: &dyn IngestionQueue,
+    config: &FrameConfig,
+) -> Result<(), Error> {
+    let ObjectCreated { bucket, object } = event.payload.detail;
+    let (tenant_id, task_id) = tenant_and_task(&object.key)
+        .ok_or_else(|| Error::from(format!("No tenant in object key {}", object.key)))?;
+
+    let directory = config.work_directory.join(format!("frames-{}", task_id));
+    tokio::fs::create_dir_all(&directory).await?;
+
+    let frames = async {
+        let media = directory.join("media");
+        if !storage.download(&bucket.name, &object.key, &media).await? {
+            return Err(Error::from("Uploaded media not found"));
+        }
+
+        frames
+            .extract_frames(&media, &directory, config.interval_seconds, config.max_frames)
+            .await
+    }
+        .await;
+
+    // /tmp survives warm invocations, so the media must not pile up there.
+    tokio::fs::remove_dir_all(&directory).await?;
+    let frames = frames?;
+
+    if frames.is_empty() {
+        info!(task_id, "no video stream");
+        return Ok(());
+    }
+
+    let descriptions = describe_frames(generation, &frames).await?;
+
+    if descriptions.is_empty() {
+        info!(task_id, frames = frames.len(), "nothing on screen");
+        return Ok(());
+    }
+
+    let metadata: MediaMetadata =
+        get_json(storage, &bucket.name, &staging_metadata_key(tenant_id, task_id))
+            .await?
+            .ok_or_else(|| Error::from("Staging media metadata not found"))?;
+
+    // The transcription handler refreshes this sidecar if it finishes after the frames.
+    let task_record_key = task_record_key(tenant_id, task_id);
+    let details = get_json::<TaskRecord>(storage, &bucket.name, &task_record_key)
+        .await?
+        .map(|task_record| task_record.details)
+        .unwrap_or_default();
+
+    let key = DocType::Visuals.kb_key(tenant_id, task_id);
+
+    put_json(
+        storage,
+        &config.kb_bucket_name,
+        &format!("{}.metadata.json", key),
+        &metadata.to_kb_metadata(tenant_id, task_id, DocType::Visuals, &details),
+    )
+        .await?;
+
+    storage
+        .put_object(
+            &config.kb_bucket_name,
+            &key,
+            visuals_document(&metadata, &descriptions).into_bytes(),
+            "text/plain",
+        )
+        .await?;
+
+    ingestion_queue
+        .enqueue(&IngestionRequest {
+            task_id: task_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            deleted: false,
+        })
+        .await?;
+
+    info!(
+        task_id,
+        frames = frames.len(),
+        descriptions = descriptions.len(),
+        "stored visuals document"
+    );
+    Ok(())
+}
+
+/// Timestamped descriptions, a slide shown over several frames is only kept once.
+async fn describe_frames(
+    generation: &dyn TextGeneration,
+    frames: &[Frame],
+) -> Result<Vec<(f64, String)>, Error> {
+    let mut descriptions: Vec<(f64, String)> = Vec::new();
+    let mut previous = String::new();
+
+    for frame in frames {
+        let description = generation
+            .describe_image(SYSTEM_PROMPT, FRAME_PROMPT, &frame.jpeg)
+            .await?
+            .trim()
+            .to_string();
+
+        if description.is_empty() || description == NOTHING_ON_SCREEN || description == previous {
+            continue;
+        }
+
+        previous = description.clone();
+        descriptions.push((frame.timestamp_seconds, description));
+    }
+
+    Ok(descriptions)
+}
+
+fn visuals_document(metadata: &MediaMetadata, descriptions: &[(f64, String)]) -> String {
+    let lines: Vec<String> = descriptions
+        .iter()
+        .map(|(timestamp_seconds, description)| {
+            format!("[{}] {}", media_timestamp(*timestamp_seconds), description)
+        })
+        .collect();
+
+    format!("On-screen content of {}\n\n{}", metadata.source_url, lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use lambda_runtime::Context;
+    use serde_json::{json, Value};
+
+    use shared::generation::InMemoryGeneration;
+    use shared::ingestion::InMemoryIngestionQueue;
+    use shared::storage::InMemoryStorage;
+
+    use super::*;
+
+    struct StubFrameExtraction {
+        frames: usize,
+    }
+
+    #[async_trait]
+    impl FrameExtraction for StubFrameExtraction {
+        async fn extract_frames(
+            &self,
+            _media: &Path,
+            _directory: &Path,
+            interval_seconds: u32,
+            max_frames: usize,
+        ) -> Result<Vec<Frame>, Error> {
+            Ok((0..self.frames.min(max_frames))
+                .map(|index| Frame {
+                    timestamp_seconds: (index as u32 * interval_seconds) as f64,
+                    jpeg: vec![index as u8],
+                })
+                .collect())
+        }
+    }
+
+    /// Tests run in parallel on the same task, each gets its own directory.
+    fn config() -> FrameConfig {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        FrameConfig {
+            kb_bucket_name: "kb".to_string(),
+            interval_seconds: 30,
+            max_frames: 10,
+            work_directory: std::env::temp_dir().join(format!(
+                "extract-video-frames-test-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            )),
+        }
+    }
+
+    fn event(key: &str) -> LambdaEvent<EventBridgeEvent<ObjectCreated>> {
+        let payload = serde_json::from_value(json!({
+            "version": "0",
+            "id": "event-1",
+            "detail-type": "Object Created",
+            "source": "aws.s3",
+            "account": "123456789012",
+            "time": "2024-07-01T00:00:00Z",
+            "region": "us-east-1",
+            "resources": ["arn:aws:s3:::media"],
+            "detail": {
+                "version": "0",
+                "bucket": { "name": "media" },
+                "object": { "key": key, "size": 4, "etag": "etag" },
+                "reason": "PutObject"
+            }
+        }))
+            .unwrap();
+
+        LambdaEvent::new(payload, Context::default())
+    }
+
+    async fn storage_with_upload() -> InMemoryStorage {
+        let storage = InMemoryStorage::new();
+        storage
+            .put_object("media", "media-uploads/acme/task-1", b"mp4".to_vec(), "video/mp4")
+            .await
+            .unwrap();
+        put_json(
+            &storage,
+            "media",
+            &staging_metadata_key("acme", "task-1"),
+            &json!({
+                "topic": "Serverless RAG",
+                "sourceUrl": "https://example.com/talk",
+                "date": "2024-07-01"
+            }),
+        )
+            .await
+            .unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn stores_and_queues_the_visuals_document() {
+        let storage = storage_with_upload().await;
+        let generation = InMemoryGeneration::with_replies(vec![
+            "Agenda: knowledge bases".to_string(),
+            "NONE".to_string(),
+            "Agenda: knowledge bases".to_string(),
+            " fn main() {} ".to_string(),
+        ]);
+        let queue = InMemoryIngestionQueue::new();
+
+        extract_video_frames(
+            event("media-uploads/acme/task-1"),
+            &storage,
+            &StubFrameExtraction { frames: 4 },
+            &generation,
+            &queue,
+            &config(),
+        )
+            .await
+            .unwrap();
+
+        let document = storage
+            .get_object("kb", "transcripts/acme/task-1.visuals")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(document).unwrap(),
+            "On-screen content of https://example.com/talk\n\n\
+            [00:00] Agenda: knowledge bases\n\
+            [01:30] fn main() {}"
+        );
+
+        let sidecar: Value =
+            get_json(&storage, "kb", "transcripts/acme/task-1.visuals.metadata.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            sidecar["metadataAttributes"]["doc_type"],
+            json!("visuals")
+        );
+        assert_eq!(sidecar["metadataAttributes"]["task_id"], json!("task-1"));
+        assert_eq!(sidecar["metadataAttributes"]["tenant_id"], json!("acme"));
+
+        assert_eq!(generation.prompts().len(), 4);
+        assert_eq!(
+            queue.drain(),
+            vec![IngestionRequest {
+                task_id: "task-1".to_string(),
+                tenant_id: "acme".to_string(),
+                deleted: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_media_without_video() {
+        let storage = storage_with_upload().await;
+        let generation = InMemoryGeneration::new("NONE");
+        let queue = InMemoryIngestionQueue::new();
+
+        extract_video_frames(
+            event("media-uploads/acme/task-1"),
+            &storage,
+            &StubFrameExtraction { frames: 0 },
+            &generation,
+            &queue,
+            &config(),
+        )
+            .await
+            .unwrap();
+
+        assert!(generation.prompts().is_empty());
+        assert!(queue.drain().is_empty());
+        assert!(storage.keys("kb").is_empty());
+    }
+
+    #[tokio::test]
+    async fn skips_videos_without_on_screen_content() {
+        let storage = storage_with_upload().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        extract_video_frames(
+            event("media-uploads/acme/task-1"),
+            &storage,
+            &StubFrameExtraction { frames: 3 },
+            &InMemoryGeneration::new("NONE"),
+            &queue,
+            &config(),
+        )
+            .await
+            .unwrap();
+
+        assert!(queue.drain().is_empty());
+        assert!(storage.keys("kb").is_empty());
+    }
+}
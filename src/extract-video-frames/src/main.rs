@@ -0,0 +1,64 @@
+use std::env;
+
+use aws_config::BehaviorVersion;
+use aws_lambda_events::event::eventbridge::EventBridgeEvent;
+use lambda_runtime::{Error, LambdaEvent, run, service_fn, tracing};
+
+use extract_video_frames::{extract_video_frames, FrameConfig, ObjectCreated};
+use extract_video_frames::frames::FfmpegFrameExtraction;
+use shared::generation::BedrockGeneration;
+use shared::ingestion::SqsIngestionQueue;
+use shared::storage::S3Storage;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let storage = S3Storage::new(aws_sdk_s3::Client::new(&config));
+    let ingestion_queue = SqsIngestionQueue::new(
+        aws_sdk_sqs::Client::new(&config),
+        env::var("INGESTION_QUEUE_URL").expect("INGESTION_QUEUE_URL not set"),
+    );
+    let generation = BedrockGeneration::new(
+        aws_sdk_bedrockruntime::Client::new(&config),
+        env::var("VISUAL_MODEL_ID").expect("VISUAL_MODEL_ID not set"),
+    );
+
+    // The ffmpeg layer is extracted under /opt.
+    let frames = FfmpegFrameExtraction::new(
+        env::var("FFMPEG_PATH").unwrap_or_else(|_| "/opt/bin/ffmpeg".to_string()),
+        env::var("FFPROBE_PATH").unwrap_or_else(|_| "/opt/bin/ffprobe".to_string()),
+    );
+
+    let frame_config = FrameConfig {
+        kb_bucket_name: env::var("KB_BUCKET").expect("KB_BUCKET not set"),
+        interval_seconds: env::var("FRAME_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?,
+        max_frames: env::var("MAX_FRAMES")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?,
+        work_directory: env::temp_dir(),
+    };
+
+    run(service_fn(|event: LambdaEvent<EventBridgeEvent<ObjectCreated>>| async {
+        extract_video_frames(
+            event,
+            &storage,
+            &frames,
+            &generation,
+            &ingestion_queue,
+            &frame_config,
+        )
+            .await
+    }))
+        .await
+}
@@ -0,0 +1,276 @@
+use shared::cleaning::{CleaningOptions, LowConfidence};
+
+use crate::transcription_result::{Item2, TranscriptionResult};
+
+const FILLER_WORDS: &[&str] = &["um", "umm", "uh", "uhm", "uhh", "er", "erm", "ah", "hmm", "mm"];
+
+const TERMINAL_PUNCTUATION: &[char] = &['.', '?', '!'];
+
+/// Text stored in the knowledge base for a transcript.
+///
+/// Without cleaning this is the transcript Transcribe assembled. Otherwise the text is rebuilt
+/// from the recognized items, one line per speaker or channel turn.
+pub fn transcript_text(result: &TranscriptionResult, options: &CleaningOptions) -> String {
+    if !options.is_enabled() {
+        return result
+            .results
+            .transcripts
+            .iter()
+            .map(|t| t.transcript.clone())
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    let mut turns = turns(result, options);
+
+    if let Some(min_seconds) = options.merge_turns_under_seconds {
+        turns = merge_short_turns(turns, min_seconds);
+    }
+
+    turns
+        .into_iter()
+        .map(|turn| turn.text(options.normalize_punctuation == Some(true)))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug, Default)]
+struct Turn {
+    speaker: Option<String>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+    words: Vec<String>,
+}
+
+impl Turn {
+    fn duration_seconds(&self) -> f64 {
+        match (self.start_time, self.end_time) {
+            (Some(start_time), Some(end_time)) => end_time - start_time,
+            _ => 0.0,
+        }
+    }
+
+    fn push_punctuation(&mut self, punctuation: &str, normalize: bool) {
+        let Some(word) = self.words.last_mut() else {
+            return;
+        };
+
+        if normalize {
+            match word.chars().last() {
+                Some(last) if punctuation.starts_with(last) => return,
+                Some(',') if punctuation.starts_with(TERMINAL_PUNCTUATION) => {
+                    word.pop();
+                }
+                _ => {}
+            }
+        }
+
+        word.push_str(punctuation);
+    }
+
+    fn text(&self, normalize: bool) -> String {
+        if !normalize {
+            return self.words.join(" ");
+        }
+
+        let mut sentence_start = true;
+        let mut words = Vec::with_capacity(self.words.len());
+
+        for word in &self.words {
+            let word = match sentence_start {
+                true => capitalize(word),
+                false => word.clone(),
+            };
+            sentence_start = word.ends_with(TERMINAL_PUNCTUATION);
+            words.push(word);
+        }
+
+        if let Some(last) = words.last_mut() {
+            if last.ends_with(',') {
+                last.pop();
+            }
+            if !last.ends_with(TERMINAL_PUNCTUATION) {
+                last.push('.');
+            }
+        }
+
+        words.join(" ")
+    }
+}
+
+fn turns(result: &TranscriptionResult, options: &CleaningOptions) -> Vec<Turn> {
+    let normalize = options.normalize_punctuation == Some(true);
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut skip_comma = false;
+
+    for item in result.items() {
+        let Some(alternative) = item.alternatives.first() else {
+            continue;
+        };
+
+        if item.type_field == "punctuation" {
+            let skipped = skip_comma && alternative.content == ",";
+            skip_comma = false;
+            if !skipped {
+                if let Some(turn) = turns.last_mut() {
+                    turn.push_punctuation(&alternative.content, normalize);
+                }
+            }
+            continue;
+        }
+        skip_comma = false;
+
+        let speaker = speaker(item);
+        if turns.last().is_none_or(|turn| turn.speaker != speaker) {
+            turns.push(Turn {
+                speaker,
+                ..Default::default()
+            });
+        }
+        let turn = turns.last_mut().unwrap();
+
+        let start_time = item.start_time.as_deref().and_then(|t| t.parse().ok());
+        let end_time = item.end_time.as_deref().and_then(|t| t.parse().ok());
+        turn.start_time = turn.start_time.or(start_time);
+        turn.end_time = end_time.or(turn.end_time);
+
+        if options.remove_fillers == Some(true) && is_filler(&alternative.content) {
+            skip_comma = true;
+            continue;
+        }
+
+        // Redacted items carry no meaningful confidence, the placeholder is always kept.
+        let low_confidence = alternative.redactions.is_empty()
+            && options.min_confidence.is_some_and(|min_confidence| {
+                alternative
+                    .confidence
+                    .parse::<f64>()
+                    .is_ok_and(|confidence| confidence < min_confidence)
+            });
+
+        match (low_confidence, options.low_confidence.unwrap_or_default()) {
+            (true, LowConfidence::Drop) => skip_comma = true,
+            (true, LowConfidence::Mark) => turn.words.push(format!("{}(?)", alternative.content)),
+            (false, _) => turn.words.push(alternative.content.clone()),
+        }
+    }
+
+    turns
+}
+
+fn merge_short_turns(turns: Vec<Turn>, min_seconds: f64) -> Vec<Turn> {
+    let mut merged: Vec<Turn> = Vec::with_capacity(turns.len());
+
+    for turn in turns {
+        match merged.last_mut() {
+            Some(previous) if turn.duration_seconds() < min_seconds => {
+                previous.end_time = turn.end_time.or(previous.end_time);
+                previous.words.extend(turn.words);
+            }
+            _ => merged.push(turn),
+        }
+    }
+
+    merged
+}
+
+fn speaker(item: &Item2) -> Option<String> {
+    item.speaker_label
+        .clone()
+        .or_else(|| item.channel_label.clone())
+}
+
+fn is_filler(word: &str) -> bool {
+    FILLER_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = include_str!("../fixtures/transcription-result-noisy.json");
+
+    fn clean(options: CleaningOptions) -> String {
+        transcript_text(&serde_json::from_str(TRANSCRIPT).unwrap(), &options)
+    }
+
+    #[test]
+    fn keeps_the_transcribe_transcript_without_options() {
+        assert_eq!(
+            clean(CleaningOptions::default()),
+            "Um, so, uh, we shipped the lamda.. Yeah. Right, it runs on graviton."
+        );
+    }
+
+    #[test]
+    fn marks_or_drops_low_confidence_words() {
+        let options = CleaningOptions {
+            min_confidence: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            clean(options.clone()),
+            "Um, so, uh, we shipped the lamda(?)..\nYeah.\nRight, it runs on graviton(?)."
+        );
+
+        assert_eq!(
+            clean(CleaningOptions {
+                low_confidence: Some(LowConfidence::Drop),
+                ..options
+            }),
+            "Um, so, uh, we shipped the..\nYeah.\nRight, it runs on."
+        );
+    }
+
+    #[test]
+    fn removes_fillers_and_normalizes_punctuation() {
+        assert_eq!(
+            clean(CleaningOptions {
+                remove_fillers: Some(true),
+                normalize_punctuation: Some(true),
+                ..Default::default()
+            }),
+            "So, we shipped the lamda.\nYeah.\nRight, it runs on graviton."
+        );
+    }
+
+    #[test]
+    fn merges_short_turns() {
+        assert_eq!(
+            clean(CleaningOptions {
+                merge_turns_under_seconds: Some(1.0),
+                ..Default::default()
+            }),
+            "Um, so, uh, we shipped the lamda.. Yeah.\nRight, it runs on graviton."
+        );
+    }
+
+    #[test]
+    fn applies_upload_overrides() {
+        let deployment = CleaningOptions {
+            min_confidence: Some(0.5),
+            remove_fillers: Some(true),
+            ..Default::default()
+        };
+
+        let options = deployment.with_overrides(Some(&CleaningOptions {
+            remove_fillers: Some(false),
+            low_confidence: Some(LowConfidence::Drop),
+            ..Default::default()
+        }));
+
+        assert_eq!(options.min_confidence, Some(0.5));
+        assert_eq!(options.remove_fillers, Some(false));
+        assert_eq!(options.low_confidence, Some(LowConfidence::Drop));
+        assert_eq!(deployment.with_overrides(None), deployment);
+    }
+}
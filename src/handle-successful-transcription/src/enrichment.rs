@@ -0,0 +1,181 @@
+use lambda_runtime::Error;
+use serde::Deserialize;
+
+use shared::generation::TextGeneration;
+use shared::models::{media_timestamp, MediaMetadata};
+
+use crate::transcription_result::TranscriptionResult;
+
+const SYSTEM_PROMPT: &str = "You index podcast and video transcripts for a search engine. \
+Answer with a single JSON object and nothing else.";
+
+/// Transcript lines are cut at this length when Transcribe returned no audio segments.
+const LINE_SECONDS: f64 = 30.0;
+
+/// What the model extracted from a transcript.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Enrichment {
+    pub summary: String,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Chapter {
+    pub start_seconds: f64,
+    pub title: String,
+    #[serde(default)]
+    pub summary: String,
+}
+
+impl Enrichment {
+    /// Text of the summary document, keywords included so they are embedded with it. The topic
+    /// is left to the metadata attributes, the text would go stale when the media is moved.
+    pub fn summary_document(&self, metadata: &MediaMetadata) -> String {
+        let mut document = format!(
+            "Summary of {} ({})\n\n{}",
+            metadata.source_url, metadata.date, self.summary
+        );
+
+        if !self.keywords.is_empty() {
+            document.push_str(&format!("\n\nKeywords: {}", self.keywords.join(", ")));
+        }
+
+        document
+    }
+
+    pub fn chapters_document(&self, metadata: &MediaMetadata) -> String {
+        let chapters: Vec<String> = self
+            .chapters
+            .iter()
+            .map(|chapter| match chapter.summary.is_empty() {
+                true => format!("[{}] {}", media_timestamp(chapter.start_seconds), chapter.title),
+                false => format!(
+                    "[{}] {}: {}",
+                    media_timestamp(chapter.start_seconds),
+                    chapter.title,
+                    chapter.summary
+                ),
+            })
+            .collect();
+
+        format!("Chapters of {}\n\n{}", metadata.source_url, chapters.join("\n"))
+    }
+}
+
+/// Asks the model for a summary, timestamped chapters and keywords of the transcript.
+pub async fn enrich(
+    generation: &dyn TextGeneration,
+    result: &TranscriptionResult,
+    metadata: &MediaMetadata,
+) -> Result<Enrichment, Error> {
+    let prompt = format!(
+        "Topic: {}\n\
+        Transcript, each line starts with its [mm:ss] timestamp:\n\
+        <transcript>\n{}\n</transcript>\n\n\
+        Reply with a JSON object with these fields:\n\
+        - \"summary\": an executive summary of the whole transcript in one or two paragraphs\n\
+        - \"chapters\": the main sections in order, each an object with \"start_seconds\" \
+        (number), \"title\" and a one sentence \"summary\"\n\
+        - \"keywords\": up to 10 keywords or key phrases",
+        metadata.topic,
+        timestamped_transcript(result)
+    );
+
+    let reply = generation.converse(SYSTEM_PROMPT, &prompt).await?;
+
+    parse_reply(&reply.text)
+}
+
+/// Parses the JSON object out of a reply, ignoring any text the model put around it.
+fn parse_reply(reply: &str) -> Result<Enrichment, Error> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err(Error::from("No JSON object in the model reply")),
+    };
+
+    Ok(serde_json::from_str(json)?)
+}
+
+fn timestamped_transcript(result: &TranscriptionResult) -> String {
+    if !result.results.audio_segments.is_empty() {
+        return result
+            .results
+            .audio_segments
+            .iter()
+            .map(|segment| {
+                let start_time = segment.start_time.parse().unwrap_or_default();
+                format!("[{}] {}", media_timestamp(start_time), segment.transcript)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    let mut lines: Vec<(f64, String)> = Vec::new();
+
+    for item in result.items() {
+        let Some(alternative) = item.alternatives.first() else {
+            continue;
+        };
+        let start_time = item.start_time.as_deref().and_then(|t| t.parse::<f64>().ok());
+
+        match (lines.last_mut(), start_time) {
+            (Some((_, line)), None) => line.push_str(&alternative.content),
+            (Some((line_start, line)), Some(start_time))
+                if start_time < *line_start + LINE_SECONDS =>
+            {
+                line.push(' ');
+                line.push_str(&alternative.content);
+            }
+            (_, start_time) => {
+                lines.push((start_time.unwrap_or_default(), alternative.content.clone()))
+            }
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(start_time, line)| format!("[{}] {}", media_timestamp(start_time), line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_replies_wrapped_in_text() {
+        let enrichment = parse_reply(
+            "Here is the JSON:\n{\"summary\": \"About Lambda.\", \"chapters\": \
+            [{\"start_seconds\": 65, \"title\": \"Intro\"}], \"keywords\": [\"lambda\"]}",
+        )
+            .unwrap();
+
+        assert_eq!(enrichment.summary, "About Lambda.");
+        assert_eq!(enrichment.chapters[0].start_seconds, 65.0);
+        assert_eq!(enrichment.keywords, vec!["lambda".to_string()]);
+
+        assert!(parse_reply("I cannot summarize this.").is_err());
+    }
+
+    #[test]
+    fn timestamps_transcript_lines() {
+        let segmented: TranscriptionResult =
+            serde_json::from_str(include_str!("../fixtures/transcription-result.json")).unwrap();
+        assert_eq!(
+            timestamped_transcript(&segmented),
+            "[00:00] Welcome to the serverless podcast.\n\
+            [00:01] Today we talk about knowledge bases on Bedrock."
+        );
+
+        let plain: TranscriptionResult =
+            serde_json::from_str(include_str!("../fixtures/transcription-result-plain.json"))
+                .unwrap();
+        assert_eq!(timestamped_transcript(&plain), "[00:00] Hello world.");
+
+        assert_eq!(media_timestamp(3725.0), "1:02:05");
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use lambda_runtime::Error;
+
+use shared::ingestion::{
+    IngestedTask, Ingestion, IngestionJobRecord, IngestionKind, IngestionQueue,
+    IngestionRequest,
+};
+use shared::models::DocType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionMode {
+    /// Queue the task for a full data source sync, coalesced by the ingestion lambda.
+    Sync,
+    /// Push only the task's transcript and metadata with `IngestKnowledgeBaseDocuments`.
+    Documents,
+}
+
+impl FromStr for IngestionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(IngestionMode::Sync),
+            "documents" => Ok(IngestionMode::Documents),
+            other => Err(format!(
+                "Invalid ingestion mode {}. Expected sync or documents.",
+                other
+            )),
+        }
+    }
+}
+
+pub struct KnowledgeBaseIngestion {
+    pub mode: IngestionMode,
+    pub queue: Arc<dyn IngestionQueue>,
+    pub ingestion: Arc<dyn Ingestion>,
+}
+
+impl KnowledgeBaseIngestion {
+    /// Returns the record the ingestion tracker should follow when the documents were pushed
+    /// directly. Queued syncs are recorded by the ingestion lambda once their job starts.
+    pub async fn ingest(
+        &self,
+        kb_bucket_name: &str,
+        tenant_id: &str,
+        task_id: &str,
+        documents: &[DocType],
+    ) -> Result<Option<IngestionJobRecord>, Error> {
+        match self.mode {
+            IngestionMode::Sync => {
+                self.queue
+                    .enqueue(&IngestionRequest {
+                        task_id: task_id.to_string(),
+                        tenant_id: tenant_id.to_string(),
+                        deleted: false,
+                    })
+                    .await?;
+                Ok(None)
+            }
+            IngestionMode::Documents => {
+                for doc_type in documents {
+                    self.ingestion
+                        .ingest_document(&format!(
+                            "s3://{}/{}",
+                            kb_bucket_name,
+                            doc_type.kb_key(tenant_id, task_id)
+                        ))
+                        .await?;
+                }
+                Ok(Some(IngestionJobRecord::pending(
+                    task_id.to_string(),
+                    IngestionKind::Documents,
+                    BTreeMap::from([(
+                        task_id.to_string(),
+                        IngestedTask {
+                            tenant_id: tenant_id.to_string(),
+                            documents: documents.to_vec(),
+                        },
+                    )]),
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,866 @@
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::{error, info, warn};
+use serde_json::Value;
+
+use shared::auth::tenant_and_task;
+use shared::cleaning::CleaningOptions;
+use shared::events::{EventPublisher, TaskStatusChanged};
+use shared::generation::TextGeneration;
+use shared::models::{
+    staging_metadata_key, DocType, MediaDetails, MediaMetadata, TranscriptionTiming,
+};
+use shared::storage::{get_json, put_json, ObjectStorage};
+use shared::task::{record_failure, task_record_key, TaskRecord, TaskStatus};
+use shared::transcription::{transcribe_output_key, Transcription, TranscriptionJob};
+use transcription_result::TranscriptionResult;
+
+use crate::cleaning::transcript_text;
+use crate::enrichment::enrich;
+use crate::ingestion::KnowledgeBaseIngestion;
+use crate::transcription_event::{TranscriptionEvent, TranscriptionJobStatus};
+
+pub mod cleaning;
+
+pub mod enrichment;
+
+pub mod ingestion;
+
+pub mod transcription_event;
+
+pub mod transcription_result;
+
+/// Deployment settings of the transcription handler.
+#[derive(Debug, Clone, Default)]
+pub struct HandlerConfig {
+    pub kb_bucket_name: String,
+    pub media_bucket_name: String,
+    /// Cleaning applied to every transcript, uploads can override single options.
+    pub cleaning: CleaningOptions,
+}
+
+pub async fn handle_transcription_job(
+    event: LambdaEvent<Value>,
+    transcription: &dyn Transcription,
+    storage: &dyn ObjectStorage,
+    ingestion: &KnowledgeBaseIngestion,
+    event_publisher: &dyn EventPublisher,
+    generation: Option<&dyn TextGeneration>,
+    config: &HandlerConfig,
+) -> Result<(), Error> {
+    let kb_bucket_name = config.kb_bucket_name.as_str();
+    let media_bucket_name = config.media_bucket_name.as_str();
+
+    info!(payload = %event.payload, "transcription job state change");
+
+    let e: TranscriptionEvent = serde_json::from_value(event.payload)?;
+
+    let job_name = e.job_name().to_string();
+
+    let transcription_job = transcription.get_transcription_job(&job_name).await?;
+
+    // Jobs are named after the task, the tenant is only known from the media folder.
+    let tenant_id = transcription_job
+        .media_uri
+        .as_deref()
+        .and_then(tenant_and_task)
+        .map(|(tenant_id, _)| tenant_id.to_string())
+        .ok_or_else(|| Error::from(format!("No tenant in the media of job {}", job_name)))?;
+
+    if e.status() == TranscriptionJobStatus::Failed {
+        let failure_reason = e.failure_reason().unwrap_or("Transcription job FAILED");
+        warn!(job_name, failure_reason, "transcription job failed");
+
+        return record_failure(
+            storage,
+            event_publisher,
+            media_bucket_name,
+            &tenant_id,
+            &job_name,
+            failure_reason,
+        )
+            .await;
+    }
+
+    match storage
+        .get_object(media_bucket_name, &transcribe_output_key(&job_name))
+        .await
+    {
+        Ok(Some(transcript)) => {
+            let transcription_result: TranscriptionResult = serde_json::from_slice(&transcript)?;
+
+            let metadata: MediaMetadata = get_json(
+                storage,
+                media_bucket_name,
+                &staging_metadata_key(&tenant_id, &job_name),
+            )
+                .await?
+                .ok_or_else(|| Error::from("Staging media metadata not found"))?;
+
+            let details = media_details(&transcription_job, &transcription_result);
+
+            let cleaning = config.cleaning.with_overrides(metadata.cleaning.as_ref());
+            let result = transcript_text(&transcription_result, &cleaning);
+
+            let task_documents = TaskDocuments {
+                kb_bucket_name,
+                tenant_id: &tenant_id,
+                task_id: &job_name,
+                metadata: &metadata,
+                details: &details,
+            };
+
+            task_documents
+                .store(storage, DocType::Transcript, &result)
+                .await?;
+
+            let mut documents = vec![DocType::Transcript];
+            let mut keywords = Vec::new();
+
+            // The transcript is searchable on its own, a failed enrichment only loses the
+            // summary and chapters.
+            if let Some(generation) = generation {
+                match enrich(generation, &transcription_result, &metadata).await {
+                    Ok(enrichment) => {
+                        task_documents
+                            .store(
+                                storage,
+                                DocType::Summary,
+                                &enrichment.summary_document(&metadata),
+                            )
+                            .await?;
+                        documents.push(DocType::Summary);
+
+                        if !enrichment.chapters.is_empty() {
+                            task_documents
+                                .store(
+                                    storage,
+                                    DocType::Chapters,
+                                    &enrichment.chapters_document(&metadata),
+                                )
+                                .await?;
+                            documents.push(DocType::Chapters);
+                        }
+
+                        keywords = enrichment.keywords;
+                    }
+                    Err(err) => warn!({ %err }, "enriching transcript"),
+                }
+            }
+
+            // Frames are described next to the transcription, the visuals document may have
+            // been stored before the details were known.
+            let visuals_key = DocType::Visuals.kb_key(&tenant_id, &job_name);
+            if storage.object_exists(kb_bucket_name, &visuals_key).await? {
+                task_documents
+                    .store_metadata(storage, DocType::Visuals)
+                    .await?;
+                documents.push(DocType::Visuals);
+            }
+
+            let task_record = TaskRecord {
+                task_id: job_name.clone(),
+                tenant_id: tenant_id.clone(),
+                metadata,
+                status: TaskStatus::Transcribed,
+                details,
+                keywords,
+                failure_reason: None,
+                stale_topics: Vec::new(),
+                updated_at: Utc::now(),
+            };
+
+            put_json(
+                storage,
+                media_bucket_name,
+                &task_record_key(&tenant_id, &task_record.task_id),
+                &task_record,
+            )
+                .await?;
+
+            if let Some(record) = ingestion
+                .ingest(kb_bucket_name, &tenant_id, &job_name, &documents)
+                .await?
+            {
+                put_json(storage, media_bucket_name, &record.key(), &record).await?;
+            }
+
+            // Only announced once ingestion is under way, a retried event would announce the
+            // transcript again.
+            event_publisher
+                .task_status_changed(&TaskStatusChanged {
+                    task_id: job_name.clone(),
+                    status: TaskStatus::Transcribed,
+                    transcript_location: Some(format!(
+                        "s3://{}/{}",
+                        kb_bucket_name,
+                        DocType::Transcript.kb_key(&tenant_id, &job_name)
+                    )),
+                    failure_reason: None,
+                })
+                .await?;
+        }
+        Ok(None) => {
+            return Err(Error::from(format!(
+                "Transcribe output for {} not found",
+                job_name
+            )));
+        }
+        Err(err) => {
+            error!({ %err }, "downloading transcription");
+            return Err(err);
+        }
+    };
+
+    Ok(())
+}
+
+/// Technical metadata from the job, completed with what only the transcript tells.
+fn media_details(job: &TranscriptionJob, result: &TranscriptionResult) -> MediaDetails {
+    let language_code = job
+        .language_code
+        .clone()
+        .or_else(|| result.language_code().map(str::to_string));
+
+    MediaDetails {
+        duration_seconds: result.duration_seconds(),
+        language_confidence: job.identified_language_score.or_else(|| {
+            language_code
+                .as_deref()
+                .and_then(|language_code| result.language_score(language_code))
+        }),
+        language_code,
+        speaker_count: result.speaker_count(),
+        word_count: Some(result.word_count()),
+        transcription: Some(TranscriptionTiming {
+            created_at: job.created_at,
+            started_at: job.started_at,
+            completed_at: job.completed_at,
+        }),
+    }
+}
+
+/// Where the knowledge base documents of a task are stored, and the metadata they carry.
+struct TaskDocuments<'a> {
+    kb_bucket_name: &'a str,
+    tenant_id: &'a str,
+    task_id: &'a str,
+    metadata: &'a MediaMetadata,
+    details: &'a MediaDetails,
+}
+
+impl TaskDocuments<'_> {
+    async fn store(
+        &self,
+        storage: &dyn ObjectStorage,
+        doc_type: DocType,
+        content: &str,
+    ) -> Result<(), Error> {
+        self.store_metadata(storage, doc_type).await?;
+
+        storage
+            .put_object(
+                self.kb_bucket_name,
+                &doc_type.kb_key(self.tenant_id, self.task_id),
+                content.as_bytes().to_vec(),
+                "text/plain",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn store_metadata(
+        &self,
+        storage: &dyn ObjectStorage,
+        doc_type: DocType,
+    ) -> Result<(), Error> {
+        put_json(
+            storage,
+            self.kb_bucket_name,
+            &format!("{}.metadata.json", doc_type.kb_key(self.tenant_id, self.task_id)),
+            &self
+                .metadata
+                .to_kb_metadata(self.tenant_id, self.task_id, doc_type, self.details),
+        )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::cleaning::LowConfidence;
+    use shared::events::{InMemoryEventPublisher, TASK_STATUS_CHANGED_DETAIL_TYPE};
+    use shared::generation::InMemoryGeneration;
+    use shared::ingestion::{
+        DocumentState, Ingestion, IngestionJobRecord, IngestionKind, InMemoryIngestion,
+        InMemoryIngestionQueue, IngestionQueue, IngestionRequest,
+    };
+    use shared::storage::InMemoryStorage;
+    use shared::transcription::InMemoryTranscription;
+
+    use crate::ingestion::IngestionMode;
+
+    use super::*;
+
+    const TRANSCRIPT: &[u8] = include_bytes!("../fixtures/transcription-result.json");
+
+    struct Fixture {
+        transcription: InMemoryTranscription,
+        storage: InMemoryStorage,
+        queue: Arc<InMemoryIngestionQueue>,
+        knowledge_base: Arc<InMemoryIngestion>,
+        ingestion: KnowledgeBaseIngestion,
+        event_publisher: InMemoryEventPublisher,
+        generation: Option<InMemoryGeneration>,
+        config: HandlerConfig,
+    }
+
+    impl Fixture {
+        async fn new(mode: IngestionMode) -> Self {
+            let queue = Arc::new(InMemoryIngestionQueue::new());
+            let knowledge_base = Arc::new(InMemoryIngestion::new());
+
+            let fixture = Fixture {
+                transcription: InMemoryTranscription::new(),
+                storage: InMemoryStorage::new(),
+                ingestion: KnowledgeBaseIngestion {
+                    mode,
+                    queue: queue.clone(),
+                    ingestion: knowledge_base.clone(),
+                },
+                queue,
+                knowledge_base,
+                event_publisher: InMemoryEventPublisher::new(),
+                generation: None,
+                config: HandlerConfig {
+                    kb_bucket_name: "kb".to_string(),
+                    media_bucket_name: "media".to_string(),
+                    cleaning: CleaningOptions::default(),
+                },
+            };
+
+            put_json(
+                &fixture.storage,
+                "media",
+                &staging_metadata_key("acme", "task-1"),
+                &MediaMetadata {
+                    topic: "serverless".to_string(),
+                    source_url: "https://example.com/episode-1".to_string(),
+                    date: "2024-07-01".to_string(),
+                    ..Default::default()
+                },
+            )
+                .await
+                .unwrap();
+            fixture.start("task-1").await;
+
+            fixture
+        }
+
+        async fn start(&self, task_id: &str) {
+            self.transcription
+                .start_transcription_job(
+                    task_id,
+                    &format!("s3://media/media-uploads/acme/{}", task_id),
+                    "media",
+                )
+                .await
+                .unwrap();
+        }
+
+        async fn complete(&self, task_id: &str, transcript: &[u8]) {
+            self.transcription.complete(task_id, "en-US");
+            self.storage
+                .put_object(
+                    "media",
+                    &transcribe_output_key(task_id),
+                    transcript.to_vec(),
+                    "application/json",
+                )
+                .await
+                .unwrap();
+        }
+
+        async fn handle(&self, task_id: &str) -> Result<(), Error> {
+            self.handle_event(json!({ "transcriptionJob": task_id })).await
+        }
+
+        async fn handle_event(&self, payload: Value) -> Result<(), Error> {
+            handle_transcription_job(
+                LambdaEvent::new(payload, Context::default()),
+                &self.transcription,
+                &self.storage,
+                &self.ingestion,
+                &self.event_publisher,
+                self.generation
+                    .as_ref()
+                    .map(|generation| generation as &dyn TextGeneration),
+                &self.config,
+            )
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_transcript_and_queues_ingestion() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.complete("task-1", TRANSCRIPT).await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        let transcript = fixture
+            .storage
+            .get_object("kb", "transcripts/acme/task-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(String::from_utf8(transcript)
+            .unwrap()
+            .starts_with("Welcome to the serverless podcast."));
+
+        let kb_metadata: Value =
+            get_json(&fixture.storage, "kb", "transcripts/acme/task-1.metadata.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(kb_metadata["metadataAttributes"]["topic"], "serverless");
+        assert_eq!(kb_metadata["metadataAttributes"]["tenant_id"], "acme");
+        assert_eq!(kb_metadata["metadataAttributes"]["task_id"], "task-1");
+        assert_eq!(kb_metadata["metadataAttributes"]["doc_type"], "transcript");
+        assert_eq!(kb_metadata["metadataAttributes"]["language_code"], "en-US");
+        assert_eq!(kb_metadata["metadataAttributes"]["duration_seconds"], 4.5);
+        assert_eq!(kb_metadata["metadataAttributes"]["word_count"], 13);
+        assert!(!fixture
+            .storage
+            .object_exists("kb", "transcripts/acme/task-1.summary")
+            .await
+            .unwrap());
+
+        let task_record: TaskRecord =
+            get_json(&fixture.storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.status, TaskStatus::Transcribed);
+        assert_eq!(task_record.tenant_id, "acme");
+        assert_eq!(task_record.details.language_code.as_deref(), Some("en-US"));
+        assert_eq!(task_record.details.speaker_count, Some(2));
+        assert_eq!(task_record.details.duration_seconds, Some(4.5));
+        assert_eq!(task_record.details.word_count, Some(13));
+        let timing = task_record.details.transcription.unwrap();
+        assert!(timing.started_at.is_some() && timing.completed_at.is_some());
+
+        assert_eq!(
+            fixture.queue.drain(),
+            vec![IngestionRequest {
+                task_id: "task-1".to_string(),
+                tenant_id: "acme".to_string(),
+                deleted: false,
+            }]
+        );
+
+        let events = fixture.event_publisher.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, TASK_STATUS_CHANGED_DETAIL_TYPE);
+        assert_eq!(events[0].1["status"], "transcribed");
+        assert_eq!(events[0].1["transcriptLocation"], "s3://kb/transcripts/acme/task-1");
+    }
+
+    fn job_state_change(task_id: &str, detail: Value) -> Value {
+        let mut detail = detail;
+        detail["TranscriptionJobName"] = json!(task_id);
+
+        json!({
+            "version": "0",
+            "id": "event-1",
+            "detail-type": "Transcribe Job State Change",
+            "source": "aws.transcribe",
+            "account": "123456789012",
+            "time": "2024-07-01T00:00:00Z",
+            "region": "us-east-1",
+            "resources": [],
+            "detail": detail
+        })
+    }
+
+    #[tokio::test]
+    async fn handles_native_completed_events() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.complete("task-1", TRANSCRIPT).await;
+
+        fixture
+            .handle_event(job_state_change(
+                "task-1",
+                json!({ "TranscriptionJobStatus": "COMPLETED" }),
+            ))
+            .await
+            .unwrap();
+
+        assert!(fixture
+            .storage
+            .object_exists("kb", "transcripts/acme/task-1")
+            .await
+            .unwrap());
+        assert_eq!(fixture.queue.drain().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_native_failed_events() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+
+        fixture
+            .handle_event(job_state_change(
+                "task-1",
+                json!({
+                    "TranscriptionJobStatus": "FAILED",
+                    "FailureReason": "Unsupported media format"
+                }),
+            ))
+            .await
+            .unwrap();
+
+        let task_record: TaskRecord =
+            get_json(&fixture.storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.status, TaskStatus::Failed);
+        assert_eq!(
+            task_record.failure_reason.as_deref(),
+            Some("Unsupported media format")
+        );
+        assert_eq!(task_record.metadata.topic, "serverless");
+
+        let events = fixture.event_publisher.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1["status"], "failed");
+        assert_eq!(events[0].1["failureReason"], "Unsupported media format");
+
+        assert!(fixture.storage.keys("kb").is_empty());
+        assert!(fixture.queue.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_payloads() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+
+        assert!(fixture.handle_event(json!({ "job": "task-1" })).await.is_err());
+        assert!(fixture
+            .handle_event(job_state_change(
+                "task-1",
+                json!({ "TranscriptionJobStatus": "IN_PROGRESS" }),
+            ))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn ingests_documents_directly_and_records_the_pending_job() {
+        let fixture = Fixture::new(IngestionMode::Documents).await;
+        fixture.complete("task-1", TRANSCRIPT).await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        assert!(fixture.queue.drain().is_empty());
+        assert_eq!(
+            fixture
+                .knowledge_base
+                .document_state("s3://kb/transcripts/acme/task-1")
+                .await
+                .unwrap(),
+            DocumentState::Pending
+        );
+
+        let record: IngestionJobRecord = get_json(
+            &fixture.storage,
+            "media",
+            "ingestion-jobs/pending/task-1.json",
+        )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.kind, IngestionKind::Documents);
+        assert_eq!(record.task_ids, vec!["task-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn records_language_confidence_and_refreshes_the_visuals_metadata() {
+        let fixture = Fixture::new(IngestionMode::Documents).await;
+        fixture
+            .storage
+            .put_object(
+                "kb",
+                "transcripts/acme/task-1.visuals",
+                b"On-screen content".to_vec(),
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        fixture
+            .complete(
+                "task-1",
+                include_bytes!("../fixtures/transcription-result-language-identification.json"),
+            )
+            .await;
+        fixture.transcription.complete("task-1", "fr-FR");
+
+        fixture.handle("task-1").await.unwrap();
+
+        let task_record: TaskRecord =
+            get_json(&fixture.storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.details.language_code.as_deref(), Some("fr-FR"));
+        assert_eq!(task_record.details.language_confidence, Some(0.9871));
+
+        let visuals_metadata: Value = get_json(
+            &fixture.storage,
+            "kb",
+            "transcripts/acme/task-1.visuals.metadata.json",
+        )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(visuals_metadata["metadataAttributes"]["language_code"], "fr-FR");
+        assert_eq!(visuals_metadata["metadataAttributes"]["doc_type"], "visuals");
+        assert_eq!(
+            fixture
+                .knowledge_base
+                .document_state("s3://kb/transcripts/acme/task-1.visuals")
+                .await
+                .unwrap(),
+            DocumentState::Pending
+        );
+    }
+
+    #[tokio::test]
+    async fn stores_transcripts_without_speaker_labels() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture
+            .complete(
+                "task-1",
+                include_bytes!("../fixtures/transcription-result-plain.json"),
+            )
+            .await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        let transcript = fixture
+            .storage
+            .get_object("kb", "transcripts/acme/task-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transcript, b"Hello world.");
+
+        let task_record: TaskRecord =
+            get_json(&fixture.storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.details.speaker_count, None);
+        assert_eq!(task_record.details.duration_seconds, Some(0.9));
+    }
+
+    #[tokio::test]
+    async fn cleans_transcripts_with_upload_overrides() {
+        let mut fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.config.cleaning = CleaningOptions {
+            remove_fillers: Some(true),
+            normalize_punctuation: Some(true),
+            ..Default::default()
+        };
+        put_json(
+            &fixture.storage,
+            "media",
+            &staging_metadata_key("acme", "task-1"),
+            &MediaMetadata {
+                topic: "serverless".to_string(),
+                source_url: "https://example.com/episode-1".to_string(),
+                date: "2024-07-01".to_string(),
+                cleaning: Some(CleaningOptions {
+                    min_confidence: Some(0.5),
+                    low_confidence: Some(LowConfidence::Drop),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )
+            .await
+            .unwrap();
+        fixture
+            .complete(
+                "task-1",
+                include_bytes!("../fixtures/transcription-result-noisy.json"),
+            )
+            .await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        let transcript = fixture
+            .storage
+            .get_object("kb", "transcripts/acme/task-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            "So, we shipped the.\nYeah.\nRight, it runs on."
+        );
+    }
+
+    const ENRICHMENT: &str = r#"{
+        "summary": "An episode about knowledge bases on Bedrock.",
+        "chapters": [
+            {"start_seconds": 0, "title": "Welcome", "summary": "The host opens the show."},
+            {"start_seconds": 1.9, "title": "Knowledge bases"}
+        ],
+        "keywords": ["bedrock", "knowledge bases"]
+    }"#;
+
+    #[tokio::test]
+    async fn stores_summary_and_chapters_as_documents() {
+        let mut fixture = Fixture::new(IngestionMode::Documents).await;
+        fixture.generation = Some(InMemoryGeneration::new(ENRICHMENT));
+        fixture.complete("task-1", TRANSCRIPT).await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        let prompts = fixture.generation.as_ref().unwrap().prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0]
+            .1
+            .contains("[00:01] Today we talk about knowledge bases on Bedrock."));
+
+        let summary = fixture
+            .storage
+            .get_object("kb", "transcripts/acme/task-1.summary")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(summary).unwrap(),
+            "Summary of https://example.com/episode-1 (2024-07-01)\n\n\
+            An episode about knowledge bases on Bedrock.\n\n\
+            Keywords: bedrock, knowledge bases"
+        );
+        let chapters = fixture
+            .storage
+            .get_object("kb", "transcripts/acme/task-1.chapters")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(chapters).unwrap(),
+            "Chapters of https://example.com/episode-1\n\n\
+            [00:00] Welcome: The host opens the show.\n\
+            [00:01] Knowledge bases"
+        );
+
+        for (key, doc_type) in [
+            ("transcripts/acme/task-1.summary.metadata.json", "summary"),
+            ("transcripts/acme/task-1.chapters.metadata.json", "chapters"),
+        ] {
+            let kb_metadata: Value = get_json(&fixture.storage, "kb", key)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(kb_metadata["metadataAttributes"]["doc_type"], doc_type);
+            assert_eq!(kb_metadata["metadataAttributes"]["task_id"], "task-1");
+        }
+
+        for uri in [
+            "s3://kb/transcripts/acme/task-1",
+            "s3://kb/transcripts/acme/task-1.summary",
+            "s3://kb/transcripts/acme/task-1.chapters",
+        ] {
+            assert_eq!(
+                fixture.knowledge_base.document_state(uri).await.unwrap(),
+                DocumentState::Pending
+            );
+        }
+
+        let task_record: TaskRecord =
+            get_json(&fixture.storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.keywords, vec!["bedrock", "knowledge bases"]);
+    }
+
+    #[tokio::test]
+    async fn ingests_the_transcript_when_enrichment_fails() {
+        let mut fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.generation = Some(InMemoryGeneration::new("I can't help with that."));
+        fixture.complete("task-1", TRANSCRIPT).await;
+
+        fixture.handle("task-1").await.unwrap();
+
+        assert_eq!(
+            fixture.storage.keys("kb"),
+            vec!["transcripts/acme/task-1", "transcripts/acme/task-1.metadata.json"]
+        );
+        assert_eq!(fixture.queue.drain().len(), 1);
+    }
+
+    struct FailingQueue;
+
+    #[async_trait]
+    impl IngestionQueue for FailingQueue {
+        async fn enqueue(&self, _request: &IngestionRequest) -> Result<(), Error> {
+            Err(Error::from("queue unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn announces_the_transcript_once_ingestion_is_under_way() {
+        let mut fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.complete("task-1", TRANSCRIPT).await;
+        let queue = std::mem::replace(&mut fixture.ingestion.queue, Arc::new(FailingQueue));
+
+        assert!(fixture.handle("task-1").await.is_err());
+        assert!(fixture.event_publisher.events().is_empty());
+
+        fixture.ingestion.queue = queue;
+        fixture.handle("task-1").await.unwrap();
+
+        assert_eq!(fixture.event_publisher.events().len(), 1);
+        assert_eq!(fixture.queue.drain().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fails_without_a_transcript() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+
+        assert!(fixture.handle("task-1").await.is_err());
+        assert!(fixture.storage.keys("kb").is_empty());
+        assert!(fixture.queue.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_on_malformed_transcript() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.complete("task-1", b"not json").await;
+
+        assert!(fixture.handle("task-1").await.is_err());
+        assert!(fixture.storage.keys("kb").is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_without_staging_metadata() {
+        let fixture = Fixture::new(IngestionMode::Sync).await;
+        fixture.start("task-2").await;
+        fixture.complete("task-2", TRANSCRIPT).await;
+
+        assert!(fixture.handle("task-2").await.is_err());
+        assert!(fixture.event_publisher.events().is_empty());
+        assert!(fixture.queue.drain().is_empty());
+    }
+}
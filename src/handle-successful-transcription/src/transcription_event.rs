@@ -0,0 +1,56 @@
+use aws_lambda_events::event::eventbridge::EventBridgeEvent;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TranscriptionJobStatus {
+    Completed,
+    Failed,
+}
+
+/// Detail of the native `aws.transcribe` "Transcribe Job State Change" event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TranscribeJobStateChange {
+    pub transcription_job_name: String,
+    pub transcription_job_status: TranscriptionJobStatus,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+}
+
+/// The shape left by the EventBridge `input_transformer`, only ever sent for completed jobs.
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionSuccessEvent {
+    pub transcription_job: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum TranscriptionEvent {
+    Native(Box<EventBridgeEvent<TranscribeJobStateChange>>),
+    Transformed(TranscriptionSuccessEvent),
+}
+
+impl TranscriptionEvent {
+    pub fn job_name(&self) -> &str {
+        match self {
+            TranscriptionEvent::Native(event) => &event.detail.transcription_job_name,
+            TranscriptionEvent::Transformed(event) => &event.transcription_job,
+        }
+    }
+
+    pub fn status(&self) -> TranscriptionJobStatus {
+        match self {
+            TranscriptionEvent::Native(event) => event.detail.transcription_job_status,
+            TranscriptionEvent::Transformed(_) => TranscriptionJobStatus::Completed,
+        }
+    }
+
+    pub fn failure_reason(&self) -> Option<&str> {
+        match self {
+            TranscriptionEvent::Native(event) => event.detail.failure_reason.as_deref(),
+            TranscriptionEvent::Transformed(_) => None,
+        }
+    }
+}
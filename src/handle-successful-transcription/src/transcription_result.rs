@@ -1,23 +1,100 @@
 use serde::{Deserialize, Serialize};
 
+/// Transcribe output JSON.
+///
+/// Only `transcripts` is present in every job output. Speaker labels, channel labels and language
+/// identification depend on the job settings, so they are optional here.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TranscriptionResult {
     pub job_name: String,
+    #[serde(default)]
     pub account_id: String,
     pub status: String,
     pub results: Results,
 }
 
+impl TranscriptionResult {
+    /// Media duration approximated by the end time of the last timed item.
+    pub fn duration_seconds(&self) -> Option<f64> {
+        self.items()
+            .filter_map(|item| item.end_time.as_deref())
+            .filter_map(|end_time| end_time.parse::<f64>().ok())
+            .reduce(f64::max)
+    }
+
+    /// Number of speakers, only known when the job ran with speaker diarization.
+    pub fn speaker_count(&self) -> Option<i64> {
+        self.results
+            .speaker_labels
+            .as_ref()
+            .map(|speaker_labels| speaker_labels.speakers)
+    }
+
+    /// Language of the media, the dominant one when the job identified several.
+    pub fn language_code(&self) -> Option<&str> {
+        if let Some(language_code) = self.results.language_code.as_deref() {
+            return Some(language_code);
+        }
+
+        self.results
+            .language_codes
+            .iter()
+            .max_by(|a, b| a.duration_in_seconds.total_cmp(&b.duration_in_seconds))
+            .map(|language| language.language_code.as_str())
+    }
+
+    /// Identification score of `language_code`, when the job identified the language.
+    pub fn language_score(&self, language_code: &str) -> Option<f64> {
+        self.results
+            .language_identification
+            .iter()
+            .find(|language| language.code == language_code)
+            .and_then(|language| language.score.parse().ok())
+    }
+
+    /// Number of recognized words, punctuation excluded.
+    pub fn word_count(&self) -> i64 {
+        self.items()
+            .filter(|item| item.type_field == "pronunciation")
+            .count() as i64
+    }
+
+    /// Every recognized item, from the per channel lists when the job used channel identification
+    /// and did not fill in `items`.
+    pub fn items(&self) -> Box<dyn Iterator<Item = &Item2> + '_> {
+        match &self.results.channel_labels {
+            Some(channel_labels) if self.results.items.is_empty() => Box::new(
+                channel_labels
+                    .channels
+                    .iter()
+                    .flat_map(|channel| channel.items.iter()),
+            ),
+            _ => Box::new(self.results.items.iter()),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Results {
     pub transcripts: Vec<Transcript>,
     #[serde(rename = "speaker_labels")]
-    pub speaker_labels: SpeakerLabels,
+    pub speaker_labels: Option<SpeakerLabels>,
+    #[serde(rename = "channel_labels")]
+    pub channel_labels: Option<ChannelLabels>,
+    #[serde(default)]
     pub items: Vec<Item2>,
-    #[serde(rename = "audio_segments")]
+    #[serde(rename = "audio_segments", default)]
     pub audio_segments: Vec<AudioSegment>,
+    /// Set when the job identified a single language.
+    #[serde(rename = "language_code")]
+    pub language_code: Option<String>,
+    #[serde(rename = "language_identification", default)]
+    pub language_identification: Vec<LanguageIdentification>,
+    /// Set when the job identified multiple languages.
+    #[serde(rename = "language_codes", default)]
+    pub language_codes: Vec<LanguageCode>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -31,7 +108,7 @@ pub struct Transcript {
 pub struct SpeakerLabels {
     pub segments: Vec<Segment>,
     #[serde(rename = "channel_label")]
-    pub channel_label: String,
+    pub channel_label: Option<String>,
     pub speakers: i64,
 }
 
@@ -58,10 +135,27 @@ pub struct Item {
     pub end_time: String,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelLabels {
+    pub channels: Vec<Channel>,
+    #[serde(rename = "number_of_channels")]
+    pub number_of_channels: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    #[serde(rename = "channel_label")]
+    pub channel_label: String,
+    pub items: Vec<Item2>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Item2 {
-    pub id: i64,
+    /// Missing on the per channel items of older outputs.
+    pub id: Option<i64>,
     #[serde(rename = "type")]
     pub type_field: String,
     pub alternatives: Vec<Alterna>,
@@ -70,7 +164,13 @@ pub struct Item2 {
     #[serde(rename = "end_time")]
     pub end_time: Option<String>,
     #[serde(rename = "speaker_label")]
-    pub speaker_label: String,
+    pub speaker_label: Option<String>,
+    #[serde(rename = "channel_label")]
+    pub channel_label: Option<String>,
+    #[serde(rename = "language_code")]
+    pub language_code: Option<String>,
+    #[serde(rename = "vocabulary_filter_match")]
+    pub vocabulary_filter_match: Option<bool>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -78,6 +178,18 @@ pub struct Item2 {
 pub struct Alterna {
     pub confidence: String,
     pub content: String,
+    /// Present when the job redacted this item, `content` is then the `[PII]` placeholder.
+    #[serde(default)]
+    pub redactions: Vec<Redaction>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Redaction {
+    pub confidence: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub category: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -90,6 +202,117 @@ pub struct AudioSegment {
     #[serde(rename = "end_time")]
     pub end_time: String,
     #[serde(rename = "speaker_label")]
-    pub speaker_label: String,
+    pub speaker_label: Option<String>,
+    #[serde(rename = "channel_label")]
+    pub channel_label: Option<String>,
+    #[serde(rename = "language_code")]
+    pub language_code: Option<String>,
     pub items: Vec<i64>,
 }
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageIdentification {
+    pub code: String,
+    pub score: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageCode {
+    #[serde(rename = "language_code")]
+    pub language_code: String,
+    #[serde(rename = "duration_in_seconds")]
+    pub duration_in_seconds: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(fixture: &str) -> TranscriptionResult {
+        serde_json::from_str(fixture).unwrap()
+    }
+
+    #[test]
+    fn parses_diarized_output() {
+        let result = parse(include_str!("../fixtures/transcription-result.json"));
+
+        assert_eq!(result.speaker_count(), Some(2));
+        assert_eq!(result.duration_seconds(), Some(4.5));
+        assert_eq!(result.language_code(), None);
+        assert_eq!(result.word_count(), 13);
+        assert_eq!(
+            result.results.items[0].speaker_label.as_deref(),
+            Some("spk_0")
+        );
+    }
+
+    #[test]
+    fn parses_output_without_speaker_labels() {
+        let result = parse(include_str!("../fixtures/transcription-result-plain.json"));
+
+        assert_eq!(result.results.speaker_labels, None);
+        assert!(result.results.audio_segments.is_empty());
+        assert_eq!(result.speaker_count(), None);
+        assert_eq!(result.duration_seconds(), Some(0.9));
+        assert_eq!(result.results.items[2].start_time, None);
+    }
+
+    #[test]
+    fn parses_channel_identification_output() {
+        let result = parse(include_str!("../fixtures/transcription-result-channels.json"));
+
+        let channel_labels = result.results.channel_labels.as_ref().unwrap();
+        assert_eq!(channel_labels.number_of_channels, 2);
+        assert_eq!(channel_labels.channels[1].channel_label, "ch_1");
+        assert_eq!(channel_labels.channels[0].items[0].id, None);
+        assert_eq!(
+            result.results.audio_segments[1].channel_label.as_deref(),
+            Some("ch_1")
+        );
+
+        assert_eq!(result.items().count(), 7);
+        assert_eq!(result.word_count(), 5);
+        assert_eq!(result.duration_seconds(), Some(1.9));
+        assert_eq!(result.speaker_count(), None);
+    }
+
+    #[test]
+    fn parses_language_identification_output() {
+        let result = parse(include_str!(
+            "../fixtures/transcription-result-language-identification.json"
+        ));
+
+        assert_eq!(result.language_code(), Some("fr-FR"));
+        assert_eq!(result.results.language_identification.len(), 2);
+        assert_eq!(result.results.language_identification[0].score, "0.9871");
+        assert_eq!(result.language_score("fr-FR"), Some(0.9871));
+        assert_eq!(result.language_score("de-DE"), None);
+    }
+
+    #[test]
+    fn parses_multi_language_output() {
+        let result = parse(include_str!(
+            "../fixtures/transcription-result-multi-language.json"
+        ));
+
+        assert_eq!(result.language_code(), Some("en-US"));
+        assert_eq!(result.results.language_codes.len(), 2);
+        assert_eq!(
+            result.results.items[3].language_code.as_deref(),
+            Some("es-US")
+        );
+    }
+
+    #[test]
+    fn parses_redacted_output() {
+        let result = parse(include_str!("../fixtures/transcription-result-redacted.json"));
+
+        let redacted = &result.results.items[3].alternatives[0];
+        assert_eq!(redacted.content, "[PII]");
+        assert_eq!(redacted.redactions[0].category, "PII");
+        assert!(result.results.items[0].alternatives[0].redactions.is_empty());
+        assert_eq!(result.results.speaker_labels.unwrap().channel_label, None);
+    }
+}
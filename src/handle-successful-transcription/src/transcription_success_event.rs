@@ -1,7 +0,0 @@
-use serde::Deserialize;
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TranscriptionSuccessEvent {
-    pub transcription_job: String,
-}
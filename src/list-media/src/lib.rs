@@ -0,0 +1,407 @@
+use futures::future::try_join_all;
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+use serde_json::json;
+
+use shared::auth::{authorize, AccessPolicy, Caller};
+use shared::error::ApiError;
+use shared::storage::{get_json, ObjectStorage};
+use shared::task::{task_id_from_record_key, task_index_prefix, task_record_key, TaskRecord};
+
+use crate::listing_query::ListingQuery;
+
+mod listing_query;
+
+/// Serves `GET /media`, and the status of a single task on `GET /media/{task_id}`.
+pub async fn list_media(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, Error> {
+    let result = match authorize(&event, policy) {
+        Ok(caller) => match event.path_parameters_ref().and_then(|p| p.first("task_id")) {
+            Some(task_id) => get_task(storage, media_bucket_name, &caller, task_id).await,
+            None => list_page(&event, storage, media_bucket_name, &caller).await,
+        },
+        Err(err) => Err(err),
+    };
+
+    result.or_else(ApiError::into_response)
+}
+
+async fn get_task(
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    caller: &Caller,
+    task_id: &str,
+) -> Result<Response<Body>, ApiError> {
+    let record: TaskRecord = get_json(
+        storage,
+        media_bucket_name,
+        &task_record_key(&caller.tenant_id, task_id),
+    )
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No media for task {}", task_id)))?;
+
+    if !caller.can_manage(record.metadata.owner.as_deref()) {
+        return Err(ApiError::AccessDenied(format!(
+            "Only the owner of task {} or an admin can see it",
+            task_id
+        )));
+    }
+
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(json!(record).to_string().into())?)
+}
+
+async fn list_page(
+    event: &Request,
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    caller: &Caller,
+) -> Result<Response<Body>, ApiError> {
+    let query = ListingQuery::from_query_map(&event.query_string_parameters())
+        .map_err(ApiError::BadRequest)?;
+
+    let (items, next_cursor) =
+        list_task_records(storage, media_bucket_name, caller, &query).await?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(
+            json!({
+                "items": items,
+                "next_cursor": next_cursor
+            })
+                .to_string()
+                .into(),
+        )?)
+}
+
+/// Walks the index in key order starting after `query.cursor` until a full page of matching
+/// records is collected. The returned cursor is the task id of the last record examined.
+/// Only the index entries of the caller's tenant are listed, and only admins see the media
+/// uploaded by other users of their tenant. Entries deleted since the listing are skipped.
+///
+/// Filters are applied while walking, there is no index on them: a page reads one record per
+/// entry examined, and a filter matching few records reads the tenant's whole index.
+async fn list_task_records(
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+    caller: &Caller,
+    query: &ListingQuery,
+) -> Result<(Vec<TaskRecord>, Option<String>), Error> {
+    let mut items = Vec::new();
+    let prefix = task_index_prefix(&caller.tenant_id);
+    let mut start_after = query
+        .cursor
+        .as_deref()
+        .map(|task_id| task_record_key(&caller.tenant_id, task_id));
+
+    loop {
+        let page = storage
+            .list_keys(
+                media_bucket_name,
+                &prefix,
+                start_after.as_deref(),
+                query.limit,
+            )
+            .await?;
+
+        let records = try_join_all(
+            page.keys
+                .iter()
+                .map(|key| get_json::<TaskRecord>(storage, media_bucket_name, key)),
+        )
+            .await?;
+
+        for (key, record) in page.keys.iter().zip(records) {
+            let Some(record) = record else {
+                continue;
+            };
+
+            if caller.can_manage(record.metadata.owner.as_deref())
+                && query.matches(&record)
+            {
+                items.push(record);
+            }
+
+            if items.len() == query.limit {
+                let cursor = task_id_from_record_key(&caller.tenant_id, key);
+                return Ok((items, cursor.map(str::to_string)));
+            }
+        }
+
+        match (page.is_truncated, page.keys.last()) {
+            (true, Some(last_key)) => start_after = Some(last_key.clone()),
+            _ => return Ok((items, None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+    use serde_json::Value;
+
+    use shared::auth::with_claims;
+    use shared::models::{MediaDetails, MediaMetadata};
+    use shared::storage::{put_json, InMemoryStorage};
+    use shared::task::TaskStatus;
+
+    use super::*;
+
+    async fn put_record(
+        storage: &InMemoryStorage,
+        tenant_id: &str,
+        owner: &str,
+        task_id: &str,
+        topic: &str,
+        date: &str,
+        language_code: Option<&str>,
+    ) {
+        let record = TaskRecord {
+            task_id: task_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            metadata: MediaMetadata {
+                topic: topic.to_string(),
+                source_url: format!("https://example.com/{}", task_id),
+                date: date.to_string(),
+                owner: Some(owner.to_string()),
+                ..Default::default()
+            },
+            status: TaskStatus::Indexed,
+            details: MediaDetails {
+                language_code: language_code.map(str::to_string),
+                ..Default::default()
+            },
+            keywords: Vec::new(),
+            failure_reason: None,
+            stale_topics: Vec::new(),
+            updated_at: Utc::now(),
+        };
+        put_json(storage, "media", &task_record_key(tenant_id, task_id), &record)
+            .await
+            .unwrap();
+    }
+
+    async fn storage_with_records(records: &[(&str, &str, &str)]) -> InMemoryStorage {
+        let storage = InMemoryStorage::new();
+
+        for (task_id, topic, date) in records {
+            put_record(&storage, "acme", "user-1", task_id, topic, date, None).await;
+        }
+
+        storage
+    }
+
+    const USER: &[(&str, &str)] = &[("sub", "user-1"), ("custom:tenant_id", "acme")];
+
+    const ADMIN: &[(&str, &str)] = &[
+        ("sub", "user-2"),
+        ("custom:tenant_id", "acme"),
+        ("cognito:groups", "[admin]"),
+    ];
+
+    async fn list(storage: &InMemoryStorage, params: &[(&str, &str)]) -> (u16, Value) {
+        list_as(storage, USER, params).await
+    }
+
+    async fn list_as(
+        storage: &InMemoryStorage,
+        claims: &[(&str, &str)],
+        params: &[(&str, &str)],
+    ) -> (u16, Value) {
+        let event = lambda_http::http::Request::builder()
+            .method("GET")
+            .uri("/media")
+            .body(Body::Empty)
+            .unwrap();
+        let event = with_claims(event, claims).with_query_string_parameters(
+            params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        );
+
+        send(storage, event).await
+    }
+
+    async fn get_as(
+        storage: &InMemoryStorage,
+        claims: &[(&str, &str)],
+        task_id: &str,
+    ) -> (u16, Value) {
+        let event = lambda_http::http::Request::builder()
+            .method("GET")
+            .uri(format!("/media/{}", task_id))
+            .body(Body::Empty)
+            .unwrap();
+        let event = with_claims(event, claims).with_path_parameters(HashMap::from([(
+            "task_id".to_string(),
+            task_id.to_string(),
+        )]));
+
+        send(storage, event).await
+    }
+
+    async fn send(storage: &InMemoryStorage, event: Request) -> (u16, Value) {
+        let policy = AccessPolicy {
+            required_scope: None,
+            admin_group: "admin".to_string(),
+        };
+
+        let response = list_media(event, storage, "media", &policy).await.unwrap();
+        (
+            response.status().as_u16(),
+            serde_json::from_slice(response.body()).unwrap(),
+        )
+    }
+
+    fn task_ids(body: &Value) -> Vec<&str> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["taskId"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_through_records_with_the_cursor() {
+        let storage = storage_with_records(&[
+            ("a", "serverless", "2024-07-01"),
+            ("b", "serverless", "2024-07-02"),
+            ("c", "serverless", "2024-07-03"),
+        ])
+            .await;
+
+        let (status, body) = list(&storage, &[("limit", "2")]).await;
+        assert_eq!(status, 200);
+        assert_eq!(task_ids(&body), vec!["a", "b"]);
+        assert_eq!(body["next_cursor"], "b");
+
+        let (_, body) = list(&storage, &[("limit", "2"), ("cursor", "b")]).await;
+        assert_eq!(task_ids(&body), vec!["c"]);
+        assert_eq!(body["next_cursor"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn filters_by_topic_and_date_range_across_pages() {
+        let storage = storage_with_records(&[
+            ("a", "serverless", "2024-07-01"),
+            ("b", "containers", "2024-07-02"),
+            ("c", "serverless", "2024-08-01"),
+            ("d", "serverless", "2024-07-15"),
+        ])
+            .await;
+
+        let (_, body) = list(
+            &storage,
+            &[
+                ("topic", "serverless"),
+                ("from", "2024-07-01"),
+                ("to", "2024-07-31"),
+                ("limit", "1"),
+            ],
+        )
+            .await;
+        assert_eq!(task_ids(&body), vec!["a"]);
+
+        let (_, body) = list(
+            &storage,
+            &[
+                ("topic", "serverless"),
+                ("from", "2024-07-01"),
+                ("to", "2024-07-31"),
+                ("limit", "1"),
+                ("cursor", "a"),
+            ],
+        )
+            .await;
+        assert_eq!(task_ids(&body), vec!["d"]);
+    }
+
+    #[tokio::test]
+    async fn filters_by_language() {
+        let storage = storage_with_records(&[("a", "serverless", "2024-07-01")]).await;
+        for (task_id, date, language_code) in [
+            ("b", "2024-07-02", "fr-FR"),
+            ("c", "2024-07-03", "en-US"),
+            ("d", "2024-07-04", "en-GB"),
+        ] {
+            put_record(&storage, "acme", "user-1", task_id, "serverless", date, Some(language_code))
+                .await;
+        }
+
+        let (_, body) = list(&storage, &[("language", "fr-FR")]).await;
+        assert_eq!(task_ids(&body), vec!["b"]);
+
+        let (_, body) = list(&storage, &[("language", "en")]).await;
+        assert_eq!(task_ids(&body), vec!["c", "d"]);
+    }
+
+    #[tokio::test]
+    async fn lists_only_the_callers_tenant() {
+        let storage = storage_with_records(&[("a", "serverless", "2024-07-01")]).await;
+        put_record(&storage, "globex", "user-1", "b", "serverless", "2024-07-02", None).await;
+        put_record(&storage, "acme", "user-1", "c", "serverless", "2024-07-03", None).await;
+
+        let (_, body) = list(&storage, &[("limit", "2")]).await;
+        assert_eq!(task_ids(&body), vec!["a", "c"]);
+        assert_eq!(body["next_cursor"], "c");
+    }
+
+    #[tokio::test]
+    async fn lists_other_users_media_to_admins_only() {
+        let storage = storage_with_records(&[("a", "serverless", "2024-07-01")]).await;
+        put_record(&storage, "acme", "user-3", "b", "serverless", "2024-07-02", None).await;
+
+        let (_, body) = list(&storage, &[]).await;
+        assert_eq!(task_ids(&body), vec!["a"]);
+
+        let (_, body) = list_as(&storage, ADMIN, &[]).await;
+        assert_eq!(task_ids(&body), vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn returns_the_status_of_a_task_to_its_owner_and_admins() {
+        let storage = storage_with_records(&[("a", "serverless", "2024-07-01")]).await;
+        put_record(&storage, "globex", "user-1", "b", "serverless", "2024-07-02", None).await;
+
+        let (status, body) = get_as(&storage, USER, "a").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["taskId"], "a");
+        assert_eq!(body["status"], "indexed");
+
+        assert_eq!(get_as(&storage, ADMIN, "a").await.0, 200);
+        assert_eq!(
+            get_as(&storage, &[("sub", "user-3"), ("custom:tenant_id", "acme")], "a")
+                .await
+                .0,
+            403
+        );
+        assert_eq!(get_as(&storage, USER, "b").await.0, 404);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_parameters() {
+        let storage = storage_with_records(&[]).await;
+
+        let (status, problem) = list(&storage, &[("limit", "0")]).await;
+        assert_eq!(status, 400);
+        assert_eq!(problem["detail"], "limit must be between 1 and 100");
+        assert_eq!(list(&storage, &[("from", "July")]).await.0, 400);
+        assert_eq!(
+            list(&storage, &[("from", "2024-08-01"), ("to", "2024-07-01")])
+                .await
+                .0,
+            400
+        );
+    }
+}
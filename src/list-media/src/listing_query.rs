@@ -0,0 +1,97 @@
+use chrono::NaiveDate;
+use lambda_http::aws_lambda_events::query_map::QueryMap;
+
+use shared::task::TaskRecord;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ListingQuery {
+    pub topic: Option<String>,
+    /// A language code such as `en-US`, or only its language such as `en`.
+    pub language: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub limit: usize,
+    pub cursor: Option<String>,
+}
+
+impl ListingQuery {
+    pub fn from_query_map(params: &QueryMap) -> Result<Self, String> {
+        let limit = match params.first("limit") {
+            Some(limit) => match limit.parse::<usize>() {
+                Ok(limit) if (1..=MAX_LIMIT).contains(&limit) => limit,
+                _ => return Err(format!("limit must be between 1 and {}", MAX_LIMIT)),
+            },
+            None => DEFAULT_LIMIT,
+        };
+
+        let query = ListingQuery {
+            topic: params.first("topic").map(str::to_string),
+            language: params.first("language").map(str::to_string),
+            from: parse_date(params.first("from"), "from")?,
+            to: parse_date(params.first("to"), "to")?,
+            limit,
+            cursor: params.first("cursor").map(str::to_string),
+        };
+
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from > to {
+                return Err("from must not be after to".to_string());
+            }
+        }
+
+        Ok(query)
+    }
+
+    pub fn matches(&self, record: &TaskRecord) -> bool {
+        if let Some(topic) = &self.topic {
+            if &record.metadata.topic != topic {
+                return false;
+            }
+        }
+
+        if let Some(language) = &self.language {
+            if !record
+                .details
+                .language_code
+                .as_deref()
+                .is_some_and(|language_code| matches_language(language_code, language))
+            {
+                return false;
+            }
+        }
+
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+
+        let date = match NaiveDate::parse_from_str(&record.metadata.date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return false,
+        };
+
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+fn matches_language(language_code: &str, language: &str) -> bool {
+    language_code.eq_ignore_ascii_case(language)
+        || language_code
+            .split_once('-')
+            .is_some_and(|(primary, _)| primary.eq_ignore_ascii_case(language))
+}
+
+fn parse_date(value: Option<&str>, name: &str) -> Result<Option<NaiveDate>, String> {
+    value
+        .map(|v| {
+            NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| {
+                format!(
+                    "Invalid {} date {}. Expected format is yyyy-MM-dd.",
+                    name, v
+                )
+            })
+        })
+        .transpose()
+}
@@ -0,0 +1,368 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aws_config::BehaviorVersion;
+use aws_lambda_events::event::s3::S3Event;
+use aws_lambda_events::event::sqs::SqsEvent;
+use aws_sdk_s3::config::Region;
+use lambda_http::{Body, Request, Response};
+use lambda_runtime::{Context, Error, LambdaEvent};
+use serde_json::{json, Map, Value};
+
+use handle_successful_transcription::ingestion::{IngestionMode, KnowledgeBaseIngestion};
+use handle_successful_transcription::HandlerConfig;
+use query_knowledge_base::quota::QuotaConfig;
+use query_knowledge_base::QueryHandler;
+use shared::auth::{with_claims, AccessPolicy};
+use shared::cache::InMemoryAnswerCache;
+use shared::cleaning::CleaningOptions;
+use shared::events::InMemoryEventPublisher;
+use shared::federation::KnowledgeBases;
+use shared::generation::InMemoryGeneration;
+use shared::ingestion::{InMemoryIngestion, InMemoryIngestionQueue};
+use shared::models::{media_upload_key, DocType};
+use shared::retrieval::{InMemoryRetrieval, Reference, Retrieval};
+use shared::storage::{get_json, InMemoryStorage, ObjectStorage, S3Storage};
+use shared::transcription::{transcribe_output_key, InMemoryTranscription};
+
+const MEDIA_BUCKET: &str = "local-media";
+const KB_BUCKET: &str = "local-kb";
+
+const DEFAULT_TRANSCRIPT: &[u8] =
+    include_bytes!("../../handle-successful-transcription/fixtures/transcription-result.json");
+
+/// Canned model reply, the summary and chapter documents are built from it.
+const LOCAL_ENRICHMENT: &str = r#"{
+    "summary": "A local run of the media pipeline.",
+    "chapters": [{"start_seconds": 0, "title": "Local media"}],
+    "keywords": ["local"]
+}"#;
+
+struct Options {
+    media: Option<String>,
+    transcript: Option<String>,
+    cleaning: CleaningOptions,
+    tenant: String,
+    topic: String,
+    question: String,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, Error> {
+        let mut options = Options {
+            media: None,
+            transcript: None,
+            cleaning: CleaningOptions::default(),
+            tenant: "local".to_string(),
+            topic: "serverless".to_string(),
+            question: "What is this episode about?".to_string(),
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--media" => options.media = Some(value()?),
+                "--transcript" => options.transcript = Some(value()?),
+                "--cleaning" => options.cleaning = serde_json::from_str(&value()?)?,
+                "--tenant" => options.tenant = value()?,
+                "--topic" => options.topic = value()?,
+                "--question" => options.question = value()?,
+                other => return Err(Error::from(format!("Unknown argument {}", other))),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Runs upload, transcription, ingestion and query end to end with the lambda handlers
+/// in-process. S3 is kept in memory unless `LOCAL_S3_ENDPOINT` points to an S3 compatible
+/// store such as MinIO; Transcribe, SQS, EventBridge and Bedrock always are.
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_max_level(lambda_runtime::tracing::Level::INFO)
+        .with_target(false)
+        .init();
+
+    let options = Options::from_args()?;
+
+    let shared_storage = local_storage().await?;
+    let storage = shared_storage.as_ref();
+
+    let transcript = match &options.transcript {
+        Some(path) => tokio::fs::read(path).await?,
+        None => DEFAULT_TRANSCRIPT.to_vec(),
+    };
+
+    let transcription = InMemoryTranscription::new();
+    let ingestion_queue = Arc::new(InMemoryIngestionQueue::new());
+    let ingestion = Arc::new(InMemoryIngestion::new());
+    let event_publisher = InMemoryEventPublisher::new();
+    let generation = InMemoryGeneration::new(LOCAL_ENRICHMENT);
+    let knowledge_base_ingestion = KnowledgeBaseIngestion {
+        mode: IngestionMode::Sync,
+        queue: ingestion_queue.clone(),
+        ingestion: ingestion.clone(),
+    };
+
+    // Upload
+    let response = create_media_upload_link::create_media_upload_link(
+        json_request(
+            "POST",
+            "/media",
+            &options.tenant,
+            json!({
+                "topic": options.topic,
+                "sourceUrl": "https://example.com/local-media",
+                "date": "2024-07-01"
+            }),
+        ),
+        storage,
+        MEDIA_BUCKET,
+        &AccessPolicy::default(),
+    )
+        .await?;
+    let task_id = response_json(response)?["task_id"]
+        .as_str()
+        .ok_or("No task_id in upload link response")?
+        .to_string();
+    println!("created upload link for task {}", task_id);
+
+    let media = match &options.media {
+        Some(path) => tokio::fs::read(path).await?,
+        None => b"local media".to_vec(),
+    };
+    let media_key = media_upload_key(&options.tenant, &task_id);
+    storage
+        .put_object(MEDIA_BUCKET, &media_key, media, "application/octet-stream")
+        .await?;
+    println!("uploaded media to s3://{}/{}", MEDIA_BUCKET, media_key);
+
+    // Transcription
+    let s3_event: S3Event = serde_json::from_value(json!({
+        "Records": [{
+            "eventTime": "2024-07-01T00:00:00Z",
+            "eventName": "ObjectCreated:Put",
+            "userIdentity": { "principalId": "local" },
+            "requestParameters": { "sourceIPAddress": "127.0.0.1" },
+            "s3": {
+                "bucket": { "name": MEDIA_BUCKET },
+                "object": { "key": media_key }
+            }
+        }]
+    }))?;
+    start_transcription_job::start_transcription_job(
+        LambdaEvent::new(s3_event, Context::default()),
+        &transcription,
+    )
+        .await?;
+    println!("started {} transcription job(s)", transcription.jobs().len());
+
+    // Transcribe writes its output next to the media, the stand-in leaves that to us.
+    transcription.complete(&task_id, "en-US");
+    storage
+        .put_object(
+            MEDIA_BUCKET,
+            &transcribe_output_key(&task_id),
+            transcript,
+            "application/json",
+        )
+        .await?;
+
+    handle_successful_transcription::handle_transcription_job(
+        LambdaEvent::new(
+            json!({
+                "version": "0",
+                "id": "local",
+                "detail-type": "Transcribe Job State Change",
+                "source": "aws.transcribe",
+                "account": "000000000000",
+                "time": "2024-07-01T00:00:00Z",
+                "region": "us-east-1",
+                "resources": [],
+                "detail": {
+                    "TranscriptionJobName": task_id,
+                    "TranscriptionJobStatus": "COMPLETED"
+                }
+            }),
+            Context::default(),
+        ),
+        &transcription,
+        storage,
+        &knowledge_base_ingestion,
+        &event_publisher,
+        Some(&generation),
+        &HandlerConfig {
+            kb_bucket_name: KB_BUCKET.to_string(),
+            media_bucket_name: MEDIA_BUCKET.to_string(),
+            cleaning: options.cleaning.clone(),
+        },
+    )
+        .await?;
+    println!(
+        "stored transcript at s3://{}/{}",
+        KB_BUCKET,
+        DocType::Transcript.kb_key(&options.tenant, &task_id)
+    );
+
+    // Ingestion
+    let messages: Vec<Value> = ingestion_queue
+        .drain()
+        .iter()
+        .enumerate()
+        .map(|(i, request)| {
+            Ok(json!({ "messageId": i.to_string(), "body": serde_json::to_string(request)? }))
+        })
+        .collect::<Result<_, Error>>()?;
+    let sqs_event: SqsEvent = serde_json::from_value(json!({ "Records": messages }))?;
+
+    let mut context = Context::default();
+    context.deadline = (SystemTime::now() + Duration::from_secs(900))
+        .duration_since(UNIX_EPOCH)?
+        .as_millis() as u64;
+
+    start_ingestion_job::start_ingestion_job(
+        LambdaEvent::new(sqs_event, context),
+        ingestion.as_ref(),
+        storage,
+        MEDIA_BUCKET,
+    )
+        .await?;
+    println!("started ingestion job for {} queued task(s)", messages.len());
+
+    // Query
+    let mut documents = Vec::new();
+    for doc_type in DocType::ALL {
+        let key = doc_type.kb_key(&options.tenant, &task_id);
+        if let Some(document) = get_knowledge_base_document(storage, &key).await? {
+            documents.push(document);
+        }
+    }
+    let query_handler = QueryHandler {
+        knowledge_bases: KnowledgeBases {
+            default_name: "media".to_string(),
+            retrievals: BTreeMap::from([(
+                "media".to_string(),
+                Arc::new(InMemoryRetrieval::new(documents)) as Arc<dyn Retrieval>,
+            )]),
+            generation: Arc::new(InMemoryGeneration::new("A local answer.")),
+            generation_model: "in-memory".to_string(),
+            max_passages: 10,
+        },
+        cache: Arc::new(InMemoryAnswerCache::new()),
+        storage: shared_storage.clone(),
+        policy: AccessPolicy::default(),
+        quotas: QuotaConfig {
+            bucket_name: MEDIA_BUCKET.to_string(),
+            burst: 10,
+            queries_per_minute: 30,
+            monthly_token_budget: None,
+        },
+        analytics_bucket_name: MEDIA_BUCKET.to_string(),
+    };
+
+    let response = query_knowledge_base::query_knowledge_base(
+        json_request(
+            "POST",
+            "/query",
+            &options.tenant,
+            json!({ "input": options.question, "topic": options.topic }),
+        ),
+        &query_handler,
+    )
+        .await?;
+    println!("query response ({}):", response.status());
+    println!("{:#}", response_json(response)?);
+
+    let events: Vec<String> = event_publisher
+        .events()
+        .into_iter()
+        .map(|(detail_type, _)| detail_type)
+        .collect();
+    println!("published events: {:?}", events);
+
+    Ok(())
+}
+
+async fn local_storage() -> Result<Arc<dyn ObjectStorage>, Error> {
+    let endpoint = match env::var("LOCAL_S3_ENDPOINT") {
+        Ok(endpoint) => endpoint,
+        Err(_) => return Ok(Arc::new(InMemoryStorage::new())),
+    };
+
+    let config = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .endpoint_url(endpoint)
+        .load()
+        .await;
+    let s3_client = aws_sdk_s3::Client::from_conf(
+        aws_sdk_s3::config::Builder::from(&config)
+            .force_path_style(true)
+            .build(),
+    );
+
+    for bucket in [MEDIA_BUCKET, KB_BUCKET] {
+        if let Err(err) = s3_client.create_bucket().bucket(bucket).send().await {
+            let err = err.into_service_error();
+            if !err.is_bucket_already_owned_by_you() {
+                return Err(Box::new(err));
+            }
+        }
+    }
+
+    Ok(Arc::new(S3Storage::new(s3_client)))
+}
+
+async fn get_knowledge_base_document(
+    storage: &dyn ObjectStorage,
+    key: &str,
+) -> Result<Option<Reference>, Error> {
+    let text = match storage.get_object(KB_BUCKET, key).await? {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+
+    let metadata: Value = get_json(storage, KB_BUCKET, &format!("{}.metadata.json", key))
+        .await?
+        .ok_or("No document metadata in the knowledge base bucket")?;
+
+    Ok(Some(Reference {
+        uri: Some(format!("s3://{}/{}", KB_BUCKET, key)),
+        text: Some(String::from_utf8(text)?),
+        metadata: metadata["metadataAttributes"]
+            .as_object()
+            .cloned()
+            .unwrap_or_else(Map::new),
+    }))
+}
+
+/// A request as API Gateway forwards it once the caller is authorized for `tenant`.
+fn json_request(method: &str, uri: &str, tenant: &str, body: Value) -> Request {
+    let request = http::Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid request");
+
+    with_claims(request, &[("sub", "local"), ("custom:tenant_id", tenant)])
+}
+
+fn response_json(response: Response<Body>) -> Result<Value, Error> {
+    let status = response.status();
+    let body: &[u8] = response.body();
+
+    if !status.is_success() {
+        return Err(Error::from(format!(
+            "Handler responded {}: {}",
+            status,
+            String::from_utf8_lossy(body)
+        )));
+    }
+
+    Ok(serde_json::from_slice(body)?)
+}
@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+
+use aws_lambda_events::event::s3::S3Event;
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::{info, warn};
+
+use shared::auth::tenant_and_task;
+use shared::events::EventPublisher;
+use shared::models::{media_timestamp, normalized_media_key};
+use shared::storage::ObjectStorage;
+use shared::task::record_failure;
+
+use crate::media::{AudioStream, MediaInfo, MediaProcessing};
+
+pub mod media;
+
+/// Transcribe accepts at most two channels, with channel identification.
+const MAX_CHANNELS: u32 = 2;
+
+/// Deployment settings of the media normalization.
+#[derive(Debug, Clone)]
+pub struct NormalizeConfig {
+    /// Longer uploads are rejected, Transcribe itself stops at 4 hours.
+    pub max_duration_seconds: f64,
+    /// Where uploads are downloaded to, `/tmp` on Lambda.
+    pub work_directory: PathBuf,
+}
+
+/// Extracts the audio track of every upload as FLAC under `media-normalized/`, in the tenant
+/// folder of the upload, which starts the transcription. Uploads that cannot be transcribed
+/// are recorded as failed tasks.
+pub async fn normalize_media(
+    event: LambdaEvent<S3Event>,
+    storage: &dyn ObjectStorage,
+    media: &dyn MediaProcessing,
+    event_publisher: &dyn EventPublisher,
+    config: &NormalizeConfig,
+) -> Result<(), Error> {
+    for record in event.payload.records {
+        let object_key = record
+            .s3
+            .object
+            .key
+            .ok_or_else(|| Error::from("Missing object key"))?;
+        let bucket_name = record
+            .s3
+            .bucket
+            .name
+            .ok_or_else(|| Error::from("Missing bucket name"))?;
+
+        let (tenant_id, task_id) = tenant_and_task(&object_key)
+            .ok_or_else(|| Error::from(format!("No tenant in object key {}", object_key)))?;
+
+        // /tmp survives warm invocations, so the media must not pile up there.
+        let directory = config.work_directory.join(format!("normalize-{}", task_id));
+        tokio::fs::create_dir_all(&directory).await?;
+
+        let result = async {
+            let upload = directory.join("media");
+            if !storage.download(&bucket_name, &object_key, &upload).await? {
+                return Err(Error::from("Uploaded media not found"));
+            }
+
+            let info = media.probe(&upload).await?;
+
+            let audio = match check(info.as_ref(), config) {
+                Ok(audio) => audio,
+                Err(failure_reason) => {
+                    warn!(task_id, failure_reason, ?info, "rejected upload");
+                    return record_failure(
+                        storage,
+                        event_publisher,
+                        &bucket_name,
+                        tenant_id,
+                        task_id,
+                        &failure_reason,
+                    )
+                        .await;
+                }
+            };
+
+            let flac = directory.join("audio.flac");
+            media
+                .extract_audio(&upload, &flac, audio.channels.min(MAX_CHANNELS))
+                .await?;
+
+            info!(
+                task_id,
+                format = info.as_ref().map(|info| info.format_name.as_str()),
+                codec = audio.codec_name,
+                channels = audio.channels,
+                upload_bytes = tokio::fs::metadata(&upload).await?.len(),
+                audio_bytes = tokio::fs::metadata(&flac).await?.len(),
+                "normalized upload"
+            );
+
+            storage
+                .upload(
+                    &bucket_name,
+                    &normalized_media_key(tenant_id, task_id),
+                    &flac,
+                    "audio/flac",
+                )
+                .await
+        }
+            .await;
+
+        tokio::fs::remove_dir_all(&directory).await?;
+        result?;
+    }
+
+    Ok(())
+}
+
+/// The audio stream to transcribe, or why the upload cannot be transcribed.
+fn check<'a>(
+    info: Option<&'a MediaInfo>,
+    config: &NormalizeConfig,
+) -> Result<&'a AudioStream, String> {
+    let Some(info) = info else {
+        return Err("Unsupported media format: the upload is not an audio or video file".to_string());
+    };
+
+    let Some(audio) = info.audio.as_ref() else {
+        return Err(format!(
+            "The upload has no audio track ({}{})",
+            info.format_name,
+            if info.has_video { ", video only" } else { "" }
+        ));
+    };
+
+    match info.duration_seconds {
+        Some(duration_seconds) if duration_seconds > config.max_duration_seconds => Err(format!(
+            "The upload is {} long, the limit is {}",
+            media_timestamp(duration_seconds),
+            media_timestamp(config.max_duration_seconds)
+        )),
+        _ => Ok(audio),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::events::InMemoryEventPublisher;
+    use shared::models::staging_metadata_key;
+    use shared::storage::{get_json, put_json, InMemoryStorage};
+    use shared::task::{task_record_key, TaskRecord, TaskStatus};
+
+    use super::*;
+
+    struct StubMediaProcessing {
+        info: Option<MediaInfo>,
+    }
+
+    #[async_trait]
+    impl MediaProcessing for StubMediaProcessing {
+        async fn probe(&self, _media: &Path) -> Result<Option<MediaInfo>, Error> {
+            Ok(self.info.clone())
+        }
+
+        async fn extract_audio(
+            &self,
+            media: &Path,
+            output: &Path,
+            channels: u32,
+        ) -> Result<(), Error> {
+            let media = tokio::fs::read(media).await?;
+            let flac = [b"flac:".as_slice(), &[channels as u8], &media].concat();
+            Ok(tokio::fs::write(output, flac).await?)
+        }
+    }
+
+    fn video(audio: Option<AudioStream>, duration_seconds: f64) -> Option<MediaInfo> {
+        Some(MediaInfo {
+            format_name: "mov,mp4,m4a,3gp,3g2,mj2".to_string(),
+            duration_seconds: Some(duration_seconds),
+            audio,
+            has_video: true,
+        })
+    }
+
+    fn surround() -> Option<AudioStream> {
+        Some(AudioStream {
+            codec_name: "aac".to_string(),
+            channels: 6,
+            sample_rate: Some(48000),
+        })
+    }
+
+    /// Tests run in parallel on the same task, each gets its own directory.
+    fn config() -> NormalizeConfig {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        NormalizeConfig {
+            max_duration_seconds: 4.0 * 3600.0,
+            work_directory: std::env::temp_dir().join(format!(
+                "normalize-media-test-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            )),
+        }
+    }
+
+    fn s3_event(key: &str) -> LambdaEvent<S3Event> {
+        let payload = serde_json::from_value(json!({
+            "Records": [{
+                "eventTime": "2024-07-01T00:00:00Z",
+                "userIdentity": { "principalId": "test" },
+                "requestParameters": { "sourceIPAddress": "127.0.0.1" },
+                "s3": {
+                    "bucket": { "name": "media" },
+                    "object": { "key": key }
+                }
+            }]
+        }))
+            .unwrap();
+
+        LambdaEvent::new(payload, Context::default())
+    }
+
+    async fn normalize(info: Option<MediaInfo>) -> (InMemoryStorage, InMemoryEventPublisher) {
+        let storage = InMemoryStorage::new();
+        let event_publisher = InMemoryEventPublisher::new();
+        storage
+            .put_object("media", "media-uploads/acme/task-1", b"mp4".to_vec(), "video/mp4")
+            .await
+            .unwrap();
+        put_json(
+            &storage,
+            "media",
+            &staging_metadata_key("acme", "task-1"),
+            &json!({
+                "topic": "Serverless RAG",
+                "sourceUrl": "https://example.com/talk",
+                "date": "2024-07-01"
+            }),
+        )
+            .await
+            .unwrap();
+
+        normalize_media(
+            s3_event("media-uploads/acme/task-1"),
+            &storage,
+            &StubMediaProcessing { info },
+            &event_publisher,
+            &config(),
+        )
+            .await
+            .unwrap();
+
+        (storage, event_publisher)
+    }
+
+    async fn failure_reason(storage: &InMemoryStorage) -> Option<String> {
+        let task_record: TaskRecord =
+            get_json(storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.status, TaskStatus::Failed);
+        assert_eq!(task_record.tenant_id, "acme");
+        task_record.failure_reason
+    }
+
+    #[tokio::test]
+    async fn extracts_the_audio_track_downmixed_to_stereo() {
+        let (storage, event_publisher) = normalize(video(surround(), 600.0)).await;
+
+        let audio = storage
+            .get_object("media", "media-normalized/acme/task-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(audio, b"flac:\x02mp4".to_vec());
+
+        assert!(event_publisher.events().is_empty());
+        assert!(!storage.keys("media").contains(&task_record_key("acme", "task-1")));
+    }
+
+    #[tokio::test]
+    async fn rejects_uploads_without_audio() {
+        let (storage, event_publisher) = normalize(video(None, 600.0)).await;
+
+        assert_eq!(
+            failure_reason(&storage).await.as_deref(),
+            Some("The upload has no audio track (mov,mp4,m4a,3gp,3g2,mj2, video only)")
+        );
+        assert_eq!(event_publisher.events()[0].1["status"], json!("failed"));
+        assert!(!storage.keys("media").contains(&normalized_media_key("acme", "task-1")));
+    }
+
+    #[tokio::test]
+    async fn rejects_uploads_over_the_duration_limit() {
+        let (storage, _) = normalize(video(surround(), 5.0 * 3600.0)).await;
+
+        assert_eq!(
+            failure_reason(&storage).await.as_deref(),
+            Some("The upload is 5:00:00 long, the limit is 4:00:00")
+        );
+        assert!(!storage.keys("media").contains(&normalized_media_key("acme", "task-1")));
+    }
+
+    #[tokio::test]
+    async fn rejects_files_that_are_not_media() {
+        let (storage, _) = normalize(None).await;
+
+        assert_eq!(
+            failure_reason(&storage).await.as_deref(),
+            Some("Unsupported media format: the upload is not an audio or video file")
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::env;
+
+use aws_config::BehaviorVersion;
+use aws_lambda_events::event::s3::S3Event;
+use lambda_runtime::{Error, LambdaEvent, run, service_fn, tracing};
+
+use normalize_media::{normalize_media, NormalizeConfig};
+use normalize_media::media::FfmpegMediaProcessing;
+use shared::events::EventBridgePublisher;
+use shared::storage::S3Storage;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let storage = S3Storage::new(aws_sdk_s3::Client::new(&config));
+    let event_publisher = EventBridgePublisher::new(
+        aws_sdk_eventbridge::Client::new(&config),
+        env::var("EVENT_BUS_NAME").unwrap_or_else(|_| "default".to_string()),
+    );
+
+    // The ffmpeg layer is extracted under /opt.
+    let media = FfmpegMediaProcessing::new(
+        env::var("FFMPEG_PATH").unwrap_or_else(|_| "/opt/bin/ffmpeg".to_string()),
+        env::var("FFPROBE_PATH").unwrap_or_else(|_| "/opt/bin/ffprobe".to_string()),
+    );
+
+    let normalize_config = NormalizeConfig {
+        max_duration_seconds: env::var("MAX_DURATION_SECONDS")
+            .unwrap_or_else(|_| "14400".to_string())
+            .parse()?,
+        work_directory: env::temp_dir(),
+    };
+
+    run(service_fn(|event: LambdaEvent<S3Event>| async {
+        normalize_media(event, &storage, &media, &event_publisher, &normalize_config).await
+    }))
+        .await
+}
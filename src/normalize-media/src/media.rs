@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use lambda_runtime::Error;
+use serde::Deserialize;
+use tokio::process::Command;
+
+/// What ffprobe found in an uploaded file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    /// Container, as ffprobe names it, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub format_name: String,
+    pub duration_seconds: Option<f64>,
+    /// The first audio stream, transcribed on its own.
+    pub audio: Option<AudioStream>,
+    pub has_video: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioStream {
+    pub codec_name: String,
+    pub channels: u32,
+    pub sample_rate: Option<u32>,
+}
+
+/// Probes and transcodes media files.
+#[async_trait]
+pub trait MediaProcessing: Send + Sync {
+    /// Returns `None` when the file is not a media file ffprobe can read.
+    async fn probe(&self, media: &Path) -> Result<Option<MediaInfo>, Error>;
+
+    /// Writes the first audio track to `output` as 16 kHz FLAC, downmixed to at most
+    /// `channels` channels.
+    async fn extract_audio(&self, media: &Path, output: &Path, channels: u32) -> Result<(), Error>;
+}
+
+/// Runs the `ffmpeg` and `ffprobe` binaries of the ffmpeg lambda layer.
+pub struct FfmpegMediaProcessing {
+    ffmpeg_path: String,
+    ffprobe_path: String,
+}
+
+impl FfmpegMediaProcessing {
+    pub fn new(ffmpeg_path: String, ffprobe_path: String) -> Self {
+        FfmpegMediaProcessing {
+            ffmpeg_path,
+            ffprobe_path,
+        }
+    }
+}
+
+#[async_trait]
+impl MediaProcessing for FfmpegMediaProcessing {
+    async fn probe(&self, media: &Path) -> Result<Option<MediaInfo>, Error> {
+        let output = Command::new(&self.ffprobe_path)
+            .args(["-v", "error", "-show_format", "-show_streams", "-of", "json"])
+            .arg(media)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let probe: Probe = serde_json::from_slice(&output.stdout)?;
+
+        let audio = probe
+            .streams
+            .iter()
+            .find(|stream| stream.codec_type == "audio")
+            .map(|stream| AudioStream {
+                codec_name: stream.codec_name.clone(),
+                channels: stream.channels.unwrap_or(1),
+                sample_rate: stream.sample_rate.as_deref().and_then(|rate| rate.parse().ok()),
+            });
+
+        // Audio files carry their cover art as a single frame video stream.
+        let has_video = probe
+            .streams
+            .iter()
+            .any(|stream| stream.codec_type == "video" && stream.disposition.attached_pic == 0);
+
+        Ok(Some(MediaInfo {
+            format_name: probe.format.format_name,
+            duration_seconds: probe.format.duration.as_deref().and_then(|d| d.parse().ok()),
+            audio,
+            has_video,
+        }))
+    }
+
+    async fn extract_audio(&self, media: &Path, output: &Path, channels: u32) -> Result<(), Error> {
+        let result = Command::new(&self.ffmpeg_path)
+            .args(["-v", "error", "-y", "-i"])
+            .arg(media)
+            .args([
+                "-vn",
+                "-map",
+                "0:a:0",
+                "-ac",
+                &channels.to_string(),
+                "-ar",
+                "16000",
+                "-c:a",
+                "flac",
+            ])
+            .arg(output)
+            .output()
+            .await?;
+
+        match result.status.success() {
+            true => Ok(()),
+            false => Err(Error::from(format!(
+                "ffmpeg failed: {}",
+                String::from_utf8_lossy(&result.stderr)
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    streams: Vec<Stream>,
+}
+
+#[derive(Default, Deserialize)]
+struct Format {
+    #[serde(default)]
+    format_name: String,
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Stream {
+    #[serde(default)]
+    codec_type: String,
+    #[serde(default)]
+    codec_name: String,
+    channels: Option<u32>,
+    sample_rate: Option<String>,
+    #[serde(default)]
+    disposition: Disposition,
+}
+
+#[derive(Default, Deserialize)]
+struct Disposition {
+    #[serde(default)]
+    attached_pic: i64,
+}
@@ -0,0 +1,302 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use aws_lambda_events::event::eventbridge::EventBridgeEvent;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::{info, warn};
+use sha2::Sha256;
+
+use shared::events::TaskStatusChanged;
+use shared::storage::{get_json, ObjectStorage};
+use shared::webhook::{webhook_callback_key, WebhookCallback};
+
+use crate::dns::PublicResolver;
+
+pub mod dns;
+
+const MAX_ATTEMPTS: u32 = 5;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Redirects are not followed and hosts only resolve to public addresses, the callback url
+/// was only checked on upload.
+pub fn http_client(timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+}
+
+pub async fn notify_webhook(
+    event: LambdaEvent<EventBridgeEvent<TaskStatusChanged>>,
+    storage: &dyn ObjectStorage,
+    http_client: &reqwest::Client,
+    media_bucket_name: &str,
+) -> Result<(), Error> {
+    let task_status = event.payload.detail;
+
+    let callback: WebhookCallback = match get_json(
+        storage,
+        media_bucket_name,
+        &webhook_callback_key(&task_status.task_id),
+    )
+        .await?
+    {
+        Some(callback) => callback,
+        None => return Ok(()),
+    };
+
+    let body = serde_json::to_string(&task_status)?;
+
+    deliver(http_client, &callback, &body).await?;
+
+    info!(
+        task_id = task_status.task_id,
+        status = ?task_status.status,
+        "webhook delivered"
+    );
+    Ok(())
+}
+
+/// Posts the payload, backing off exponentially on network errors, throttling and server
+/// errors. Other client errors are not retried since the same request would fail again, nor
+/// are redirects, which could point anywhere.
+async fn deliver(
+    http_client: &reqwest::Client,
+    callback: &WebhookCallback,
+    body: &str,
+) -> Result<(), Error> {
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let timestamp = Utc::now().timestamp().to_string();
+
+        let result = http_client
+            .post(&callback.url)
+            .header("content-type", "application/json")
+            .header("x-timestamp", &timestamp)
+            .header(
+                "x-signature",
+                format!("sha256={}", sign(&callback.secret, &timestamp, body)?),
+            )
+            .body(body.to_string())
+            .send()
+            .await;
+
+        let retryable = match result {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) if resp.status().is_redirection() => {
+                return Err(Error::from(format!(
+                    "Webhook redirected with status {}, redirects are not followed",
+                    resp.status()
+                )));
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                if !status.is_server_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(Error::from(format!("Webhook rejected with status {}", status)));
+                }
+                format!("status {}", status)
+            }
+            Err(err) => err.to_string(),
+        };
+
+        warn!(attempt, error = retryable, "webhook delivery failed");
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    Err(Error::from(format!(
+        "Webhook delivery failed after {} attempts",
+        MAX_ATTEMPTS
+    )))
+}
+
+/// Hex encoded HMAC-SHA256 of `{timestamp}.{body}`, so receivers can reject replayed payloads.
+fn sign(secret: &str, timestamp: &str, body: &str) -> Result<String, Error> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::storage::{put_json, InMemoryStorage};
+
+    use super::*;
+
+    /// Answers one request with `status` and hands back its headers and body.
+    fn serve_once(status: u16) -> (String, mpsc::Receiver<(Vec<String>, String)>) {
+        serve_once_with(status, "")
+    }
+
+    /// Same as [`serve_once`], with extra `headers` each ending in `\r\n`.
+    fn serve_once_with(
+        status: u16,
+        headers: &str,
+    ) -> (String, mpsc::Receiver<(Vec<String>, String)>) {
+        let response_headers = headers.to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/media", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                headers.push(line.trim().to_lowercase());
+            }
+
+            let content_length = headers
+                .iter()
+                .find_map(|header| header.strip_prefix("content-length: "))
+                .map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 {} OK\r\n{}content-length: 0\r\n\r\n",
+                status, response_headers
+            )
+                .unwrap();
+            sender.send((headers, String::from_utf8(body).unwrap())).unwrap();
+        });
+
+        (url, receiver)
+    }
+
+    fn event(task_id: &str) -> LambdaEvent<EventBridgeEvent<TaskStatusChanged>> {
+        let payload = serde_json::from_value(json!({
+            "version": "0",
+            "id": "event-1",
+            "detail-type": "Task Status Changed",
+            "source": "media-rag",
+            "account": "123456789012",
+            "time": "2024-07-01T00:00:00Z",
+            "region": "us-east-1",
+            "resources": [],
+            "detail": {
+                "taskId": task_id,
+                "status": "indexed",
+                "transcriptLocation": "s3://kb/transcripts/task-1"
+            }
+        }))
+            .unwrap();
+
+        LambdaEvent::new(payload, Context::default())
+    }
+
+    async fn storage_with_callback(url: &str) -> InMemoryStorage {
+        let storage = InMemoryStorage::new();
+        put_json(
+            &storage,
+            "media",
+            &webhook_callback_key("task-1"),
+            &WebhookCallback {
+                url: url.to_string(),
+                secret: "0123456789abcdef".to_string(),
+            },
+        )
+            .await
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        let signature = sign("secret", "1720000000", "{}").unwrap();
+
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, sign("secret", "1720000000", "{}").unwrap());
+        assert_ne!(signature, sign("secret", "1720000001", "{}").unwrap());
+        assert_ne!(signature, sign("other", "1720000000", "{}").unwrap());
+    }
+
+    #[tokio::test]
+    async fn skips_tasks_without_callback() {
+        let storage = InMemoryStorage::new();
+
+        notify_webhook(event("task-1"), &storage, &reqwest::Client::new(), "media")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn posts_signed_status_change() {
+        let (url, received) = serve_once(200);
+        let storage = storage_with_callback(&url).await;
+
+        notify_webhook(event("task-1"), &storage, &reqwest::Client::new(), "media")
+            .await
+            .unwrap();
+
+        let (headers, body) = received.recv().unwrap();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find_map(|header| header.strip_prefix(&format!("{}: ", name)))
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            header("x-signature"),
+            format!(
+                "sha256={}",
+                sign("0123456789abcdef", &header("x-timestamp"), &body).unwrap()
+            )
+        );
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["taskId"], "task-1");
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_rejected_deliveries() {
+        let (url, received) = serve_once(400);
+        let storage = storage_with_callback(&url).await;
+
+        let result =
+            notify_webhook(event("task-1"), &storage, &reqwest::Client::new(), "media").await;
+
+        assert!(result.is_err());
+        assert!(received.recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn does_not_follow_redirects() {
+        let (target, redirected) = serve_once(200);
+        let (url, received) = serve_once_with(302, &format!("location: {}\r\n", target));
+        let storage = storage_with_callback(&url).await;
+        let http_client = http_client(Duration::from_secs(10)).unwrap();
+
+        let result = notify_webhook(event("task-1"), &storage, &http_client, "media").await;
+
+        assert!(result.is_err());
+        assert!(received.recv().is_ok());
+        assert!(redirected.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use std::env;
+use std::time::Duration;
+
+use aws_config::BehaviorVersion;
+use aws_lambda_events::event::eventbridge::EventBridgeEvent;
+use lambda_runtime::{Error, LambdaEvent, run, service_fn, tracing};
+
+use notify_webhook::{http_client, notify_webhook};
+use shared::events::TaskStatusChanged;
+use shared::storage::S3Storage;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let storage = S3Storage::new(aws_sdk_s3::Client::new(&config));
+    let http_client = http_client(Duration::from_secs(10))?;
+
+    let media_bucket_name = env::var("MEDIA_BUCKET").expect("MEDIA_BUCKET not set");
+
+    run(service_fn(
+        |event: LambdaEvent<EventBridgeEvent<TaskStatusChanged>>| async {
+            notify_webhook(event, &storage, &http_client, &media_bucket_name).await
+        },
+    ))
+        .await
+}
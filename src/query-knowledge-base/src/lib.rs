@@ -0,0 +1,662 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::Utc;
+use lambda_http::tracing::warn;
+use lambda_http::{Body, Error, Request, Response};
+use nanoid::nanoid;
+use serde_valid::json::json;
+use serde_valid::Validate;
+
+use shared::analytics::{log_query, response_id, QueryLogEntry};
+use shared::auth::{authorize, AccessPolicy, Caller, TENANT_ID_ATTRIBUTE};
+use shared::cache::{AnswerCache, AnswerCacheKey, CacheLookup, CachedAnswer};
+use shared::error::ApiError;
+use shared::federation::{KnowledgeBases, KNOWLEDGE_BASE_ATTRIBUTE};
+use shared::retrieval::{AttributeFilter, GeneratedAnswer, RetrievalQuery};
+use shared::storage::ObjectStorage;
+use shared::validation::parse_json;
+
+use crate::query::Query;
+use crate::quota::{acquire, caller_key, record_usage, QuotaConfig};
+
+pub mod query;
+pub mod quota;
+
+/// Dependencies and settings of the query route.
+pub struct QueryHandler {
+    pub knowledge_bases: KnowledgeBases,
+    pub cache: Arc<dyn AnswerCache>,
+    pub storage: Arc<dyn ObjectStorage>,
+    pub policy: AccessPolicy,
+    pub quotas: QuotaConfig,
+    /// Bucket the query log is written to, under `analytics/queries/`.
+    pub analytics_bucket_name: String,
+}
+
+/// Answer of a query with how it was obtained, for the query log.
+struct Outcome {
+    answer: Option<CachedAnswer>,
+    cached: bool,
+    citation_count: usize,
+}
+
+pub async fn query_knowledge_base(
+    event: Request,
+    handler: &QueryHandler,
+) -> Result<Response<Body>, Error> {
+    handler
+        .answer_query(event)
+        .await
+        .or_else(ApiError::into_response)
+}
+
+impl QueryHandler {
+    async fn answer_query(&self, event: Request) -> Result<Response<Body>, ApiError> {
+        let caller = authorize(&event, &self.policy)?;
+
+        let query: Query = parse_json(event.body())?;
+
+        query.validate()?;
+
+        let knowledge_bases = self.knowledge_bases.select(&query.knowledge_bases)?;
+
+        let caller_key = caller_key(&caller.tenant_id, &caller.subject);
+        let received_at = Utc::now();
+        acquire(self.storage.as_ref(), &self.quotas, &caller_key, received_at).await?;
+
+        let started_at = Instant::now();
+        let response_id = response_id(received_at, &nanoid!());
+        let topic = query.topic.clone();
+        let retrieval_query = to_retrieval_query(&caller.tenant_id, query);
+
+        let outcome = self
+            .answer(&caller, &topic, &knowledge_bases, &retrieval_query)
+            .await?;
+
+        self.log_query(QueryLogEntry {
+            request_id: response_id.clone(),
+            tenant_id: caller.tenant_id.clone(),
+            subject: caller.subject.clone(),
+            timestamp: received_at,
+            input: retrieval_query.input,
+            topic,
+            filters: retrieval_query
+                .filters
+                .into_iter()
+                .map(|filter| (filter.key, filter.value))
+                .collect(),
+            model: self.knowledge_bases.model(&knowledge_bases).to_string(),
+            knowledge_bases,
+            latency_ms: started_at.elapsed().as_millis() as u64,
+            cached: outcome.cached,
+            answered: outcome.answer.is_some(),
+            citation_count: outcome.citation_count,
+            sources: outcome
+                .answer
+                .as_ref()
+                .map(|answer| answer.sources.clone())
+                .unwrap_or_default(),
+        })
+            .await;
+
+        let answer = outcome.answer.ok_or_else(|| {
+            ApiError::NotFound("No answer was generated for this query".to_string())
+        })?;
+
+        answer_response(&response_id, &answer, outcome.cached)
+    }
+
+    async fn answer(
+        &self,
+        caller: &Caller,
+        topic: &str,
+        knowledge_bases: &[String],
+        retrieval_query: &RetrievalQuery,
+    ) -> Result<Outcome, ApiError> {
+        let cache_key = AnswerCacheKey::new(
+            &caller.tenant_id,
+            topic,
+            &retrieval_query.input,
+            &retrieval_query.filters,
+            knowledge_bases,
+            self.knowledge_bases.model(knowledge_bases),
+        );
+
+        // The cache only saves model calls, a failing cache does not fail the query.
+        let version = match self.cache.get(&cache_key).await {
+            Ok(CacheLookup {
+                answer: Some(answer),
+                ..
+            }) => {
+                return Ok(Outcome {
+                    citation_count: answer.citation_count,
+                    answer: Some(answer),
+                    cached: true,
+                })
+            }
+            Ok(CacheLookup { version, .. }) => Some(version),
+            Err(err) => {
+                warn!(error = %err, "answer cache lookup failed");
+                None
+            }
+        };
+
+        let answer = self
+            .knowledge_bases
+            .retrieve_and_generate(knowledge_bases, retrieval_query)
+            .await?;
+        let answer = match answer {
+            Some(answer) => answer,
+            None => {
+                return Ok(Outcome {
+                    answer: None,
+                    cached: false,
+                    citation_count: 0,
+                })
+            }
+        };
+
+        // The answer is paid for already, failing here would only make the caller pay again.
+        if let Err(err) = record_usage(
+            self.storage.as_ref(),
+            &self.quotas,
+            &caller_key(&caller.tenant_id, &caller.subject),
+            answer.usage.total(),
+            Utc::now(),
+        )
+            .await
+        {
+            warn!(error = %err, "recording token usage failed");
+        }
+
+        let answer = unwrap_answer(answer);
+
+        // Without the topic version of the lookup the answer might outlive its documents.
+        if let Some(version) = version {
+            if let Err(err) = self.cache.put(&cache_key, &version, &answer).await {
+                warn!(error = %err, "answer cache update failed");
+            }
+        }
+
+        Ok(Outcome {
+            citation_count: answer.citation_count,
+            answer: Some(answer),
+            cached: false,
+        })
+    }
+
+    /// Analytics are best effort, the caller still gets the answer if they cannot be written.
+    async fn log_query(&self, entry: QueryLogEntry) {
+        let logged = log_query(self.storage.as_ref(), &self.analytics_bucket_name, &entry).await;
+
+        if let Err(err) = logged {
+            warn!(error = %err, request_id = entry.request_id, "query log failed");
+        }
+    }
+}
+
+fn answer_response(
+    response_id: &str,
+    answer: &CachedAnswer,
+    cached: bool,
+) -> Result<Response<Body>, ApiError> {
+    let resp = Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .header("x-cache", if cached { "hit" } else { "miss" })
+        .body(
+            json!({
+                "response_id": response_id,
+                "output": answer.output,
+                "sources": answer.sources,
+                "sources_by_knowledge_base": answer.sources_by_knowledge_base
+            })
+                .to_string()
+                .into(),
+        )?;
+
+    Ok(resp)
+}
+
+/// Every query is scoped to the caller's tenant, whatever else it filters on.
+pub fn to_retrieval_query(tenant_id: &str, query: Query) -> RetrievalQuery {
+    let mut filters = vec![
+        AttributeFilter {
+            key: TENANT_ID_ATTRIBUTE.to_string(),
+            value: tenant_id.to_string(),
+        },
+        AttributeFilter {
+            key: "topic".to_string(),
+            value: query.topic,
+        },
+    ];
+
+    if let Some(language) = query.language {
+        filters.push(AttributeFilter {
+            key: "language_code".to_string(),
+            value: language,
+        });
+    }
+
+    RetrievalQuery {
+        input: query.input,
+        filters,
+    }
+}
+
+/// The answer text with its distinct sources, overall and per knowledge base.
+pub fn unwrap_answer(answer: GeneratedAnswer) -> CachedAnswer {
+    let mut sources = BTreeSet::new();
+    let mut sources_by_knowledge_base: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for reference in &answer.references {
+        let source_url = match reference.metadata.get("source_url").and_then(|url| url.as_str()) {
+            Some(source_url) => source_url.to_string(),
+            None => continue,
+        };
+
+        if let Some(name) = reference
+            .metadata
+            .get(KNOWLEDGE_BASE_ATTRIBUTE)
+            .and_then(|name| name.as_str())
+        {
+            sources_by_knowledge_base
+                .entry(name.to_string())
+                .or_default()
+                .insert(source_url.clone());
+        }
+        sources.insert(source_url);
+    }
+
+    CachedAnswer {
+        output: answer.text,
+        sources: sources.into_iter().collect(),
+        citation_count: answer.references.len(),
+        sources_by_knowledge_base: sources_by_knowledge_base
+            .into_iter()
+            .map(|(name, sources)| (name, sources.into_iter().collect()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use shared::auth::with_claims;
+    use shared::cache::InMemoryAnswerCache;
+    use shared::generation::InMemoryGeneration;
+    use shared::retrieval::{InMemoryRetrieval, Reference, Retrieval};
+    use shared::storage::{get_json, InMemoryStorage};
+
+    use super::*;
+
+    fn reference(tenant_id: &str, topic: &str, source_url: &str, text: &str) -> Reference {
+        let mut metadata = Map::new();
+        metadata.insert("tenant_id".to_string(), json!(tenant_id));
+        metadata.insert("topic".to_string(), json!(topic));
+        metadata.insert("source_url".to_string(), json!(source_url));
+
+        Reference {
+            uri: Some(format!("s3://kb/transcripts/{}", text.len())),
+            text: Some(text.to_string()),
+            metadata,
+        }
+    }
+
+    fn retrieval() -> InMemoryRetrieval {
+        InMemoryRetrieval::new(vec![
+            reference("acme", "serverless", "https://example.com/1", "Lambda scales to zero."),
+            reference("acme", "serverless", "https://example.com/1", "Cold starts matter."),
+            reference("acme", "containers", "https://example.com/2", "Pods run containers."),
+            reference("globex", "serverless", "https://example.com/3", "Globex runs Lambda."),
+        ])
+    }
+
+    fn quotas() -> QuotaConfig {
+        QuotaConfig {
+            bucket_name: "media".to_string(),
+            burst: 10,
+            queries_per_minute: 60,
+            monthly_token_budget: None,
+        }
+    }
+
+    struct Fixture {
+        retrieval: Arc<InMemoryRetrieval>,
+        generation: Arc<InMemoryGeneration>,
+        cache: Arc<InMemoryAnswerCache>,
+        storage: Arc<InMemoryStorage>,
+        handler: QueryHandler,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Fixture::with_quotas(quotas())
+        }
+
+        fn with_quotas(quotas: QuotaConfig) -> Self {
+            let retrieval = Arc::new(retrieval());
+            let webinars = InMemoryRetrieval::new(vec![reference(
+                "acme",
+                "serverless",
+                "https://example.com/webinar",
+                "Lambda scales per request.",
+            )]);
+            let generation = Arc::new(InMemoryGeneration::new("Lambda scales out."));
+            let cache = Arc::new(InMemoryAnswerCache::new());
+            let storage = Arc::new(InMemoryStorage::new());
+
+            Fixture {
+                handler: QueryHandler {
+                    knowledge_bases: KnowledgeBases {
+                        default_name: "podcasts".to_string(),
+                        retrievals: BTreeMap::from([
+                            ("podcasts".to_string(), retrieval.clone() as Arc<dyn Retrieval>),
+                            ("webinars".to_string(), Arc::new(webinars) as Arc<dyn Retrieval>),
+                        ]),
+                        generation: generation.clone(),
+                        generation_model: "claude".to_string(),
+                        max_passages: 10,
+                    },
+                    cache: cache.clone(),
+                    storage: storage.clone(),
+                    policy: AccessPolicy {
+                        required_scope: Some("kb:query".to_string()),
+                        ..Default::default()
+                    },
+                    quotas,
+                    analytics_bucket_name: "media".to_string(),
+                },
+                retrieval,
+                generation,
+                cache,
+                storage,
+            }
+        }
+
+        async fn send(&self, event: Request) -> Response<Body> {
+            query_knowledge_base(event, &self.handler).await.unwrap()
+        }
+
+        async fn query(&self, body: Value) -> (u16, Vec<u8>) {
+            let response = self.send(request(body)).await;
+            (response.status().as_u16(), response.body().to_vec())
+        }
+
+        async fn query_log(&self) -> Vec<Value> {
+            let mut entries = Vec::new();
+            for key in self.storage.keys("media") {
+                if key.starts_with("analytics/queries/tenant_id=acme/dt=") {
+                    let line = self.storage.get_object("media", &key).await.unwrap().unwrap();
+                    entries.push(serde_json::from_slice(&line).unwrap());
+                }
+            }
+            entries
+        }
+    }
+
+    fn request(body: Value) -> Request {
+        let event = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/query")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        let claims = [
+            ("sub", "user-1"),
+            ("custom:tenant_id", "acme"),
+            ("scope", "kb:query"),
+        ];
+        with_claims(event, &claims)
+    }
+
+    #[tokio::test]
+    async fn answers_from_the_requested_topic_with_distinct_sources() {
+        let fixture = Fixture::new();
+
+        let (status, body) = fixture
+            .query(json!({ "input": "How does Lambda scale?", "topic": "serverless" }))
+            .await;
+
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["output"], "Lambda scales to zero. Cold starts matter.");
+        assert_eq!(body["sources"], json!(["https://example.com/1"]));
+        assert_eq!(
+            body["sources_by_knowledge_base"],
+            json!({ "podcasts": ["https://example.com/1"] })
+        );
+
+        assert_eq!(
+            fixture.retrieval.queries(),
+            vec![RetrievalQuery {
+                input: "How does Lambda scale?".to_string(),
+                filters: vec![
+                    AttributeFilter {
+                        key: "tenant_id".to_string(),
+                        value: "acme".to_string(),
+                    },
+                    AttributeFilter {
+                        key: "topic".to_string(),
+                        value: "serverless".to_string(),
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_callers_without_a_tenant_or_the_query_scope() {
+        let fixture = Fixture::new();
+
+        for claims in [
+            vec![("sub", "user-1"), ("scope", "kb:query")],
+            vec![("sub", "user-1"), ("custom:tenant_id", "acme")],
+        ] {
+            let event = lambda_http::http::Request::builder()
+                .method("POST")
+                .uri("/query")
+                .body(Body::from(
+                    json!({ "input": "How does Lambda scale?", "topic": "serverless" })
+                        .to_string(),
+                ))
+                .unwrap();
+            let response = fixture.send(with_claims(event, &claims)).await;
+
+            assert_eq!(response.status(), 403);
+        }
+        assert!(fixture.retrieval.queries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn limits_the_queries_and_tokens_of_each_caller() {
+        let fixture = Fixture::with_quotas(QuotaConfig {
+            burst: 1,
+            monthly_token_budget: Some(1_000_000),
+            ..quotas()
+        });
+        let body = json!({ "input": "How does Lambda scale?", "topic": "serverless" });
+
+        let response = fixture.send(request(body.clone())).await;
+        assert_eq!(response.status(), 200);
+
+        let response = fixture.send(request(body)).await;
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "1");
+        assert_eq!(fixture.retrieval.queries().len(), 1);
+
+        let state: Value = get_json(fixture.storage.as_ref(), "media", "quotas/acme/user-1.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state["tokensUsed"], 27);
+    }
+
+    #[tokio::test]
+    async fn answers_repeated_questions_from_the_cache() {
+        let fixture = Fixture::new();
+
+        let mut bodies = Vec::new();
+        for input in ["How does Lambda scale?", "  how does lambda   scale "] {
+            let response = fixture
+                .send(request(json!({ "input": input, "topic": "serverless" })))
+                .await;
+            assert_eq!(response.status(), 200);
+            let body: Value = serde_json::from_slice(response.body()).unwrap();
+            bodies.push((response.headers()["x-cache"].clone(), body));
+        }
+
+        assert_eq!(bodies[0].0, "miss");
+        assert_eq!(bodies[1].0, "hit");
+        assert_eq!(bodies[0].1["output"], bodies[1].1["output"]);
+        assert_ne!(bodies[0].1["response_id"], bodies[1].1["response_id"]);
+        assert_eq!(fixture.retrieval.queries().len(), 1);
+
+        fixture.cache.invalidate_topic("acme", "serverless").await.unwrap();
+        let response = fixture
+            .send(request(json!({ "input": "How does Lambda scale?", "topic": "serverless" })))
+            .await;
+        assert_eq!(response.headers()["x-cache"], "miss");
+        assert_eq!(fixture.retrieval.queries().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn logs_answered_and_unanswered_queries() {
+        let fixture = Fixture::new();
+
+        let (_, body) = fixture
+            .query(json!({ "input": "How does Lambda scale?", "topic": "serverless" }))
+            .await;
+        let response_id = serde_json::from_slice::<Value>(&body).unwrap()["response_id"].clone();
+        fixture
+            .query(json!({ "input": "What is Kubernetes?", "topic": "databases" }))
+            .await;
+
+        let mut entries = fixture.query_log().await;
+        entries.sort_by_key(|entry| entry["topic"].as_str().unwrap_or_default().to_string());
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0]["topic"], "databases");
+        assert_eq!(entries[0]["answered"], false);
+        assert_eq!(entries[0]["citation_count"], 0);
+
+        assert_eq!(entries[1]["request_id"], response_id);
+        assert_eq!(entries[1]["subject"], "user-1");
+        assert_eq!(entries[1]["answered"], true);
+        assert_eq!(entries[1]["cached"], false);
+        assert_eq!(entries[1]["model"], "in-memory");
+        assert_eq!(entries[1]["citation_count"], 2);
+        assert_eq!(entries[1]["sources"], json!(["https://example.com/1"]));
+        assert_eq!(
+            entries[1]["filters"],
+            json!({ "tenant_id": "acme", "topic": "serverless" })
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_from_several_knowledge_bases_at_once() {
+        let fixture = Fixture::new();
+
+        let (status, body) = fixture
+            .query(json!({
+                "input": "How does Lambda scale?",
+                "topic": "serverless",
+                "knowledgeBases": ["*"]
+            }))
+            .await;
+
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["output"], "Lambda scales out.");
+        assert_eq!(
+            body["sources_by_knowledge_base"],
+            json!({
+                "podcasts": ["https://example.com/1"],
+                "webinars": ["https://example.com/webinar"]
+            })
+        );
+        assert_eq!(fixture.generation.prompts().len(), 1);
+        assert_eq!(fixture.retrieval.queries()[0].filters[0].value, "acme");
+
+        let entries = fixture.query_log().await;
+        assert_eq!(entries[0]["knowledge_bases"], json!(["podcasts", "webinars"]));
+        assert_eq!(entries[0]["model"], "claude");
+
+        let (status, body) = fixture
+            .query(json!({
+                "input": "How does Lambda scale?",
+                "topic": "serverless",
+                "knowledgeBases": ["support-calls"]
+            }))
+            .await;
+        assert_eq!(status, 400);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["detail"], "Unknown knowledge base support-calls");
+    }
+
+    #[tokio::test]
+    async fn filters_by_language() {
+        let fixture = Fixture::new();
+
+        let (status, _) = fixture
+            .query(json!({
+                "input": "How does Lambda scale?",
+                "topic": "serverless",
+                "language": "fr-FR"
+            }))
+            .await;
+
+        assert_eq!(status, 404);
+        assert_eq!(
+            fixture.retrieval.queries()[0].filters[2],
+            AttributeFilter {
+                key: "language_code".to_string(),
+                value: "fr-FR".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_not_found_without_an_answer() {
+        let fixture = Fixture::new();
+
+        let (status, body) = fixture
+            .query(json!({ "input": "What is Kubernetes?", "topic": "databases" }))
+            .await;
+
+        assert_eq!(status, 404);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["code"], "not-found");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_query() {
+        let fixture = Fixture::new();
+
+        let (status, body) = fixture
+            .query(json!({ "input": "Why?", "topic": "serverless" }))
+            .await;
+        assert_eq!(status, 400);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            problem["errors"],
+            json!([{
+                "field": "input",
+                "rule": "min_length",
+                "message": "The length of the value must be `>= 5`."
+            }])
+        );
+
+        let (status, body) = fixture.query(json!({ "input": "How does Lambda scale?" })).await;
+        assert_eq!(status, 400);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "topic");
+        assert_eq!(problem["errors"][0]["rule"], "required");
+
+        assert!(fixture.retrieval.queries().is_empty());
+        assert!(fixture.query_log().await.is_empty());
+    }
+}
@@ -1,149 +1,18 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::env;
+use std::sync::Arc;
 
 use aws_config::BehaviorVersion;
-use aws_sdk_bedrockagentruntime::operation::retrieve_and_generate::RetrieveAndGenerateOutput;
-use aws_sdk_bedrockagentruntime::types::{
-    FilterAttribute, KnowledgeBaseRetrievalConfiguration,
-    KnowledgeBaseRetrieveAndGenerateConfiguration, KnowledgeBaseVectorSearchConfiguration,
-    RetrievalFilter, RetrieveAndGenerateConfiguration, RetrieveAndGenerateInput, RetrieveAndGenerateType,
-};
-use lambda_http::{Body, Error, Request, Response, run, service_fn, tracing};
-use serde_valid::json::json;
-use serde_valid::Validate;
+use lambda_http::{Error, Request, run, service_fn, tracing};
 
-use crate::query::Query;
-
-mod query;
-
-async fn query_knowledge_base(
-    event: Request,
-    bedrock_agent_runtime_client: &aws_sdk_bedrockagentruntime::Client,
-    knowledge_base_id: &str,
-    model_arn: &str,
-) -> Result<Response<Body>, Error> {
-    let query_body = std::str::from_utf8(event.body())?;
-
-    let query: Query = match serde_json::from_str(&query_body) {
-        Ok(req) => req,
-        Err(err) => {
-            return Ok(Response::builder()
-                .status(400)
-                .header("content-type", "application/json")
-                .body(json!({ "error": err.to_string() }).to_string().into())
-                .map_err(Box::new)?)
-        }
-    };
-
-    let validation = query.validate();
-
-    if validation.is_err() {
-        let errs = validation.unwrap_err();
-        return Ok(Response::builder()
-            .status(400)
-            .header("content-type", "application/json")
-            .body(errs.to_string().into())
-            .map_err(Box::new)?);
-    }
-
-    let configuration =
-        build_retrieve_and_generate_configuration(knowledge_base_id, model_arn, &query)?;
-
-    let input = RetrieveAndGenerateInput::builder()
-        .text(query.input)
-        .build()?;
-
-    let result = bedrock_agent_runtime_client
-        .retrieve_and_generate()
-        .retrieve_and_generate_configuration(configuration)
-        .input(input)
-        .send()
-        .await?;
-
-    if result.output.is_none() {
-        return Ok(Response::builder()
-            .status(404)
-            .header("content-type", "application/json")
-            .body("Not found".into())
-            .map_err(Box::new)?);
-    }
-
-    let (output_text, sources) = unwrap_result(result);
-
-    let resp = Response::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .body(
-            json!({
-                "output": output_text,
-                "sources": sources
-            })
-                .to_string()
-                .into(),
-        )
-        .map_err(Box::new)?;
-
-    Ok(resp)
-}
-
-fn unwrap_result(rng_output: RetrieveAndGenerateOutput) -> (std::string::String, HashSet<std::string::String>) {
-    let output_text = rng_output.output.unwrap().text;
-
-    let sources: HashSet<_> = rng_output
-        .citations
-        .unwrap_or_default()
-        .into_iter()
-        .flat_map(|citation| citation.retrieved_references.unwrap_or_default())
-        .filter_map(|reference| {
-            reference
-                .metadata
-                .as_ref()
-                .and_then(|metadata| metadata.get("source_url"))
-                .and_then(|url| url.as_string().map(|url_str| url_str.to_string()))
-        })
-        .collect();
-
-    (output_text, sources)
-}
-
-fn build_retrieve_and_generate_configuration(
-    knowledge_base_id: &str,
-    model_arn: &str,
-    query: &Query,
-) -> Result<RetrieveAndGenerateConfiguration, Error> {
-    let q = query.clone();
-
-    let filter = RetrievalFilter::Equals(
-        FilterAttribute::builder()
-            .key("topic")
-            .value(q.topic.into())
-            .build()?,
-    );
-
-    // Create the vector search configuration
-    let vector_search_config = KnowledgeBaseVectorSearchConfiguration::builder()
-        .filter(filter)
-        .build();
-
-    let retrieval_config = KnowledgeBaseRetrievalConfiguration::builder()
-        .vector_search_configuration(vector_search_config)
-        .build();
-
-    let rng_config = KnowledgeBaseRetrieveAndGenerateConfiguration::builder()
-        .retrieval_configuration(retrieval_config)
-        .knowledge_base_id(knowledge_base_id)
-        .model_arn(model_arn)
-        .build()
-        .map_err(Box::new)?;
-
-    let configuration = RetrieveAndGenerateConfiguration::builder()
-        .r#type(RetrieveAndGenerateType::KnowledgeBase)
-        .knowledge_base_configuration(rng_config)
-        .build()
-        .map_err(Box::new)?;
-
-    Ok(configuration)
-}
+use query_knowledge_base::{query_knowledge_base, QueryHandler};
+use query_knowledge_base::quota::QuotaConfig;
+use shared::auth::AccessPolicy;
+use shared::cache::S3AnswerCache;
+use shared::federation::KnowledgeBases;
+use shared::generation::BedrockGeneration;
+use shared::retrieval::{BedrockRetrieval, Retrieval, RetrievalSettings};
+use shared::storage::S3Storage;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -159,18 +28,77 @@ async fn main() -> Result<(), Error> {
     let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
 
     let bedrock_agent_runtime_client = aws_sdk_bedrockagentruntime::Client::new(&config);
-
-    let knowledge_base_id = env::var("KB_ID").expect("KB_ID not set");
     let model_arn = env::var("MODEL_ARN").expect("MODEL_ARN not set");
+    let settings = RetrievalSettings {
+        number_of_results: match env::var("NUMBER_OF_RESULTS") {
+            Ok(number) if !number.is_empty() => Some(number.parse()?),
+            _ => None,
+        },
+        prompt_template: env::var("PROMPT_TEMPLATE").ok().filter(|prompt| !prompt.is_empty()),
+    };
+
+    // Knowledge bases other than the default one, by name.
+    let mut knowledge_base_ids: BTreeMap<String, String> = match env::var("KNOWLEDGE_BASES") {
+        Ok(knowledge_bases) if !knowledge_bases.is_empty() => {
+            serde_json::from_str(&knowledge_bases)?
+        }
+        _ => BTreeMap::new(),
+    };
+    let default_name = env::var("KB_NAME").unwrap_or_else(|_| "media".to_string());
+    knowledge_base_ids.insert(default_name.clone(), env::var("KB_ID").expect("KB_ID not set"));
+
+    let knowledge_bases = KnowledgeBases {
+        default_name,
+        retrievals: knowledge_base_ids
+            .into_iter()
+            .map(|(name, knowledge_base_id)| {
+                let retrieval: Arc<dyn Retrieval> = Arc::new(BedrockRetrieval::new(
+                    bedrock_agent_runtime_client.clone(),
+                    knowledge_base_id,
+                    model_arn.clone(),
+                    settings.clone(),
+                ));
+                (name, retrieval)
+            })
+            .collect(),
+        generation: Arc::new(BedrockGeneration::new(
+            aws_sdk_bedrockruntime::Client::new(&config),
+            model_arn.clone(),
+        )),
+        generation_model: model_arn,
+        max_passages: env::var("MAX_PASSAGES")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()?,
+    };
+    let storage = Arc::new(S3Storage::new(aws_sdk_s3::Client::new(&config)));
+    let media_bucket_name = env::var("MEDIA_BUCKET").expect("MEDIA_BUCKET not set");
+
+    let handler = QueryHandler {
+        knowledge_bases,
+        cache: Arc::new(S3AnswerCache::new(storage.clone(), media_bucket_name.clone())),
+        storage,
+        policy: AccessPolicy {
+            required_scope: env::var("REQUIRED_SCOPE").ok().filter(|scope| !scope.is_empty()),
+            admin_group: env::var("ADMIN_GROUP").unwrap_or_else(|_| "admin".to_string()),
+        },
+        quotas: QuotaConfig {
+            bucket_name: media_bucket_name.clone(),
+            burst: env::var("QUERY_BURST")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()?,
+            queries_per_minute: env::var("QUERIES_PER_MINUTE")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()?,
+            monthly_token_budget: match env::var("MONTHLY_TOKEN_BUDGET") {
+                Ok(budget) if !budget.is_empty() => Some(budget.parse()?),
+                _ => None,
+            },
+        },
+        analytics_bucket_name: media_bucket_name,
+    };
 
     run(service_fn(|event: Request| async {
-        query_knowledge_base(
-            event,
-            &bedrock_agent_runtime_client,
-            &knowledge_base_id,
-            &model_arn,
-        )
-            .await
+        query_knowledge_base(event, &handler).await
     }))
         .await
 }
@@ -8,4 +8,11 @@ pub struct Query {
     pub input: String,
     #[validate(min_length = 5)]
     pub topic: String,
+    /// Only answers from media in this language, a Transcribe code such as `en-US`.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Names of the knowledge bases to answer from, `*` for all of them, the default one when
+    /// empty.
+    #[serde(default)]
+    pub knowledge_bases: Vec<String>,
 }
@@ -0,0 +1,386 @@
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use shared::error::ApiError;
+use shared::storage::{get_json_versioned, put_json_if, ObjectStorage};
+
+pub const QUOTA_PREFIX: &str = "quotas/";
+
+/// Limits applied to every caller of the query route.
+#[derive(Debug, Clone)]
+pub struct QuotaConfig {
+    /// Bucket holding the quota state of the callers, under `quotas/`.
+    pub bucket_name: String,
+    /// Queries a caller can send in a burst.
+    pub burst: u32,
+    /// Rate the burst is refilled at, 0 disables the rate limit.
+    pub queries_per_minute: u32,
+    /// Model tokens a caller can spend per calendar month (UTC), `None` for no budget.
+    pub monthly_token_budget: Option<u64>,
+}
+
+/// Token bucket and monthly token count of a caller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaState {
+    /// Queries left in the bucket, refilled continuously up to the burst.
+    available: f64,
+    refilled_at: DateTime<Utc>,
+    /// Month the token count applies to, such as `2024-07`.
+    month: String,
+    tokens_used: u64,
+}
+
+/// Quotas are tracked per user of a tenant, machine clients have their client id as subject.
+pub fn caller_key(tenant_id: &str, subject: &str) -> String {
+    format!("{}/{}", tenant_id, subject)
+}
+
+fn quota_key(caller_key: &str) -> String {
+    format!("{}{}.json", QUOTA_PREFIX, caller_key)
+}
+
+/// Conflicting writes of concurrent queries tolerated before giving up.
+const MAX_UPDATE_ATTEMPTS: u32 = 5;
+
+/// Takes a query from the caller's bucket, or tells when to retry if the bucket is empty or
+/// the monthly budget is spent.
+pub async fn acquire(
+    storage: &dyn ObjectStorage,
+    config: &QuotaConfig,
+    caller_key: &str,
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    update(storage, config, caller_key, now, |state| {
+        if let Some(budget) = config.monthly_token_budget {
+            if state.tokens_used >= budget {
+                return Err(ApiError::QuotaExceeded {
+                    detail: format!("The monthly budget of {} tokens is spent", budget),
+                    retry_after_seconds: seconds_until(next_month(now), now),
+                });
+            }
+        }
+
+        if config.queries_per_minute == 0 {
+            return Ok(false);
+        }
+
+        if state.available < 1.0 {
+            let per_second = config.queries_per_minute as f64 / 60.0;
+            return Err(ApiError::QuotaExceeded {
+                detail: format!(
+                    "Too many queries, at most {} per minute are allowed",
+                    config.queries_per_minute
+                ),
+                retry_after_seconds: ((1.0 - state.available) / per_second).ceil() as u64,
+            });
+        }
+
+        state.available -= 1.0;
+        Ok(true)
+    })
+        .await
+}
+
+/// Adds the tokens of an answer to the caller's monthly count. A query is let through as long
+/// as the budget is not spent, so the last one may overrun it.
+pub async fn record_usage(
+    storage: &dyn ObjectStorage,
+    config: &QuotaConfig,
+    caller_key: &str,
+    tokens: u64,
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    update(storage, config, caller_key, now, |state| {
+        state.tokens_used += tokens;
+        Ok(true)
+    })
+        .await
+}
+
+/// Applies `change` to the caller's state, refilled up to `now`, and writes it back unless
+/// `change` returns `false`. The write is conditional on the state read, so concurrent
+/// queries of one caller cannot both take the last query or overwrite each other's tokens:
+/// the loser reads the state again and retries.
+async fn update<F>(
+    storage: &dyn ObjectStorage,
+    config: &QuotaConfig,
+    caller_key: &str,
+    now: DateTime<Utc>,
+    change: F,
+) -> Result<(), ApiError>
+where
+    F: Fn(&mut QuotaState) -> Result<bool, ApiError>,
+{
+    let key = quota_key(caller_key);
+
+    for _ in 0..MAX_UPDATE_ATTEMPTS {
+        let (mut state, etag) = load(storage, config, &key, now).await?;
+
+        if !change(&mut state)? {
+            return Ok(());
+        }
+
+        if put_json_if(storage, &config.bucket_name, &key, &state, etag.as_deref()).await? {
+            return Ok(());
+        }
+    }
+
+    Err(ApiError::Throttled(
+        "Too many concurrent queries, please retry".to_string(),
+    ))
+}
+
+/// The caller's state refilled up to `now`, with a new count once the month is over, and the
+/// ETag it was read with. `None` before the caller's first query.
+async fn load(
+    storage: &dyn ObjectStorage,
+    config: &QuotaConfig,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Result<(QuotaState, Option<String>), ApiError> {
+    let month = now.format("%Y-%m").to_string();
+    let burst = config.burst as f64;
+
+    let (state, etag) = match get_json_versioned::<QuotaState>(storage, &config.bucket_name, key)
+        .await?
+    {
+        Some((state, etag)) => (state, Some(etag)),
+        None => (
+            QuotaState {
+                available: burst,
+                refilled_at: now,
+                month: month.clone(),
+                tokens_used: 0,
+            },
+            None,
+        ),
+    };
+
+    let elapsed_seconds = (now - state.refilled_at).num_milliseconds().max(0) as f64 / 1000.0;
+    let refill = elapsed_seconds * config.queries_per_minute as f64 / 60.0;
+
+    let state = QuotaState {
+        available: (state.available + refill).min(burst),
+        refilled_at: now,
+        tokens_used: if state.month == month { state.tokens_used } else { 0 },
+        month,
+    };
+
+    Ok((state, etag))
+}
+
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = match now.month() {
+        12 => (now.year() + 1, 1),
+        month => (now.year(), month + 1),
+    };
+
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+fn seconds_until(time: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (time - now).num_seconds().max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use async_trait::async_trait;
+    use chrono::Duration;
+
+    use shared::storage::{get_json, InMemoryStorage, KeyPage};
+    use shared::Error;
+
+    use super::*;
+
+    /// Lets another query of the caller write its usage right before the first conditional
+    /// write, as a concurrent invocation would.
+    #[derive(Default)]
+    struct RacingStorage {
+        storage: InMemoryStorage,
+        raced: AtomicBool,
+    }
+
+    #[async_trait]
+    impl ObjectStorage for RacingStorage {
+        async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, Error> {
+            self.storage.get_object(bucket, key).await
+        }
+
+        async fn put_object(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_type: &str,
+        ) -> Result<(), Error> {
+            self.storage.put_object(bucket, key, body, content_type).await
+        }
+
+        async fn get_object_versioned(
+            &self,
+            bucket: &str,
+            key: &str,
+        ) -> Result<Option<(Vec<u8>, String)>, Error> {
+            self.storage.get_object_versioned(bucket, key).await
+        }
+
+        async fn put_object_if(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_type: &str,
+            if_match: Option<&str>,
+        ) -> Result<bool, Error> {
+            if !self.raced.swap(true, Ordering::SeqCst) {
+                let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+                record_usage(&self.storage, &config(), "acme/user-1", 100, now).await?;
+            }
+            self.storage
+                .put_object_if(bucket, key, body, content_type, if_match)
+                .await
+        }
+
+        async fn download(&self, bucket: &str, key: &str, path: &Path) -> Result<bool, Error> {
+            self.storage.download(bucket, key, path).await
+        }
+
+        async fn upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            path: &Path,
+            content_type: &str,
+        ) -> Result<(), Error> {
+            self.storage.upload(bucket, key, path, content_type).await
+        }
+
+        async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool, Error> {
+            self.storage.object_exists(bucket, key).await
+        }
+
+        async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), Error> {
+            self.storage.delete_object(bucket, key).await
+        }
+
+        async fn list_keys(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            start_after: Option<&str>,
+            max_keys: usize,
+        ) -> Result<KeyPage, Error> {
+            self.storage
+                .list_keys(bucket, prefix, start_after, max_keys)
+                .await
+        }
+
+        async fn presign_put(
+            &self,
+            bucket: &str,
+            key: &str,
+            task_id: &str,
+            expires_in: std::time::Duration,
+        ) -> Result<String, Error> {
+            self.storage.presign_put(bucket, key, task_id, expires_in).await
+        }
+    }
+
+    fn config() -> QuotaConfig {
+        QuotaConfig {
+            bucket_name: "media".to_string(),
+            burst: 2,
+            queries_per_minute: 6,
+            monthly_token_budget: Some(1000),
+        }
+    }
+
+    fn retry_after(result: Result<(), ApiError>) -> u64 {
+        match result {
+            Err(ApiError::QuotaExceeded {
+                retry_after_seconds,
+                ..
+            }) => retry_after_seconds,
+            other => panic!("expected an exceeded quota, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn refills_the_burst_over_time() {
+        let storage = InMemoryStorage::new();
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+
+        acquire(&storage, &config(), "acme/user-1", now).await.unwrap();
+        acquire(&storage, &config(), "acme/user-1", now).await.unwrap();
+        let later = now + Duration::seconds(4);
+        let result = acquire(&storage, &config(), "acme/user-1", later).await;
+        assert_eq!(retry_after(result), 6);
+
+        acquire(&storage, &config(), "acme/user-2", now).await.unwrap();
+        acquire(&storage, &config(), "acme/user-1", now + Duration::seconds(10))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_limit_the_rate_without_queries_per_minute() {
+        let storage = InMemoryStorage::new();
+        let config = QuotaConfig {
+            queries_per_minute: 0,
+            ..config()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+
+        for _ in 0..5 {
+            acquire(&storage, &config, "acme/user-1", now).await.unwrap();
+        }
+
+        record_usage(&storage, &config, "acme/user-1", 1200, now)
+            .await
+            .unwrap();
+        let result = acquire(&storage, &config, "acme/user-1", now).await;
+        assert!(retry_after(result) > 0);
+    }
+
+    #[tokio::test]
+    async fn stops_queries_once_the_monthly_budget_is_spent() {
+        let storage = InMemoryStorage::new();
+        let now = Utc.with_ymd_and_hms(2024, 12, 31, 23, 0, 0).unwrap();
+
+        acquire(&storage, &config(), "acme/user-1", now).await.unwrap();
+        record_usage(&storage, &config(), "acme/user-1", 1200, now)
+            .await
+            .unwrap();
+
+        let later = now + Duration::minutes(1);
+        let result = acquire(&storage, &config(), "acme/user-1", later).await;
+        assert_eq!(retry_after(result), 59 * 60);
+
+        let next_month = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        acquire(&storage, &config(), "acme/user-1", next_month)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_updates_that_lost_a_race() {
+        let storage = RacingStorage::default();
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+
+        record_usage(&storage, &config(), "acme/user-1", 200, now)
+            .await
+            .unwrap();
+
+        let state: QuotaState = get_json(&storage, "media", "quotas/acme/user-1.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.tokens_used, 300);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::ObjectStorage;
+use crate::Error;
+
+pub const ANALYTICS_PREFIX: &str = "analytics/";
+
+/// A query as the query route answered it, or failed to. Column names are snake case for
+/// Athena.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    /// Returned to the caller as `response_id`, feedback refers to it.
+    pub request_id: String,
+    pub tenant_id: String,
+    pub subject: String,
+    pub timestamp: DateTime<Utc>,
+    pub input: String,
+    pub topic: String,
+    /// Every filter the retrieval applied, by attribute.
+    pub filters: BTreeMap<String, String>,
+    /// Names of the knowledge bases answering.
+    #[serde(default)]
+    pub knowledge_bases: Vec<String>,
+    pub model: String,
+    pub latency_ms: u64,
+    pub cached: bool,
+    /// False when no answer was generated, the questions the transcripts fail to answer.
+    pub answered: bool,
+    pub citation_count: usize,
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+/// What a caller thought of an answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackEntry {
+    pub response_id: String,
+    pub tenant_id: String,
+    pub subject: String,
+    pub timestamp: DateTime<Utc>,
+    pub rating: Rating,
+    pub comment: Option<String>,
+}
+
+/// `analytics/{table}/tenant_id={tenant_id}/dt={date}/{id}.json`, Hive style partitions for
+/// Athena.
+pub fn analytics_key(table: &str, tenant_id: &str, date: NaiveDate, id: &str) -> String {
+    format!(
+        "{}{}/tenant_id={}/dt={}/{}.json",
+        ANALYTICS_PREFIX,
+        table,
+        tenant_id,
+        date.format("%Y-%m-%d"),
+        id
+    )
+}
+
+/// Response ids start with the UTC date of their query, so feedback is filed under the
+/// query's partition however late it comes.
+pub fn response_id(timestamp: DateTime<Utc>, unique_id: &str) -> String {
+    format!("{}-{}", timestamp.format("%Y%m%d"), unique_id)
+}
+
+/// Date of the query a response id was returned for.
+pub fn response_date(response_id: &str) -> Option<NaiveDate> {
+    let (date, _) = response_id.split_once('-')?;
+    match date.len() {
+        8 => NaiveDate::parse_from_str(date, "%Y%m%d").ok(),
+        _ => None,
+    }
+}
+
+pub async fn log_query(
+    storage: &dyn ObjectStorage,
+    bucket_name: &str,
+    entry: &QueryLogEntry,
+) -> Result<(), Error> {
+    let key = analytics_key(
+        "queries",
+        &entry.tenant_id,
+        entry.timestamp.date_naive(),
+        &entry.request_id,
+    );
+    put_json_line(storage, bucket_name, &key, entry).await
+}
+
+/// Feedback is kept per response and caller, a caller changing their mind replaces it, even
+/// days later.
+pub async fn log_feedback(
+    storage: &dyn ObjectStorage,
+    bucket_name: &str,
+    entry: &FeedbackEntry,
+) -> Result<(), Error> {
+    // Ids returned before they carried a date are filed under the day of the feedback.
+    let date = response_date(&entry.response_id).unwrap_or(entry.timestamp.date_naive());
+    let id = format!("{}-{}", entry.response_id, entry.subject);
+    let key = analytics_key("feedback", &entry.tenant_id, date, &id);
+    put_json_line(storage, bucket_name, &key, entry).await
+}
+
+/// S3 objects cannot be appended to, so each entry is a JSON Lines file of its own.
+async fn put_json_line<T: Serialize>(
+    storage: &dyn ObjectStorage,
+    bucket_name: &str,
+    key: &str,
+    entry: &T,
+) -> Result<(), Error> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    storage
+        .put_object(bucket_name, key, line, "application/x-ndjson")
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::{json, Value};
+
+    use crate::storage::InMemoryStorage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_partitioned_json_lines() {
+        let storage = InMemoryStorage::new();
+        let entry = FeedbackEntry {
+            response_id: "response-1".to_string(),
+            tenant_id: "acme".to_string(),
+            subject: "user-1".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap(),
+            rating: Rating::Down,
+            comment: Some("Wrong episode".to_string()),
+        };
+
+        log_feedback(&storage, "media", &entry).await.unwrap();
+
+        let key = "analytics/feedback/tenant_id=acme/dt=2024-07-01/response-1-user-1.json";
+        assert_eq!(storage.keys("media"), vec![key]);
+
+        let line = storage.get_object("media", key).await.unwrap().unwrap();
+        assert!(line.ends_with(b"}\n"));
+        let line: Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(line["rating"], json!("down"));
+        assert_eq!(line["response_id"], json!("response-1"));
+    }
+
+    #[tokio::test]
+    async fn replaces_feedback_under_the_date_of_the_query() {
+        let storage = InMemoryStorage::new();
+        let queried_at = Utc.with_ymd_and_hms(2024, 7, 1, 23, 59, 0).unwrap();
+        let response_id = response_id(queried_at, "V1StGXR8_Z5jdHi6B-myT");
+
+        for (day, rating) in [(2, Rating::Down), (3, Rating::Up)] {
+            let entry = FeedbackEntry {
+                response_id: response_id.clone(),
+                tenant_id: "acme".to_string(),
+                subject: "user-1".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2024, 7, day, 8, 0, 0).unwrap(),
+                rating,
+                comment: None,
+            };
+            log_feedback(&storage, "media", &entry).await.unwrap();
+        }
+
+        let key = "analytics/feedback/tenant_id=acme/dt=2024-07-01/\
+            20240701-V1StGXR8_Z5jdHi6B-myT-user-1.json";
+        assert_eq!(storage.keys("media"), vec![key]);
+        let line: Value =
+            serde_json::from_slice(&storage.get_object("media", key).await.unwrap().unwrap())
+                .unwrap();
+        assert_eq!(line["rating"], json!("up"));
+    }
+
+    #[test]
+    fn reads_the_query_date_from_response_ids() {
+        assert_eq!(
+            response_date("20240701-V1StGXR8_Z5jdHi6B-myT"),
+            NaiveDate::from_ymd_opt(2024, 7, 1)
+        );
+        assert_eq!(response_date("V1StGXR8_Z5jdHi6B-myT"), None);
+        assert_eq!(response_date("response-1"), None);
+        assert_eq!(response_date("20241399-V1StGXR8_Z5jdHi6B-myT"), None);
+    }
+}
@@ -0,0 +1,270 @@
+use lambda_http::aws_lambda_events::apigw::ApiGatewayRequestAuthorizer;
+use lambda_http::request::RequestContext;
+use lambda_http::{Request, RequestExt};
+
+use crate::error::ApiError;
+
+/// Knowledge base metadata attribute holding the tenant owning a document.
+pub const TENANT_ID_ATTRIBUTE: &str = "tenant_id";
+
+/// Where the tenant is read from in the authorizer context: the Cognito custom attribute, a
+/// plain JWT claim, or the context returned by a Lambda authorizer.
+const TENANT_ID_CLAIMS: [&str; 2] = ["custom:tenant_id", "tenant_id"];
+
+/// Cognito puts the groups of the user in this claim.
+const GROUPS_CLAIM: &str = "cognito:groups";
+
+/// What a route requires from its callers.
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    /// Scope the token must grant, such as `media:write`. `None` accepts any token.
+    pub required_scope: Option<String>,
+    /// Members of this group manage every task of their tenant, not only their own.
+    pub admin_group: String,
+}
+
+/// The authenticated user behind a request, from the authorizer claims.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caller {
+    /// The `sub` claim, recorded as the owner of the tasks the caller uploads.
+    pub subject: String,
+    pub tenant_id: String,
+    pub admin: bool,
+}
+
+impl Caller {
+    /// Only the owner of a task or an admin of its tenant may see, update or remove it. Tasks
+    /// uploaded before owners were recorded are left to admins.
+    pub fn can_manage(&self, owner: Option<&str>) -> bool {
+        self.admin || owner == Some(self.subject.as_str())
+    }
+}
+
+/// Identifies the caller and checks the scope the route requires.
+pub fn authorize(request: &Request, policy: &AccessPolicy) -> Result<Caller, ApiError> {
+    let subject = authorizer_value(request, "sub")
+        .ok_or_else(|| ApiError::AccessDenied("The caller is not authenticated".to_string()))?;
+
+    let tenant_id = tenant_id(request)?;
+
+    if let Some(required_scope) = &policy.required_scope {
+        if !scopes(request).contains(required_scope) {
+            return Err(ApiError::AccessDenied(format!(
+                "The {} scope is required",
+                required_scope
+            )));
+        }
+    }
+
+    let admin = authorizer_value(request, GROUPS_CLAIM)
+        .is_some_and(|groups| list_claim(&groups).any(|group| group == policy.admin_group));
+
+    Ok(Caller {
+        subject,
+        tenant_id,
+        admin,
+    })
+}
+
+/// The tenant of the caller. Requests without one are denied, there is no shared namespace
+/// to fall back to.
+fn tenant_id(request: &Request) -> Result<String, ApiError> {
+    let tenant_id = TENANT_ID_CLAIMS
+        .iter()
+        .find_map(|claim| authorizer_value(request, claim))
+        .ok_or_else(|| {
+            ApiError::AccessDenied("The caller is not assigned to a tenant".to_string())
+        })?;
+
+    if !is_valid_tenant_id(&tenant_id) {
+        return Err(ApiError::AccessDenied(format!(
+            "Invalid tenant id {}",
+            tenant_id
+        )));
+    }
+
+    Ok(tenant_id)
+}
+
+/// Tenant ids end up in object keys, so only url safe characters are accepted.
+pub fn is_valid_tenant_id(tenant_id: &str) -> bool {
+    (1..=64).contains(&tenant_id.len())
+        && tenant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Tenant and task of an object key ending in `{tenant_id}/{task_id}`.
+pub fn tenant_and_task(key: &str) -> Option<(&str, &str)> {
+    let mut segments = key.rsplitn(3, '/');
+    let task_id = segments.next().filter(|task_id| !task_id.is_empty())?;
+    let tenant_id = segments.next().filter(|tenant_id| is_valid_tenant_id(tenant_id))?;
+    segments.next()?;
+    Some((tenant_id, task_id))
+}
+
+/// Scopes granted by an access token, as API Gateway parsed them or from the `scope` claim.
+fn scopes(request: &Request) -> Vec<String> {
+    let parsed = authorizer(request)
+        .and_then(|authorizer| authorizer.jwt.as_ref())
+        .and_then(|jwt| jwt.scopes.clone());
+
+    parsed.unwrap_or_else(|| {
+        authorizer_value(request, "scope")
+            .map(|scope| list_claim(&scope).map(str::to_string).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// API Gateway flattens array claims to `[admin editors]`, scopes are space separated.
+fn list_claim(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split([' ', ','])
+        .filter(|item| !item.is_empty())
+}
+
+fn authorizer(request: &Request) -> Option<&ApiGatewayRequestAuthorizer> {
+    match request.request_context_ref()? {
+        RequestContext::ApiGatewayV2(context) => context.authorizer.as_ref(),
+        _ => None,
+    }
+}
+
+fn authorizer_value(request: &Request, name: &str) -> Option<String> {
+    let authorizer = authorizer(request)?;
+
+    let claim = authorizer
+        .jwt
+        .as_ref()
+        .and_then(|jwt| jwt.claims.get(name).cloned());
+
+    claim.or_else(|| {
+        authorizer
+            .fields
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    })
+}
+
+/// Attaches JWT authorizer claims to a request the way API Gateway does, for tests and local
+/// runs of the HTTP lambdas.
+#[cfg(any(test, feature = "test-util"))]
+pub fn with_claims(request: Request, claims: &[(&str, &str)]) -> Request {
+    use lambda_http::aws_lambda_events::apigw::{
+        ApiGatewayRequestAuthorizerJwtDescription, ApiGatewayV2httpRequestContext,
+    };
+
+    let jwt = ApiGatewayRequestAuthorizerJwtDescription {
+        claims: claims
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        scopes: None,
+    };
+
+    request.with_request_context(RequestContext::ApiGatewayV2(ApiGatewayV2httpRequestContext {
+        authorizer: Some(ApiGatewayRequestAuthorizer {
+            jwt: Some(jwt),
+            ..ApiGatewayRequestAuthorizer::default()
+        }),
+        ..ApiGatewayV2httpRequestContext::default()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_http::Body;
+
+    use super::*;
+
+    fn request() -> Request {
+        lambda_http::http::Request::builder()
+            .uri("/media")
+            .body(Body::Empty)
+            .unwrap()
+    }
+
+    fn policy(required_scope: Option<&str>) -> AccessPolicy {
+        AccessPolicy {
+            required_scope: required_scope.map(str::to_string),
+            admin_group: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_the_caller_from_the_jwt_claims() {
+        let request = with_claims(
+            request(),
+            &[
+                ("sub", "user-1"),
+                ("custom:tenant_id", "acme"),
+                ("cognito:groups", "[editors admin]"),
+            ],
+        );
+
+        assert_eq!(
+            authorize(&request, &policy(None)).unwrap(),
+            Caller {
+                subject: "user-1".to_string(),
+                tenant_id: "acme".to_string(),
+                admin: true,
+            }
+        );
+    }
+
+    #[test]
+    fn denies_callers_without_a_valid_tenant() {
+        assert_eq!(authorize(&request(), &policy(None)).unwrap_err().status(), 403);
+
+        let other = with_claims(request(), &[("sub", "user-1"), ("tenant_id", "../other")]);
+        assert_eq!(authorize(&other, &policy(None)).unwrap_err().status(), 403);
+    }
+
+    #[test]
+    fn requires_the_route_scope() {
+        let request = with_claims(
+            request(),
+            &[
+                ("sub", "user-1"),
+                ("tenant_id", "acme"),
+                ("scope", "media:read kb:query"),
+            ],
+        );
+
+        assert!(authorize(&request, &policy(Some("kb:query"))).is_ok());
+        let problem = authorize(&request, &policy(Some("media:write")))
+            .unwrap_err()
+            .to_problem();
+        assert_eq!(problem["detail"], "The media:write scope is required");
+    }
+
+    #[test]
+    fn lets_owners_and_admins_manage_tasks() {
+        let caller = Caller {
+            subject: "user-1".to_string(),
+            tenant_id: "acme".to_string(),
+            admin: false,
+        };
+
+        assert!(caller.can_manage(Some("user-1")));
+        assert!(!caller.can_manage(Some("user-2")));
+        assert!(!caller.can_manage(None));
+        assert!(Caller { admin: true, ..caller }.can_manage(Some("user-2")));
+    }
+
+    #[test]
+    fn splits_tenant_and_task_from_keys() {
+        assert_eq!(
+            tenant_and_task("media-uploads/acme/task-1"),
+            Some(("acme", "task-1"))
+        );
+        assert_eq!(
+            tenant_and_task("s3://media/media-normalized/acme/task-1"),
+            Some(("acme", "task-1"))
+        );
+        assert_eq!(tenant_and_task("media-uploads/task-1"), None);
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::retrieval::AttributeFilter;
+use crate::storage::{get_json, put_json, ObjectStorage};
+use crate::Error;
+
+pub const ANSWER_CACHE_PREFIX: &str = "answer-cache/";
+
+/// Identifies a question asked of a model about the documents of a tenant's topic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswerCacheKey {
+    pub tenant_id: String,
+    pub topic: String,
+    /// Lowercase, with whitespace collapsed and trailing punctuation trimmed.
+    pub input: String,
+    /// Every filter of the query, sorted by key.
+    pub filters: Vec<AttributeFilter>,
+    /// Names of the knowledge bases answering, sorted.
+    pub knowledge_bases: Vec<String>,
+    pub model: String,
+}
+
+impl AnswerCacheKey {
+    pub fn new(
+        tenant_id: &str,
+        topic: &str,
+        input: &str,
+        filters: &[AttributeFilter],
+        knowledge_bases: &[String],
+        model: &str,
+    ) -> Self {
+        let mut filters = filters.to_vec();
+        filters.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.value.cmp(&b.value)));
+        let mut knowledge_bases = knowledge_bases.to_vec();
+        knowledge_bases.sort();
+
+        AnswerCacheKey {
+            tenant_id: tenant_id.to_string(),
+            topic: topic.to_string(),
+            input: normalize_input(input),
+            filters,
+            knowledge_bases,
+            model: model.to_string(),
+        }
+    }
+
+    /// Hex SHA-256 of every part of the key.
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}\n{}\n{}", self.tenant_id, self.topic, self.input).as_bytes());
+        for filter in &self.filters {
+            hasher.update(format!("\n{}={}", filter.key, filter.value).as_bytes());
+        }
+        hasher.update(format!("\n{}\n{}", self.knowledge_bases.join(","), self.model).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// `How does Lambda scale?` and `how does  lambda scale` are the same question.
+pub fn normalize_input(input: &str) -> String {
+    input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['?', '!', '.'])
+        .trim_end()
+        .to_lowercase()
+}
+
+/// What the query route answered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedAnswer {
+    pub output: String,
+    pub sources: Vec<String>,
+    /// Retrieved passages the answer was generated from.
+    #[serde(default)]
+    pub citation_count: usize,
+    /// The sources of each knowledge base answering.
+    #[serde(default)]
+    pub sources_by_knowledge_base: BTreeMap<String, Vec<String>>,
+}
+
+/// What the cache holds for a key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheLookup {
+    pub answer: Option<CachedAnswer>,
+    /// Data version of the topic at lookup time. An answer generated after the lookup is
+    /// stored under it, so it is never served once the topic is invalidated in between.
+    pub version: String,
+}
+
+/// Answers to repeated questions, valid until documents of their topic are ingested again.
+#[async_trait]
+pub trait AnswerCache: Send + Sync {
+    async fn get(&self, key: &AnswerCacheKey) -> Result<CacheLookup, Error>;
+
+    /// Stores the answer under the topic `version` returned by the lookup.
+    async fn put(
+        &self,
+        key: &AnswerCacheKey,
+        version: &str,
+        answer: &CachedAnswer,
+    ) -> Result<(), Error>;
+
+    /// Forgets the answers about the topic, once its knowledge base documents changed.
+    async fn invalidate_topic(&self, tenant_id: &str, topic: &str) -> Result<(), Error>;
+}
+
+/// Data version of a topic, bumped by every invalidation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TopicVersion {
+    version: String,
+}
+
+/// Stores answers under `answer-cache/{tenant_id}/{topic}/{version}/`, where the version of the
+/// topic changes on every invalidation. Entries of older versions are never read again and are
+/// left to the bucket lifecycle rule.
+pub struct S3AnswerCache {
+    storage: Arc<dyn ObjectStorage>,
+    bucket_name: String,
+}
+
+impl S3AnswerCache {
+    pub fn new(storage: Arc<dyn ObjectStorage>, bucket_name: String) -> Self {
+        S3AnswerCache {
+            storage,
+            bucket_name,
+        }
+    }
+
+    /// Topics are free text, their hash keeps the object keys short and safe.
+    fn topic_prefix(tenant_id: &str, topic: &str) -> String {
+        format!(
+            "{}{}/{}/",
+            ANSWER_CACHE_PREFIX,
+            tenant_id,
+            hex::encode(Sha256::digest(topic.as_bytes()))
+        )
+    }
+
+    fn entry_key(key: &AnswerCacheKey, version: &str) -> String {
+        format!(
+            "{}{}/{}.json",
+            Self::topic_prefix(&key.tenant_id, &key.topic),
+            version,
+            key.digest()
+        )
+    }
+}
+
+#[async_trait]
+impl AnswerCache for S3AnswerCache {
+    async fn get(&self, key: &AnswerCacheKey) -> Result<CacheLookup, Error> {
+        let version = get_json::<TopicVersion>(
+            self.storage.as_ref(),
+            &self.bucket_name,
+            &format!("{}version.json", Self::topic_prefix(&key.tenant_id, &key.topic)),
+        )
+            .await?
+            .map_or_else(|| "0".to_string(), |topic_version| topic_version.version);
+
+        let answer = get_json(
+            self.storage.as_ref(),
+            &self.bucket_name,
+            &Self::entry_key(key, &version),
+        )
+            .await?;
+
+        Ok(CacheLookup { answer, version })
+    }
+
+    async fn put(
+        &self,
+        key: &AnswerCacheKey,
+        version: &str,
+        answer: &CachedAnswer,
+    ) -> Result<(), Error> {
+        let entry_key = Self::entry_key(key, version);
+        put_json(self.storage.as_ref(), &self.bucket_name, &entry_key, answer).await
+    }
+
+    async fn invalidate_topic(&self, tenant_id: &str, topic: &str) -> Result<(), Error> {
+        put_json(
+            self.storage.as_ref(),
+            &self.bucket_name,
+            &format!("{}version.json", Self::topic_prefix(tenant_id, topic)),
+            &TopicVersion {
+                version: Utc::now().timestamp_millis().to_string(),
+            },
+        )
+            .await
+    }
+}
+
+/// Keeps the answers in memory, for tests and local runs.
+#[derive(Default)]
+pub struct InMemoryAnswerCache {
+    answers: Mutex<HashMap<String, (AnswerCacheKey, CachedAnswer)>>,
+    /// Invalidations per tenant and topic.
+    versions: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl InMemoryAnswerCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keys(&self) -> Vec<AnswerCacheKey> {
+        self.answers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn version(&self, key: &AnswerCacheKey) -> String {
+        self.versions
+            .lock()
+            .unwrap()
+            .get(&(key.tenant_id.clone(), key.topic.clone()))
+            .copied()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+#[async_trait]
+impl AnswerCache for InMemoryAnswerCache {
+    async fn get(&self, key: &AnswerCacheKey) -> Result<CacheLookup, Error> {
+        let answer = self
+            .answers
+            .lock()
+            .unwrap()
+            .get(&key.digest())
+            .filter(|(cached_key, _)| cached_key == key)
+            .map(|(_, answer)| answer.clone());
+
+        Ok(CacheLookup {
+            answer,
+            version: self.version(key),
+        })
+    }
+
+    /// Answers looked up before an invalidation of their topic are dropped.
+    async fn put(
+        &self,
+        key: &AnswerCacheKey,
+        version: &str,
+        answer: &CachedAnswer,
+    ) -> Result<(), Error> {
+        if version == self.version(key) {
+            self.answers
+                .lock()
+                .unwrap()
+                .insert(key.digest(), (key.clone(), answer.clone()));
+        }
+        Ok(())
+    }
+
+    async fn invalidate_topic(&self, tenant_id: &str, topic: &str) -> Result<(), Error> {
+        self.answers
+            .lock()
+            .unwrap()
+            .retain(|_, (key, _)| key.tenant_id != tenant_id || key.topic != topic);
+        *self
+            .versions
+            .lock()
+            .unwrap()
+            .entry((tenant_id.to_string(), topic.to_string()))
+            .or_default() += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::InMemoryStorage;
+
+    use super::*;
+
+    fn key(tenant_id: &str, topic: &str, input: &str) -> AnswerCacheKey {
+        AnswerCacheKey::new(
+            tenant_id,
+            topic,
+            input,
+            &[
+                AttributeFilter {
+                    key: "topic".to_string(),
+                    value: topic.to_string(),
+                },
+                AttributeFilter {
+                    key: "tenant_id".to_string(),
+                    value: tenant_id.to_string(),
+                },
+            ],
+            &["podcasts".to_string()],
+            "claude",
+        )
+    }
+
+    fn answer(output: &str) -> CachedAnswer {
+        CachedAnswer {
+            output: output.to_string(),
+            sources: vec!["https://example.com/1".to_string()],
+            citation_count: 1,
+            sources_by_knowledge_base: BTreeMap::from([(
+                "podcasts".to_string(),
+                vec!["https://example.com/1".to_string()],
+            )]),
+        }
+    }
+
+    #[test]
+    fn normalizes_the_question() {
+        assert_eq!(
+            normalize_input("  How does\n Lambda   SCALE ?? "),
+            "how does lambda scale"
+        );
+        assert_eq!(
+            key("acme", "serverless", "How does Lambda scale?"),
+            key("acme", "serverless", "how does lambda scale")
+        );
+        assert_eq!(key("acme", "serverless", "Why?").filters[0].key, "tenant_id");
+    }
+
+    #[tokio::test]
+    async fn invalidates_the_answers_of_a_topic() {
+        let cache = S3AnswerCache::new(Arc::new(InMemoryStorage::new()), "media".to_string());
+        let serverless = key("acme", "serverless", "How does Lambda scale?");
+        let containers = key("acme", "containers", "How does Lambda scale?");
+
+        cache.put(&serverless, "0", &answer("It scales")).await.unwrap();
+        cache.put(&containers, "0", &answer("It does not")).await.unwrap();
+        assert_eq!(
+            cached(&cache, &key("acme", "serverless", "how does lambda scale")).await,
+            Some(answer("It scales"))
+        );
+        assert_eq!(
+            cached(&cache, &key("globex", "serverless", "How does Lambda scale?")).await,
+            None
+        );
+
+        cache.invalidate_topic("acme", "serverless").await.unwrap();
+
+        assert_eq!(cached(&cache, &serverless).await, None);
+        assert_eq!(cached(&cache, &containers).await, Some(answer("It does not")));
+    }
+
+    async fn cached(cache: &dyn AnswerCache, key: &AnswerCacheKey) -> Option<CachedAnswer> {
+        cache.get(key).await.unwrap().answer
+    }
+
+    #[tokio::test]
+    async fn never_serves_answers_generated_across_an_invalidation() {
+        let s3 = S3AnswerCache::new(Arc::new(InMemoryStorage::new()), "media".to_string());
+        let in_memory = InMemoryAnswerCache::new();
+        let serverless = key("acme", "serverless", "How does Lambda scale?");
+
+        for cache in [&s3 as &dyn AnswerCache, &in_memory] {
+            let lookup = cache.get(&serverless).await.unwrap();
+            assert_eq!(lookup.answer, None);
+
+            // New documents of the topic are indexed while the answer is generated.
+            cache.invalidate_topic("acme", "serverless").await.unwrap();
+            cache
+                .put(&serverless, &lookup.version, &answer("It scales"))
+                .await
+                .unwrap();
+
+            assert_eq!(cached(cache, &serverless).await, None);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+
+/// How transcript words are cleaned before they reach the knowledge base.
+///
+/// Every option is optional so an upload only overrides the options it sets, see
+/// [`CleaningOptions::with_overrides`]. Unset options leave the transcript untouched.
+///
+/// Validated fields spell out their `rename`: serde_valid otherwise names the field after the
+/// `skip_serializing_if` path in its errors.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CleaningOptions {
+    /// Words Transcribe recognized with a lower confidence are handled as `lowConfidence` says.
+    #[validate(minimum = 0.0)]
+    #[validate(maximum = 1.0)]
+    #[serde(rename = "minConfidence", default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_confidence: Option<LowConfidence>,
+    /// Drops filler words such as "um" and "uh".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remove_fillers: Option<bool>,
+    /// Speaker turns shorter than this are merged into the previous turn.
+    #[validate(minimum = 0.0)]
+    #[serde(rename = "mergeTurnsUnderSeconds", default, skip_serializing_if = "Option::is_none")]
+    pub merge_turns_under_seconds: Option<f64>,
+    /// Collapses repeated punctuation, capitalizes sentences and terminates every turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize_punctuation: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowConfidence {
+    /// Keep the word, suffixed with `(?)`.
+    #[default]
+    Mark,
+    Drop,
+}
+
+impl CleaningOptions {
+    /// Deployment defaults with the options an upload set taking precedence.
+    pub fn with_overrides(&self, overrides: Option<&CleaningOptions>) -> CleaningOptions {
+        let Some(overrides) = overrides else {
+            return self.clone();
+        };
+
+        CleaningOptions {
+            min_confidence: overrides.min_confidence.or(self.min_confidence),
+            low_confidence: overrides.low_confidence.or(self.low_confidence),
+            remove_fillers: overrides.remove_fillers.or(self.remove_fillers),
+            merge_turns_under_seconds: overrides
+                .merge_turns_under_seconds
+                .or(self.merge_turns_under_seconds),
+            normalize_punctuation: overrides
+                .normalize_punctuation
+                .or(self.normalize_punctuation),
+        }
+    }
+
+    /// Whether any option changes the transcript.
+    pub fn is_enabled(&self) -> bool {
+        self.min_confidence.is_some()
+            || self.remove_fillers == Some(true)
+            || self.merge_turns_under_seconds.is_some()
+            || self.normalize_punctuation == Some(true)
+    }
+}
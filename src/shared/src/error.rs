@@ -0,0 +1,290 @@
+use std::fmt;
+
+use aws_sdk_s3::error::SdkError;
+use aws_smithy_types::error::metadata::ProvideErrorMetadata;
+use lambda_http::tracing::error;
+use lambda_http::{Body, Response};
+use serde_json::{json, Value};
+use serde_valid::validation::Errors;
+
+use crate::validation::{field_errors, FieldError};
+use crate::Error;
+
+/// Errors the HTTP lambdas answer with an RFC 7807 `application/problem+json` body.
+///
+/// AWS SDK errors are classified by their error code so throttling, access denied and missing
+/// resources keep their meaning instead of all surfacing as internal errors.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request could not be parsed.
+    BadRequest(String),
+    /// The request body failed to parse or validate, field by field.
+    Validation(Vec<FieldError>),
+    NotFound(String),
+    AccessDenied(String),
+    Throttled(String),
+    /// A rate limit or budget of the caller was hit, it may retry after that many seconds.
+    QuotaExceeded {
+        detail: String,
+        retry_after_seconds: u64,
+    },
+    Internal(Error),
+}
+
+impl ApiError {
+    /// Classifies a service error from its AWS error code.
+    pub fn from_service_error<E: ProvideErrorMetadata>(err: E, fallback: Error) -> Self {
+        let message = err
+            .message()
+            .map(str::to_string)
+            .unwrap_or_else(|| fallback.to_string());
+
+        match err.code() {
+            Some(
+                "ThrottlingException" | "Throttling" | "TooManyRequestsException" | "SlowDown"
+                | "RequestLimitExceeded" | "ProvisionedThroughputExceededException",
+            ) => ApiError::Throttled(message),
+            Some(
+                "AccessDenied" | "AccessDeniedException" | "UnauthorizedOperation"
+                | "UnrecognizedClientException",
+            ) => ApiError::AccessDenied(message),
+            Some("NoSuchKey" | "NoSuchBucket" | "NotFound" | "ResourceNotFoundException") => {
+                ApiError::NotFound(message)
+            }
+            Some("ValidationException" | "BadRequestException") => {
+                ApiError::BadRequest(message)
+            }
+            _ => ApiError::Internal(fallback),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Validation(_) => 400,
+            ApiError::AccessDenied(_) => 403,
+            ApiError::NotFound(_) => 404,
+            ApiError::Throttled(_) | ApiError::QuotaExceeded { .. } => 429,
+            ApiError::Internal(_) => 500,
+        }
+    }
+
+    /// Stable machine readable code, also used in the problem `type`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad-request",
+            ApiError::Validation(_) => "validation-failed",
+            ApiError::NotFound(_) => "not-found",
+            ApiError::AccessDenied(_) => "access-denied",
+            ApiError::Throttled(_) => "throttled",
+            ApiError::QuotaExceeded { .. } => "quota-exceeded",
+            ApiError::Internal(_) => "internal-error",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "Bad request",
+            ApiError::Validation(_) => "Validation failed",
+            ApiError::NotFound(_) => "Not found",
+            ApiError::AccessDenied(_) => "Access denied",
+            ApiError::Throttled(_) => "Too many requests",
+            ApiError::QuotaExceeded { .. } => "Quota exceeded",
+            ApiError::Internal(_) => "Internal error",
+        }
+    }
+
+    pub fn to_problem(&self) -> Value {
+        let mut problem = json!({
+            "type": format!("urn:media-rag:problem:{}", self.code()),
+            "title": self.title(),
+            "status": self.status(),
+            "code": self.code(),
+        });
+
+        match self {
+            ApiError::BadRequest(detail)
+            | ApiError::NotFound(detail)
+            | ApiError::AccessDenied(detail)
+            | ApiError::Throttled(detail) => problem["detail"] = json!(detail),
+            ApiError::QuotaExceeded {
+                detail,
+                retry_after_seconds,
+            } => {
+                problem["detail"] = json!(detail);
+                problem["retryAfter"] = json!(retry_after_seconds);
+            }
+            ApiError::Validation(errors) => {
+                problem["detail"] = json!("The request content is invalid");
+                problem["errors"] = json!(errors);
+            }
+            // Internal details stay in the logs.
+            ApiError::Internal(_) => problem["detail"] = json!("An unexpected error occurred"),
+        }
+
+        problem
+    }
+
+    pub fn into_response(self) -> Result<Response<Body>, Error> {
+        if let ApiError::Internal(err) = &self {
+            error!(error = %err, "request failed");
+        }
+
+        let mut response = Response::builder()
+            .status(self.status())
+            .header("content-type", "application/problem+json");
+
+        if let ApiError::QuotaExceeded {
+            retry_after_seconds,
+            ..
+        } = &self
+        {
+            response = response.header("retry-after", retry_after_seconds.to_string());
+        }
+
+        Ok(response
+            .body(self.to_problem().to_string().into())
+            .map_err(Box::new)?)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(detail)
+            | ApiError::NotFound(detail)
+            | ApiError::AccessDenied(detail)
+            | ApiError::Throttled(detail)
+            | ApiError::QuotaExceeded { detail, .. } => {
+                write!(f, "{}: {}", self.title(), detail)
+            }
+            ApiError::Validation(errors) => {
+                let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+                write!(f, "{}: {}", self.title(), messages.join("; "))
+            }
+            ApiError::Internal(err) => write!(f, "{}: {}", self.title(), err),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl<E, R> From<SdkError<E, R>> for ApiError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    R: fmt::Debug + Send + Sync + 'static,
+{
+    fn from(err: SdkError<E, R>) -> Self {
+        match err {
+            SdkError::ServiceError(context) => {
+                let service_error = context.into_err();
+                let metadata = service_error.meta().clone();
+                ApiError::from_service_error(metadata, Box::new(service_error))
+            }
+            err => ApiError::Internal(Box::new(err)),
+        }
+    }
+}
+
+impl From<FieldError> for ApiError {
+    fn from(err: FieldError) -> Self {
+        ApiError::Validation(vec![err])
+    }
+}
+
+impl From<Errors> for ApiError {
+    fn from(errors: Errors) -> Self {
+        ApiError::Validation(field_errors(&errors))
+    }
+}
+
+impl From<lambda_http::http::Error> for ApiError {
+    fn from(err: lambda_http::http::Error) -> Self {
+        ApiError::Internal(Box::new(err))
+    }
+}
+
+/// Recovers the [`ApiError`] a shared implementation failed with, anything else is internal.
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err.downcast::<ApiError>() {
+            Ok(err) => *err,
+            Err(err) => ApiError::Internal(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_smithy_types::error::ErrorMetadata;
+
+    use super::*;
+
+    fn service_error(code: &str) -> ApiError {
+        ApiError::from_service_error(
+            ErrorMetadata::builder().code(code).message("from AWS").build(),
+            Error::from("fallback"),
+        )
+    }
+
+    #[test]
+    fn classifies_service_errors_by_code() {
+        assert_eq!(service_error("ThrottlingException").status(), 429);
+        assert_eq!(service_error("SlowDown").status(), 429);
+        assert_eq!(service_error("AccessDeniedException").status(), 403);
+        assert_eq!(service_error("NoSuchKey").status(), 404);
+        assert_eq!(service_error("ResourceNotFoundException").status(), 404);
+        assert_eq!(service_error("ValidationException").status(), 400);
+        assert_eq!(service_error("InternalServerException").status(), 500);
+    }
+
+    #[test]
+    fn keeps_internal_details_out_of_problems() {
+        let problem = service_error("InternalServerException").to_problem();
+
+        assert_eq!(problem["type"], "urn:media-rag:problem:internal-error");
+        assert_eq!(problem["code"], "internal-error");
+        assert_eq!(problem["status"], 500);
+        assert_eq!(problem["detail"], "An unexpected error occurred");
+    }
+
+    #[test]
+    fn recovers_api_errors_from_boxed_errors() {
+        let boxed: Error = Box::new(ApiError::NotFound("No such task".to_string()));
+        assert_eq!(ApiError::from(boxed).to_problem()["detail"], "No such task");
+
+        let boxed = Error::from("boom");
+        assert_eq!(ApiError::from(boxed).code(), "internal-error");
+    }
+
+    #[test]
+    fn responds_with_problem_json() {
+        let response = ApiError::Throttled("Slow down".to_string())
+            .into_response()
+            .unwrap();
+
+        assert_eq!(response.status(), 429);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/problem+json"
+        );
+        let problem: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(problem["title"], "Too many requests");
+        assert!(!response.headers().contains_key("retry-after"));
+    }
+
+    #[test]
+    fn tells_when_to_retry_once_a_quota_is_exceeded() {
+        let response = ApiError::QuotaExceeded {
+            detail: "Too many queries".to_string(),
+            retry_after_seconds: 12,
+        }
+            .into_response()
+            .unwrap();
+
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "12");
+        let problem: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(problem["code"], "quota-exceeded");
+        assert_eq!(problem["retryAfter"], 12);
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use aws_sdk_eventbridge::types::PutEventsRequestEntry;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::task::TaskStatus;
+use crate::error::ApiError;
+use crate::Error;
+
+pub const EVENT_SOURCE: &str = "media-rag";
+
+pub const TASK_STATUS_CHANGED_DETAIL_TYPE: &str = "Task Status Changed";
+
+/// Detail of the `Task Status Changed` event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatusChanged {
+    pub task_id: String,
+    pub status: TaskStatus,
+    pub transcript_location: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+/// Publishes `media-rag` events.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, detail_type: &str, detail: Value) -> Result<(), Error>;
+
+    async fn task_status_changed(&self, event: &TaskStatusChanged) -> Result<(), Error> {
+        self.publish(TASK_STATUS_CHANGED_DETAIL_TYPE, serde_json::to_value(event)?)
+            .await
+    }
+}
+
+pub struct EventBridgePublisher {
+    eventbridge_client: aws_sdk_eventbridge::Client,
+    event_bus_name: String,
+}
+
+impl EventBridgePublisher {
+    pub fn new(eventbridge_client: aws_sdk_eventbridge::Client, event_bus_name: String) -> Self {
+        EventBridgePublisher {
+            eventbridge_client,
+            event_bus_name,
+        }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for EventBridgePublisher {
+    async fn publish(&self, detail_type: &str, detail: Value) -> Result<(), Error> {
+        let output = self
+            .eventbridge_client
+            .put_events()
+            .entries(
+                PutEventsRequestEntry::builder()
+                    .event_bus_name(&self.event_bus_name)
+                    .source(EVENT_SOURCE)
+                    .detail_type(detail_type)
+                    .detail(detail.to_string())
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+
+        if output.failed_entry_count > 0 {
+            return Err(format!("Failed to publish {} event", detail_type).into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryEventPublisher {
+    events: Mutex<Vec<(String, Value)>>,
+}
+
+impl InMemoryEventPublisher {
+    pub fn new() -> Self {
+        InMemoryEventPublisher::default()
+    }
+
+    /// Detail type and detail of every event published so far.
+    pub fn events(&self) -> Vec<(String, Value)> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InMemoryEventPublisher {
+    async fn publish(&self, detail_type: &str, detail: Value) -> Result<(), Error> {
+        self.events
+            .lock()
+            .unwrap()
+            .push((detail_type.to_string(), detail));
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+use futures::future::try_join_all;
+use serde_json::Value;
+
+use crate::error::ApiError;
+use crate::generation::TextGeneration;
+use crate::retrieval::{GeneratedAnswer, Passage, Retrieval, RetrievalQuery};
+use crate::Error;
+
+/// Reference metadata attribute naming the knowledge base a passage was retrieved from.
+pub const KNOWLEDGE_BASE_ATTRIBUTE: &str = "knowledge_base";
+
+/// Selects every knowledge base.
+pub const ALL_KNOWLEDGE_BASES: &str = "*";
+
+const SYSTEM_PROMPT: &str = "You answer questions from the search results of several \
+knowledge bases. Only use the search results, and say so when they do not contain the answer.";
+
+/// The knowledge bases queries can target by name. A single one answers with its own
+/// retrieval and generation, answers over several are generated from their combined passages.
+pub struct KnowledgeBases {
+    /// Answers the queries naming no knowledge base.
+    pub default_name: String,
+    pub retrievals: BTreeMap<String, Arc<dyn Retrieval>>,
+    pub generation: Arc<dyn TextGeneration>,
+    /// The model behind `generation`.
+    pub generation_model: String,
+    /// Passages an answer over several knowledge bases is generated from, best scored first.
+    pub max_passages: usize,
+}
+
+impl KnowledgeBases {
+    /// Sorted names of the knowledge bases a query targets, the default one when it names none.
+    pub fn select(&self, names: &[String]) -> Result<Vec<String>, ApiError> {
+        if names.is_empty() {
+            return Ok(vec![self.default_name.clone()]);
+        }
+
+        if names.iter().any(|name| name == ALL_KNOWLEDGE_BASES) {
+            return Ok(self.retrievals.keys().cloned().collect());
+        }
+
+        let names: BTreeSet<_> = names.iter().cloned().collect();
+        match names.iter().find(|name| !self.retrievals.contains_key(*name)) {
+            Some(unknown) => Err(ApiError::BadRequest(format!(
+                "Unknown knowledge base {}",
+                unknown
+            ))),
+            None => Ok(names.into_iter().collect()),
+        }
+    }
+
+    /// The model generating the answers over the selected knowledge bases.
+    pub fn model(&self, selected: &[String]) -> &str {
+        match selected {
+            [name] => self.retrieval(name).map_or("", |retrieval| retrieval.model()),
+            _ => &self.generation_model,
+        }
+    }
+
+    /// Returns `None` when no answer was generated. Every reference of the answer names its
+    /// knowledge base in the `knowledge_base` attribute.
+    pub async fn retrieve_and_generate(
+        &self,
+        selected: &[String],
+        query: &RetrievalQuery,
+    ) -> Result<Option<GeneratedAnswer>, Error> {
+        let [name] = selected else {
+            return self.generate_from_passages(selected, query).await;
+        };
+
+        let answer = self.retrieval(name)?.retrieve_and_generate(query).await?;
+
+        Ok(answer.map(|mut answer| {
+            for reference in &mut answer.references {
+                reference.metadata.insert(
+                    KNOWLEDGE_BASE_ATTRIBUTE.to_string(),
+                    Value::String(name.clone()),
+                );
+            }
+            answer
+        }))
+    }
+
+    fn retrieval(&self, name: &str) -> Result<&Arc<dyn Retrieval>, Error> {
+        self.retrievals
+            .get(name)
+            .ok_or_else(|| Error::from(format!("Unknown knowledge base {}", name)))
+    }
+
+    /// Retrieves from every knowledge base in parallel, then makes one generation call over
+    /// the best passages of all of them.
+    async fn generate_from_passages(
+        &self,
+        selected: &[String],
+        query: &RetrievalQuery,
+    ) -> Result<Option<GeneratedAnswer>, Error> {
+        let retrieved = try_join_all(selected.iter().map(|name| async move {
+            let passages = self.retrieval(name)?.retrieve(query).await?;
+            Ok::<_, Error>(passages.into_iter().map(|mut passage| {
+                passage.reference.metadata.insert(
+                    KNOWLEDGE_BASE_ATTRIBUTE.to_string(),
+                    Value::String(name.clone()),
+                );
+                passage
+            }))
+        }))
+            .await?;
+
+        let passages = merge_passages(retrieved.into_iter().flatten(), self.max_passages);
+        if passages.is_empty() {
+            return Ok(None);
+        }
+
+        let reply = self
+            .generation
+            .converse(SYSTEM_PROMPT, &build_prompt(&query.input, &passages))
+            .await?;
+
+        Ok(Some(GeneratedAnswer {
+            text: reply.text,
+            references: passages.into_iter().map(|passage| passage.reference).collect(),
+            usage: reply.usage,
+        }))
+    }
+}
+
+/// Keeps the best scored copy of passages found in several knowledge bases, then the best
+/// `max_passages` of them, best first.
+fn merge_passages(passages: impl Iterator<Item = Passage>, max_passages: usize) -> Vec<Passage> {
+    let mut best: HashMap<(Option<String>, Option<String>), Passage> = HashMap::new();
+
+    for passage in passages {
+        let key = (passage.reference.uri.clone(), passage.reference.text.clone());
+        match best.get(&key) {
+            Some(kept) if kept.score >= passage.score => {}
+            _ => {
+                best.insert(key, passage);
+            }
+        }
+    }
+
+    let mut passages: Vec<_> = best.into_values().collect();
+    passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    passages.truncate(max_passages);
+    passages
+}
+
+fn build_prompt(input: &str, passages: &[Passage]) -> String {
+    let results = passages
+        .iter()
+        .enumerate()
+        .map(|(i, passage)| {
+            format!(
+                "<search_result index=\"{}\" knowledge_base=\"{}\">\n{}\n</search_result>",
+                i + 1,
+                passage.reference.metadata[KNOWLEDGE_BASE_ATTRIBUTE]
+                    .as_str()
+                    .unwrap_or_default(),
+                passage.reference.text.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "<search_results>\n{}\n</search_results>\n\nQuestion: {}",
+        results, input
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map};
+
+    use crate::generation::InMemoryGeneration;
+    use crate::retrieval::{InMemoryRetrieval, Reference, TokenUsage};
+
+    use super::*;
+
+    fn reference(uri: &str, text: &str) -> Reference {
+        let mut metadata = Map::new();
+        metadata.insert("topic".to_string(), json!("serverless"));
+        metadata.insert("source_url".to_string(), json!(format!("https://example.com/{}", uri)));
+
+        Reference {
+            uri: Some(format!("s3://kb/{}", uri)),
+            text: Some(text.to_string()),
+            metadata,
+        }
+    }
+
+    fn knowledge_bases(generation: Arc<InMemoryGeneration>) -> KnowledgeBases {
+        let podcasts = InMemoryRetrieval::new(vec![
+            reference("podcast-1", "Lambda scales out per request"),
+            reference("shared", "Lambda scales"),
+        ]);
+        let webinars = InMemoryRetrieval::new(vec![
+            reference("webinar-1", "Provisioned concurrency"),
+            reference("shared", "Lambda scales"),
+        ]);
+
+        KnowledgeBases {
+            default_name: "podcasts".to_string(),
+            retrievals: BTreeMap::from([
+                ("podcasts".to_string(), Arc::new(podcasts) as Arc<dyn Retrieval>),
+                ("webinars".to_string(), Arc::new(webinars) as Arc<dyn Retrieval>),
+            ]),
+            generation,
+            generation_model: "claude".to_string(),
+            max_passages: 2,
+        }
+    }
+
+    #[test]
+    fn selects_knowledge_bases_by_name() {
+        let knowledge_bases = knowledge_bases(Arc::new(InMemoryGeneration::new("")));
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(knowledge_bases.select(&[]).unwrap(), names(&["podcasts"]));
+        assert_eq!(
+            knowledge_bases.select(&names(&["webinars", "podcasts", "webinars"])).unwrap(),
+            names(&["podcasts", "webinars"])
+        );
+        assert_eq!(
+            knowledge_bases.select(&names(&["*"])).unwrap(),
+            names(&["podcasts", "webinars"])
+        );
+        assert!(knowledge_bases.select(&names(&["support-calls"])).is_err());
+        assert_eq!(knowledge_bases.model(&names(&["podcasts"])), "in-memory");
+        assert_eq!(knowledge_bases.model(&names(&["podcasts", "webinars"])), "claude");
+    }
+
+    #[tokio::test]
+    async fn generates_one_answer_from_the_best_passages_of_every_knowledge_base() {
+        let usage = TokenUsage {
+            input_tokens: 120,
+            output_tokens: 4,
+        };
+        let generation = Arc::new(InMemoryGeneration::new("It scales out").with_usage(usage));
+        let knowledge_bases = knowledge_bases(generation.clone());
+        let query = RetrievalQuery {
+            input: "How does Lambda scale out?".to_string(),
+            filters: Vec::new(),
+        };
+
+        let answer = knowledge_bases
+            .retrieve_and_generate(&knowledge_bases.select(&["*".to_string()]).unwrap(), &query)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(answer.text, "It scales out");
+        assert_eq!(answer.usage, usage);
+        let passages: Vec<_> = answer
+            .references
+            .iter()
+            .map(|reference| {
+                (
+                    reference.uri.as_deref().unwrap(),
+                    reference.metadata[KNOWLEDGE_BASE_ATTRIBUTE].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(passages[0], ("s3://kb/podcast-1", "podcasts"));
+        assert_eq!(passages[1].0, "s3://kb/shared");
+        assert_eq!(passages.len(), 2);
+
+        let prompts = generation.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].1.contains("knowledge_base=\"podcasts\">\nLambda scales out per"));
+        assert!(prompts[0].1.ends_with("Question: How does Lambda scale out?"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ConversationRole, ConverseOutput, ImageBlock, ImageFormat, ImageSource,
+    InferenceConfiguration, Message, SystemContentBlock,
+};
+use aws_smithy_types::Blob;
+
+use crate::error::ApiError;
+use crate::retrieval::TokenUsage;
+use crate::Error;
+
+/// The text of a model reply and the tokens the model reported for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Single turn text generation with a foundation model.
+#[async_trait]
+pub trait TextGeneration: Send + Sync {
+    /// Returns the model's reply to `prompt`.
+    async fn converse(&self, system_prompt: &str, prompt: &str) -> Result<Reply, Error>;
+
+    /// Returns the text of the model's reply to `prompt` about a JPEG image, the model must
+    /// accept image input.
+    async fn describe_image(
+        &self,
+        system_prompt: &str,
+        prompt: &str,
+        jpeg: &[u8],
+    ) -> Result<String, Error>;
+}
+
+pub struct BedrockGeneration {
+    bedrock_runtime_client: aws_sdk_bedrockruntime::Client,
+    model_id: String,
+}
+
+impl BedrockGeneration {
+    pub fn new(bedrock_runtime_client: aws_sdk_bedrockruntime::Client, model_id: String) -> Self {
+        BedrockGeneration {
+            bedrock_runtime_client,
+            model_id,
+        }
+    }
+
+    async fn send(&self, system_prompt: &str, content: Vec<ContentBlock>) -> Result<Reply, Error> {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .set_content(Some(content))
+            .build()
+            .map_err(Box::new)?;
+
+        let result = self
+            .bedrock_runtime_client
+            .converse()
+            .model_id(&self.model_id)
+            .system(SystemContentBlock::Text(system_prompt.to_string()))
+            .messages(message)
+            .inference_config(
+                InferenceConfiguration::builder()
+                    .max_tokens(4096)
+                    .temperature(0.2)
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+
+        let usage = result.usage.map_or_else(TokenUsage::default, |usage| TokenUsage {
+            input_tokens: usage.input_tokens.max(0) as u64,
+            output_tokens: usage.output_tokens.max(0) as u64,
+        });
+
+        match result.output {
+            Some(ConverseOutput::Message(message)) => Ok(Reply {
+                text: message
+                    .content
+                    .into_iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(text) => Some(text),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(""),
+                usage,
+            }),
+            _ => Err(Error::from("The model returned no message")),
+        }
+    }
+}
+
+#[async_trait]
+impl TextGeneration for BedrockGeneration {
+    async fn converse(&self, system_prompt: &str, prompt: &str) -> Result<Reply, Error> {
+        self.send(system_prompt, vec![ContentBlock::Text(prompt.to_string())]).await
+    }
+
+    async fn describe_image(
+        &self,
+        system_prompt: &str,
+        prompt: &str,
+        jpeg: &[u8],
+    ) -> Result<String, Error> {
+        let image = ImageBlock::builder()
+            .format(ImageFormat::Jpeg)
+            .source(ImageSource::Bytes(Blob::new(jpeg)))
+            .build()
+            .map_err(Box::new)?;
+
+        self.send(
+            system_prompt,
+            vec![ContentBlock::Image(image), ContentBlock::Text(prompt.to_string())],
+        )
+            .await
+            .map(|reply| reply.text)
+    }
+}
+
+/// Replies with canned texts in order, the last one is repeated once the others are used up.
+#[derive(Default)]
+pub struct InMemoryGeneration {
+    replies: Mutex<VecDeque<String>>,
+    prompts: Mutex<Vec<(String, String)>>,
+    usage: TokenUsage,
+}
+
+impl InMemoryGeneration {
+    pub fn new(reply: impl Into<String>) -> Self {
+        InMemoryGeneration::with_replies(vec![reply.into()])
+    }
+
+    pub fn with_replies(replies: Vec<String>) -> Self {
+        InMemoryGeneration {
+            replies: Mutex::new(replies.into()),
+            prompts: Mutex::new(Vec::new()),
+            usage: TokenUsage::default(),
+        }
+    }
+
+    /// Reports `usage` for every reply instead of no tokens.
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    fn reply(&self, system_prompt: &str, prompt: &str) -> String {
+        self.prompts
+            .lock()
+            .unwrap()
+            .push((system_prompt.to_string(), prompt.to_string()));
+
+        let mut replies = self.replies.lock().unwrap();
+        match replies.len() {
+            0 | 1 => replies.front().cloned().unwrap_or_default(),
+            _ => replies.pop_front().unwrap_or_default(),
+        }
+    }
+
+    /// System prompt and prompt of every call, in call order.
+    pub fn prompts(&self) -> Vec<(String, String)> {
+        self.prompts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl TextGeneration for InMemoryGeneration {
+    async fn converse(&self, system_prompt: &str, prompt: &str) -> Result<Reply, Error> {
+        Ok(Reply {
+            text: self.reply(system_prompt, prompt),
+            usage: self.usage,
+        })
+    }
+
+    async fn describe_image(
+        &self,
+        system_prompt: &str,
+        prompt: &str,
+        _jpeg: &[u8],
+    ) -> Result<String, Error> {
+        Ok(self.reply(system_prompt, prompt))
+    }
+}
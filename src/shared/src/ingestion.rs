@@ -0,0 +1,521 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use aws_sdk_bedrockagent::types::{
+    ContentDataSourceType, CustomS3Location, DocumentContent, DocumentIdentifier,
+    DocumentMetadata, DocumentStatus, IngestionJobFilter, IngestionJobFilterAttribute,
+    IngestionJobFilterOperator, IngestionJobStatistics,
+    IngestionJobStatus as BedrockIngestionJobStatus, KnowledgeBaseDocument, MetadataSourceType,
+    S3Content, S3Location,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::models::DocType;
+use crate::Error;
+
+pub const INGESTION_JOB_PREFIX: &str = "ingestion-jobs/";
+
+pub const PENDING_INGESTION_JOB_PREFIX: &str = "ingestion-jobs/pending/";
+
+pub const INGESTION_OUTCOME_DETAIL_TYPE: &str = "Ingestion Job Outcome";
+
+/// Message sent to the ingestion queue once a task's documents are in the KB bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionRequest {
+    pub task_id: String,
+    /// Empty in messages sent before tenants were recorded.
+    #[serde(default)]
+    pub tenant_id: String,
+    /// The task's documents were removed, the sync only drops them from the knowledge base.
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestionKind {
+    /// A full data source sync started with `StartIngestionJob`.
+    Sync,
+    /// A single task pushed with `IngestKnowledgeBaseDocuments`.
+    Documents,
+}
+
+/// What the tracker needs to know about a task of an ingestion job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestedTask {
+    /// Documents are stored in a folder per tenant.
+    pub tenant_id: String,
+    /// The documents pushed directly. Empty for syncs, which ingest whatever documents of the
+    /// task the knowledge base bucket holds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<DocType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestionJobStatus {
+    Pending,
+    Complete,
+    Failed,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionStatistics {
+    pub documents_scanned: i64,
+    pub metadata_documents_scanned: i64,
+    pub new_documents_indexed: i64,
+    pub modified_documents_indexed: i64,
+    pub metadata_documents_modified: i64,
+    pub documents_deleted: i64,
+    pub documents_failed: i64,
+}
+
+/// Which tasks a knowledge base ingestion job was started for, and how it ended.
+///
+/// Jobs still running live under `ingestion-jobs/pending/` until the tracker records their
+/// outcome under `ingestion-jobs/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionJobRecord {
+    pub ingestion_job_id: String,
+    pub kind: IngestionKind,
+    pub task_ids: Vec<String>,
+    /// The tasks by id, empty in records written before tenants were recorded.
+    #[serde(default)]
+    pub tasks: BTreeMap<String, IngestedTask>,
+    pub started_at: DateTime<Utc>,
+    pub status: IngestionJobStatus,
+    pub statistics: Option<IngestionStatistics>,
+    #[serde(default)]
+    pub indexed_task_ids: Vec<String>,
+    #[serde(default)]
+    pub failed_task_ids: Vec<String>,
+    #[serde(default)]
+    pub failure_reasons: Vec<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl IngestionJobRecord {
+    pub fn pending(
+        ingestion_job_id: String,
+        kind: IngestionKind,
+        tasks: BTreeMap<String, IngestedTask>,
+    ) -> Self {
+        IngestionJobRecord {
+            ingestion_job_id,
+            kind,
+            task_ids: tasks.keys().cloned().collect(),
+            tasks,
+            started_at: Utc::now(),
+            status: IngestionJobStatus::Pending,
+            statistics: None,
+            indexed_task_ids: Vec::new(),
+            failed_task_ids: Vec::new(),
+            failure_reasons: Vec::new(),
+            completed_at: None,
+        }
+    }
+
+    pub fn key(&self) -> String {
+        match self.status {
+            IngestionJobStatus::Pending => {
+                format!("{}{}.json", PENDING_INGESTION_JOB_PREFIX, self.ingestion_job_id)
+            }
+            _ => format!("{}{}.json", INGESTION_JOB_PREFIX, self.ingestion_job_id),
+        }
+    }
+}
+
+/// Detail of the `Ingestion Job Outcome` event published once a job is finished.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionOutcome {
+    pub ingestion_job_id: String,
+    pub status: IngestionJobStatus,
+    pub statistics: Option<IngestionStatistics>,
+    pub indexed_task_ids: Vec<String>,
+    pub failed_task_ids: Vec<String>,
+}
+
+impl From<&IngestionJobRecord> for IngestionOutcome {
+    fn from(record: &IngestionJobRecord) -> Self {
+        IngestionOutcome {
+            ingestion_job_id: record.ingestion_job_id.clone(),
+            status: record.status,
+            statistics: record.statistics.clone(),
+            indexed_task_ids: record.indexed_task_ids.clone(),
+            failed_task_ids: record.failed_task_ids.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionJobState {
+    Running,
+    Complete,
+    Failed,
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestionJobDetails {
+    pub state: IngestionJobState,
+    pub statistics: Option<IngestionStatistics>,
+    pub failure_reasons: Vec<String>,
+}
+
+/// Where a single document stands in the knowledge base.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentState {
+    Pending,
+    Indexed,
+    Failed(String),
+}
+
+/// Queue coalescing ingestion requests into data source syncs.
+#[async_trait]
+pub trait IngestionQueue: Send + Sync {
+    async fn enqueue(&self, request: &IngestionRequest) -> Result<(), Error>;
+}
+
+/// The knowledge base ingestion operations the handlers rely on, scoped to one data source.
+#[async_trait]
+pub trait Ingestion: Send + Sync {
+    /// Number of ingestion jobs starting, in progress or stopping.
+    async fn running_ingestion_jobs(&self) -> Result<usize, Error>;
+
+    /// Starts a full data source sync and returns its ingestion job id.
+    async fn start_ingestion_job(&self) -> Result<String, Error>;
+
+    async fn get_ingestion_job(&self, ingestion_job_id: &str) -> Result<IngestionJobDetails, Error>;
+
+    /// Pushes the document at `document_uri`, with its metadata read from the
+    /// `.metadata.json` sidecar next to it.
+    async fn ingest_document(&self, document_uri: &str) -> Result<(), Error>;
+
+    async fn document_state(&self, document_uri: &str) -> Result<DocumentState, Error>;
+}
+
+pub struct SqsIngestionQueue {
+    sqs_client: aws_sdk_sqs::Client,
+    queue_url: String,
+}
+
+impl SqsIngestionQueue {
+    pub fn new(sqs_client: aws_sdk_sqs::Client, queue_url: String) -> Self {
+        SqsIngestionQueue {
+            sqs_client,
+            queue_url,
+        }
+    }
+}
+
+#[async_trait]
+impl IngestionQueue for SqsIngestionQueue {
+    async fn enqueue(&self, request: &IngestionRequest) -> Result<(), Error> {
+        self.sqs_client
+            .send_message()
+            .queue_url(&self.queue_url)
+            .message_body(serde_json::to_string(request)?)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+}
+
+pub struct BedrockIngestion {
+    bedrock_agent_client: aws_sdk_bedrockagent::Client,
+    kb_id: String,
+    data_source_id: String,
+}
+
+impl BedrockIngestion {
+    pub fn new(
+        bedrock_agent_client: aws_sdk_bedrockagent::Client,
+        kb_id: String,
+        data_source_id: String,
+    ) -> Self {
+        BedrockIngestion {
+            bedrock_agent_client,
+            kb_id,
+            data_source_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Ingestion for BedrockIngestion {
+    async fn running_ingestion_jobs(&self) -> Result<usize, Error> {
+        let running_jobs = self
+            .bedrock_agent_client
+            .list_ingestion_jobs()
+            .knowledge_base_id(&self.kb_id)
+            .data_source_id(&self.data_source_id)
+            .filters(
+                IngestionJobFilter::builder()
+                    .attribute(IngestionJobFilterAttribute::Status)
+                    .operator(IngestionJobFilterOperator::Eq)
+                    .values("STARTING")
+                    .values("IN_PROGRESS")
+                    .values("STOPPING")
+                    .build()?,
+            )
+            .send()
+            .await
+            .map_err(ApiError::from)?
+            .ingestion_job_summaries;
+
+        Ok(running_jobs.len())
+    }
+
+    async fn start_ingestion_job(&self) -> Result<String, Error> {
+        let ingestion_job = self
+            .bedrock_agent_client
+            .start_ingestion_job()
+            .knowledge_base_id(&self.kb_id)
+            .data_source_id(&self.data_source_id)
+            .send()
+            .await
+            .map_err(ApiError::from)?
+            .ingestion_job
+            .ok_or_else(|| Error::from("Ingestion job error"))?;
+
+        Ok(ingestion_job.ingestion_job_id)
+    }
+
+    async fn get_ingestion_job(&self, ingestion_job_id: &str) -> Result<IngestionJobDetails, Error> {
+        let ingestion_job = self
+            .bedrock_agent_client
+            .get_ingestion_job()
+            .knowledge_base_id(&self.kb_id)
+            .data_source_id(&self.data_source_id)
+            .ingestion_job_id(ingestion_job_id)
+            .send()
+            .await
+            .map_err(ApiError::from)?
+            .ingestion_job
+            .ok_or_else(|| Error::from("Ingestion job error"))?;
+
+        let state = match ingestion_job.status() {
+            BedrockIngestionJobStatus::Complete => IngestionJobState::Complete,
+            BedrockIngestionJobStatus::Failed => IngestionJobState::Failed,
+            BedrockIngestionJobStatus::Stopped => IngestionJobState::Stopped,
+            _ => IngestionJobState::Running,
+        };
+
+        Ok(IngestionJobDetails {
+            state,
+            statistics: ingestion_job.statistics().map(to_statistics),
+            failure_reasons: ingestion_job.failure_reasons().to_vec(),
+        })
+    }
+
+    async fn ingest_document(&self, document_uri: &str) -> Result<(), Error> {
+        let document = KnowledgeBaseDocument::builder()
+            .content(
+                DocumentContent::builder()
+                    .data_source_type(ContentDataSourceType::S3)
+                    .s3(
+                        S3Content::builder()
+                            .s3_location(S3Location::builder().uri(document_uri).build()?)
+                            .build(),
+                    )
+                    .build()?,
+            )
+            .metadata(
+                DocumentMetadata::builder()
+                    .r#type(MetadataSourceType::S3Location)
+                    .s3_location(
+                        CustomS3Location::builder()
+                            .uri(format!("{}.metadata.json", document_uri))
+                            .build()?,
+                    )
+                    .build()?,
+            )
+            .build();
+
+        self.bedrock_agent_client
+            .ingest_knowledge_base_documents()
+            .knowledge_base_id(&self.kb_id)
+            .data_source_id(&self.data_source_id)
+            .documents(document)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+
+    async fn document_state(&self, document_uri: &str) -> Result<DocumentState, Error> {
+        let details = self
+            .bedrock_agent_client
+            .get_knowledge_base_documents()
+            .knowledge_base_id(&self.kb_id)
+            .data_source_id(&self.data_source_id)
+            .document_identifiers(
+                DocumentIdentifier::builder()
+                    .data_source_type(ContentDataSourceType::S3)
+                    .s3(S3Location::builder().uri(document_uri).build()?)
+                    .build()?,
+            )
+            .send()
+            .await
+            .map_err(ApiError::from)?
+            .document_details
+            .unwrap_or_default();
+
+        let detail = match details.first() {
+            Some(detail) => detail,
+            None => {
+                return Ok(DocumentState::Failed(
+                    "Document not found in knowledge base".to_string(),
+                ))
+            }
+        };
+
+        Ok(match detail.status() {
+            DocumentStatus::Indexed
+            | DocumentStatus::PartiallyIndexed
+            | DocumentStatus::MetadataPartiallyIndexed => DocumentState::Indexed,
+            DocumentStatus::Pending | DocumentStatus::Starting | DocumentStatus::InProgress => {
+                DocumentState::Pending
+            }
+            other => DocumentState::Failed(
+                detail
+                    .status_reason()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Document {}", other.as_str())),
+            ),
+        })
+    }
+}
+
+fn to_statistics(statistics: &IngestionJobStatistics) -> IngestionStatistics {
+    IngestionStatistics {
+        documents_scanned: statistics.number_of_documents_scanned(),
+        metadata_documents_scanned: statistics.number_of_metadata_documents_scanned(),
+        new_documents_indexed: statistics.number_of_new_documents_indexed(),
+        modified_documents_indexed: statistics.number_of_modified_documents_indexed(),
+        metadata_documents_modified: statistics.number_of_metadata_documents_modified(),
+        documents_deleted: statistics.number_of_documents_deleted(),
+        documents_failed: statistics.number_of_documents_failed(),
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryIngestionQueue {
+    requests: Mutex<Vec<IngestionRequest>>,
+}
+
+impl InMemoryIngestionQueue {
+    pub fn new() -> Self {
+        InMemoryIngestionQueue::default()
+    }
+
+    /// Removes and returns every request enqueued so far.
+    pub fn drain(&self) -> Vec<IngestionRequest> {
+        self.requests.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[async_trait]
+impl IngestionQueue for InMemoryIngestionQueue {
+    async fn enqueue(&self, request: &IngestionRequest) -> Result<(), Error> {
+        self.requests.lock().unwrap().push(request.clone());
+        Ok(())
+    }
+}
+
+/// Jobs and documents stay where they are until moved on with
+/// [`InMemoryIngestion::set_job`] and [`InMemoryIngestion::set_document_state`].
+#[derive(Default)]
+pub struct InMemoryIngestion {
+    jobs: Mutex<BTreeMap<String, IngestionJobDetails>>,
+    documents: Mutex<BTreeMap<String, DocumentState>>,
+}
+
+impl InMemoryIngestion {
+    pub fn new() -> Self {
+        InMemoryIngestion::default()
+    }
+
+    pub fn job_ids(&self) -> Vec<String> {
+        self.jobs.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn set_job(&self, ingestion_job_id: &str, details: IngestionJobDetails) {
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(ingestion_job_id.to_string(), details);
+    }
+
+    pub fn document_uris(&self) -> Vec<String> {
+        self.documents.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn set_document_state(&self, document_uri: &str, state: DocumentState) {
+        self.documents
+            .lock()
+            .unwrap()
+            .insert(document_uri.to_string(), state);
+    }
+}
+
+#[async_trait]
+impl Ingestion for InMemoryIngestion {
+    async fn running_ingestion_jobs(&self) -> Result<usize, Error> {
+        let jobs = self.jobs.lock().unwrap();
+        Ok(jobs
+            .values()
+            .filter(|job| job.state == IngestionJobState::Running)
+            .count())
+    }
+
+    async fn start_ingestion_job(&self) -> Result<String, Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let ingestion_job_id = format!("job-{}", jobs.len() + 1);
+
+        jobs.insert(
+            ingestion_job_id.clone(),
+            IngestionJobDetails {
+                state: IngestionJobState::Running,
+                statistics: None,
+                failure_reasons: Vec::new(),
+            },
+        );
+        Ok(ingestion_job_id)
+    }
+
+    async fn get_ingestion_job(&self, ingestion_job_id: &str) -> Result<IngestionJobDetails, Error> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(ingestion_job_id)
+            .cloned()
+            .ok_or_else(|| Error::from(format!("Ingestion job {} not found", ingestion_job_id)))
+    }
+
+    async fn ingest_document(&self, document_uri: &str) -> Result<(), Error> {
+        self.set_document_state(document_uri, DocumentState::Pending);
+        Ok(())
+    }
+
+    async fn document_state(&self, document_uri: &str) -> Result<DocumentState, Error> {
+        Ok(self
+            .documents
+            .lock()
+            .unwrap()
+            .get(document_uri)
+            .cloned()
+            .unwrap_or_else(|| {
+                DocumentState::Failed("Document not found in knowledge base".to_string())
+            }))
+    }
+}
@@ -1 +1,18 @@
+pub mod analytics;
+pub mod auth;
+pub mod cache;
+pub mod cleaning;
+pub mod error;
+pub mod events;
+pub mod federation;
+pub mod generation;
+pub mod ingestion;
 pub mod models;
+pub mod retrieval;
+pub mod storage;
+pub mod task;
+pub mod transcription;
+pub mod validation;
+pub mod webhook;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -1,25 +1,324 @@
-use chrono::NaiveDate;
-use serde::Deserialize;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use serde_valid::Validate;
 use serde_valid::validation::Error;
+use url::{Host, Url};
 
-#[derive(Default, Debug, Clone, PartialEq, Deserialize, Validate)]
+use crate::auth::TENANT_ID_ATTRIBUTE;
+use crate::cleaning::CleaningOptions;
+use crate::validation::{field_rule_error, rule_error};
+use crate::webhook::WebhookCallback;
+
+pub const MEDIA_UPLOAD_PREFIX: &str = "media-uploads/";
+
+pub const STAGING_METADATA_PREFIX: &str = "media-metadata/";
+
+/// Audio tracks extracted from the uploads, what Transcribe is started on.
+pub const NORMALIZED_MEDIA_PREFIX: &str = "media-normalized/";
+
+/// The knowledge base documents generated for a task, told apart by their `doc_type` metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocType {
+    Transcript,
+    Summary,
+    Chapters,
+    /// Timestamped descriptions of the on-screen content of a video.
+    Visuals,
+}
+
+impl DocType {
+    pub const ALL: [DocType; 4] = [
+        DocType::Transcript,
+        DocType::Summary,
+        DocType::Chapters,
+        DocType::Visuals,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocType::Transcript => "transcript",
+            DocType::Summary => "summary",
+            DocType::Chapters => "chapters",
+            DocType::Visuals => "visuals",
+        }
+    }
+
+    /// Key of the document in the knowledge base bucket. Every document stays under
+    /// `transcripts/`, the only prefix the data source includes, in a folder per tenant.
+    pub fn kb_key(&self, tenant_id: &str, task_id: &str) -> String {
+        match self {
+            DocType::Transcript => format!("{}/{}/{}", "transcripts", tenant_id, task_id),
+            doc_type => format!(
+                "{}/{}/{}.{}",
+                "transcripts",
+                tenant_id,
+                task_id,
+                doc_type.as_str()
+            ),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
+#[validate(custom = |m| validate_callback(&m.callback_url, &m.callback_secret))]
 pub struct MediaMetadata {
     #[validate(min_length = 5)]
     pub topic: String,
     #[validate(min_length = 5)]
     pub source_url: String,
-    #[validate(custom = | v | validate_date_format(v))]
+    #[validate(custom = validate_date_format)]
     pub date: String,
+    /// Only accepted on upload: the callback is kept apart from the task metadata, see
+    /// [`MediaMetadata::webhook`].
+    #[serde(default, skip_serializing)]
+    pub callback_url: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub callback_secret: Option<String>,
+    /// Overrides the deployment's transcript cleaning for this upload.
+    #[validate]
+    #[serde(rename = "cleaning", default, skip_serializing_if = "Option::is_none")]
+    pub cleaning: Option<CleaningOptions>,
+    /// Subject of the user who uploaded the media, always taken from the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// Technical metadata of the media, known once Transcribe processed it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDetails {
+    pub duration_seconds: Option<f64>,
+    pub language_code: Option<String>,
+    /// Score of the identified language, between 0 and 1.
+    #[serde(default)]
+    pub language_confidence: Option<f64>,
+    pub speaker_count: Option<i64>,
+    #[serde(default)]
+    pub word_count: Option<i64>,
+    #[serde(default)]
+    pub transcription: Option<TranscriptionTiming>,
 }
 
-fn validate_date_format(date_str: &str) -> Result<(), serde_valid::validation::Error> {
+/// When the Transcribe job was queued, started and finished.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionTiming {
+    pub created_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl MediaMetadata {
+    /// Builds the `.metadata.json` sidecar document Bedrock reads next to each document. Details
+    /// that are not known are left out so filters on them skip the document, the tenant is
+    /// always set since every query filters on it.
+    pub fn to_kb_metadata(
+        &self,
+        tenant_id: &str,
+        task_id: &str,
+        doc_type: DocType,
+        details: &MediaDetails,
+    ) -> Value {
+        let mut metadata = json!({
+            "metadataAttributes" : {
+                TENANT_ID_ATTRIBUTE: tenant_id,
+                "topic" : self.topic,
+                "source_url": self.source_url,
+                "task_id": task_id,
+                "doc_type": doc_type.as_str()
+            }
+        });
+
+        let attributes = [
+            ("language_code", details.language_code.as_ref().map(|l| json!(l))),
+            ("duration_seconds", details.duration_seconds.map(|d| json!(d))),
+            ("speaker_count", details.speaker_count.map(|s| json!(s))),
+            ("word_count", details.word_count.map(|w| json!(w))),
+        ];
+
+        for (key, value) in attributes {
+            if let Some(value) = value {
+                metadata["metadataAttributes"][key] = value;
+            }
+        }
+
+        metadata
+    }
+
+    pub fn webhook(&self) -> Option<WebhookCallback> {
+        match (&self.callback_url, &self.callback_secret) {
+            (Some(url), Some(secret)) => Some(WebhookCallback {
+                url: url.clone(),
+                secret: secret.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&mut self, patch: MediaMetadataPatch) {
+        if let Some(topic) = patch.topic {
+            self.topic = topic;
+        }
+        if let Some(source_url) = patch.source_url {
+            self.source_url = source_url;
+        }
+        if let Some(date) = patch.date {
+            self.date = date;
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MediaMetadataPatch {
+    pub topic: Option<String>,
+    pub source_url: Option<String>,
+    pub date: Option<String>,
+}
+
+/// Position in the media as `mm:ss`, or `h:mm:ss` past the first hour.
+pub fn media_timestamp(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+
+    match seconds / 3600 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds % 3600 / 60, seconds % 60),
+    }
+}
+
+/// Metadata submitted with the upload link, kept until the transcript is ready.
+pub fn staging_metadata_key(tenant_id: &str, task_id: &str) -> String {
+    format!("{}{}/{}", STAGING_METADATA_PREFIX, tenant_id, task_id)
+}
+
+/// Where the media of a task is uploaded to.
+pub fn media_upload_key(tenant_id: &str, task_id: &str) -> String {
+    format!("{}{}/{}", MEDIA_UPLOAD_PREFIX, tenant_id, task_id)
+}
+
+pub fn normalized_media_key(tenant_id: &str, task_id: &str) -> String {
+    format!("{}{}/{}", NORMALIZED_MEDIA_PREFIX, tenant_id, task_id)
+}
+
+/// Checked on the struct since the two fields go together, errors are still reported under
+/// the field at fault.
+fn validate_callback(
+    callback_url: &Option<String>,
+    callback_secret: &Option<String>,
+) -> Result<(), Error> {
+    match (callback_url, callback_secret) {
+        (None, None) => Ok(()),
+        (Some(url), Some(secret)) => {
+            if !is_public_https_url(url) {
+                return Err(field_rule_error(
+                    "callbackUrl",
+                    "https_url",
+                    format!(
+                        "Invalid callback url {}. Expected an https url of a public host.",
+                        url
+                    ),
+                ));
+            }
+            if secret.len() < 16 {
+                return Err(field_rule_error(
+                    "callbackSecret",
+                    "min_length",
+                    "Callback secret must be at least 16 characters long.",
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(field_rule_error(
+            "callbackSecret",
+            "required_together",
+            "callbackUrl and callbackSecret must be provided together.",
+        )),
+    }
+}
+
+/// The webhook lambda posts callbacks from AWS's network, where the instance metadata
+/// endpoint, loopback and other internal hosts are reachable. IP literals are refused
+/// outright, which covers loopback, link-local and private addresses, and so are
+/// single-label names such as `localhost`. Names resolving to such addresses are refused
+/// when the callback is sent.
+fn is_public_https_url(url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(url) if url.scheme() == "https" => url,
+        _ => return false,
+    };
+
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain.contains('.') && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) | None => false,
+    }
+}
+
+fn validate_date_format(date_str: &str) -> Result<(), Error> {
     match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
         Ok(_) => Ok(()),
-        Err(_) => Err(Error::Custom(format!(
-            "Invalid date format {}. Expected format is yyyy-MM-dd.",
-            date_str
-        ))),
+        Err(_) => Err(rule_error(
+            "date_format",
+            format!(
+                "Invalid date format {}. Expected format is yyyy-MM-dd.",
+                date_str
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callback(url: &str) -> Result<(), Error> {
+        validate_callback(
+            &Some(url.to_string()),
+            &Some("0123456789abcdef".to_string()),
+        )
+    }
+
+    #[test]
+    fn accepts_https_callbacks_to_public_hosts() {
+        assert!(callback("https://example.com/hooks/media").is_ok());
+        assert!(callback("https://hooks.example.com:8443/media?team=1").is_ok());
+        assert!(validate_callback(&None, &None).is_ok());
+    }
+
+    #[test]
+    fn rejects_callbacks_to_internal_hosts() {
+        for url in [
+            "http://example.com/hooks/media",
+            "https://127.0.0.1/hooks",
+            "https://2130706433/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.5/hooks",
+            "https://192.168.1.1/hooks",
+            "https://[::1]/hooks",
+            "https://[fd00::1]/hooks",
+            "https://localhost/hooks",
+            "https://LOCALHOST./hooks",
+            "https://api.localhost/hooks",
+            "https://metadata/hooks",
+            "not a url",
+        ] {
+            assert!(callback(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn requires_url_and_secret_together() {
+        assert!(validate_callback(&Some("https://example.com".to_string()), &None).is_err());
+        assert!(callback("https://example.com").is_ok());
+        assert!(validate_callback(
+            &Some("https://example.com".to_string()),
+            &Some("short".to_string())
+        )
+            .is_err());
     }
 }
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use aws_sdk_bedrockagentruntime::types::{
+    FilterAttribute, GenerationConfiguration, KnowledgeBaseQuery,
+    KnowledgeBaseRetrievalConfiguration, KnowledgeBaseRetrieveAndGenerateConfiguration,
+    KnowledgeBaseVectorSearchConfiguration, PromptTemplate, RetrievalFilter,
+    RetrievalResultContent, RetrievalResultLocation, RetrieveAndGenerateConfiguration,
+    RetrieveAndGenerateInput, RetrieveAndGenerateType,
+};
+use aws_smithy_types::Document;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::error::ApiError;
+use crate::Error;
+
+/// Restricts retrieval to documents whose metadata attribute `key` equals `value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeFilter {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievalQuery {
+    pub input: String,
+    /// Every filter must match.
+    pub filters: Vec<AttributeFilter>,
+}
+
+/// A knowledge base chunk the answer was generated from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub uri: Option<String>,
+    pub text: Option<String>,
+    pub metadata: Map<String, Value>,
+}
+
+/// Model tokens spent on an answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// `RetrieveAndGenerate` does not report the model usage, unlike `Converse`, so answers
+    /// generated by it are charged an estimate from the question, the retrieved passages and
+    /// the answer at about four characters per token.
+    pub fn estimate(input: &str, references: &[Reference], output: &str) -> Self {
+        let passages: usize = references
+            .iter()
+            .filter_map(|reference| reference.text.as_deref())
+            .map(str::len)
+            .sum();
+
+        TokenUsage {
+            input_tokens: estimate_tokens(input.len() + passages),
+            output_tokens: estimate_tokens(output.len()),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+fn estimate_tokens(characters: usize) -> u64 {
+    characters.div_ceil(4) as u64
+}
+
+/// A knowledge base chunk matching a query, the higher the score the closer the match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    pub reference: Reference,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedAnswer {
+    pub text: String,
+    pub references: Vec<Reference>,
+    pub usage: TokenUsage,
+}
+
+/// The retrieval augmented generation operations the handlers rely on.
+#[async_trait]
+pub trait Retrieval: Send + Sync {
+    /// The model generating the answers, answers of another model are not reused.
+    fn model(&self) -> &str;
+
+    /// Returns `None` when no answer was generated.
+    async fn retrieve_and_generate(
+        &self,
+        query: &RetrievalQuery,
+    ) -> Result<Option<GeneratedAnswer>, Error>;
+
+    /// Returns the passages matching the query without generating an answer.
+    async fn retrieve(&self, query: &RetrievalQuery) -> Result<Vec<Passage>, Error>;
+}
+
+/// How passages are retrieved and the answer prompted, Bedrock defaults when unset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievalSettings {
+    /// Passages retrieved per query.
+    pub number_of_results: Option<i32>,
+    /// Generation prompt, with the `$search_results$` placeholder for the passages.
+    pub prompt_template: Option<String>,
+}
+
+pub struct BedrockRetrieval {
+    bedrock_agent_runtime_client: aws_sdk_bedrockagentruntime::Client,
+    knowledge_base_id: String,
+    model_arn: String,
+    settings: RetrievalSettings,
+}
+
+impl BedrockRetrieval {
+    pub fn new(
+        bedrock_agent_runtime_client: aws_sdk_bedrockagentruntime::Client,
+        knowledge_base_id: String,
+        model_arn: String,
+        settings: RetrievalSettings,
+    ) -> Self {
+        BedrockRetrieval {
+            bedrock_agent_runtime_client,
+            knowledge_base_id,
+            model_arn,
+            settings,
+        }
+    }
+
+    fn build_retrieval_configuration(
+        &self,
+        query: &RetrievalQuery,
+    ) -> Result<KnowledgeBaseRetrievalConfiguration, Error> {
+        let mut vector_search_config = KnowledgeBaseVectorSearchConfiguration::builder();
+
+        if let Some(filter) = build_filter(&query.filters)? {
+            vector_search_config = vector_search_config.filter(filter);
+        }
+
+        if let Some(number_of_results) = self.settings.number_of_results {
+            vector_search_config = vector_search_config.number_of_results(number_of_results);
+        }
+
+        Ok(KnowledgeBaseRetrievalConfiguration::builder()
+            .vector_search_configuration(vector_search_config.build())
+            .build())
+    }
+
+    fn build_configuration(
+        &self,
+        query: &RetrievalQuery,
+    ) -> Result<RetrieveAndGenerateConfiguration, Error> {
+        let mut rng_config = KnowledgeBaseRetrieveAndGenerateConfiguration::builder()
+            .retrieval_configuration(self.build_retrieval_configuration(query)?)
+            .knowledge_base_id(&self.knowledge_base_id)
+            .model_arn(&self.model_arn);
+
+        if let Some(prompt_template) = &self.settings.prompt_template {
+            rng_config = rng_config.generation_configuration(
+                GenerationConfiguration::builder()
+                    .prompt_template(
+                        PromptTemplate::builder()
+                            .text_prompt_template(prompt_template)
+                            .build(),
+                    )
+                    .build(),
+            );
+        }
+
+        let rng_config = rng_config
+            .build()
+            .map_err(Box::new)?;
+
+        let configuration = RetrieveAndGenerateConfiguration::builder()
+            .r#type(RetrieveAndGenerateType::KnowledgeBase)
+            .knowledge_base_configuration(rng_config)
+            .build()
+            .map_err(Box::new)?;
+
+        Ok(configuration)
+    }
+}
+
+#[async_trait]
+impl Retrieval for BedrockRetrieval {
+    fn model(&self) -> &str {
+        &self.model_arn
+    }
+
+    async fn retrieve_and_generate(
+        &self,
+        query: &RetrievalQuery,
+    ) -> Result<Option<GeneratedAnswer>, Error> {
+        let result = self
+            .bedrock_agent_runtime_client
+            .retrieve_and_generate()
+            .retrieve_and_generate_configuration(self.build_configuration(query)?)
+            .input(RetrieveAndGenerateInput::builder().text(&query.input).build()?)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+
+        let output = match result.output {
+            Some(output) => output,
+            None => return Ok(None),
+        };
+
+        let references = result
+            .citations
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|citation| citation.retrieved_references.unwrap_or_default())
+            .map(|reference| {
+                to_reference(reference.content, reference.location, reference.metadata)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Some(GeneratedAnswer {
+            usage: TokenUsage::estimate(&query.input, &references, &output.text),
+            text: output.text,
+            references,
+        }))
+    }
+
+    async fn retrieve(&self, query: &RetrievalQuery) -> Result<Vec<Passage>, Error> {
+        let result = self
+            .bedrock_agent_runtime_client
+            .retrieve()
+            .knowledge_base_id(&self.knowledge_base_id)
+            .retrieval_query(KnowledgeBaseQuery::builder().text(&query.input).build())
+            .retrieval_configuration(self.build_retrieval_configuration(query)?)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(result
+            .retrieval_results
+            .into_iter()
+            .map(|result| Passage {
+                reference: to_reference(result.content, result.location, result.metadata),
+                score: result.score.unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+fn to_reference(
+    content: Option<RetrievalResultContent>,
+    location: Option<RetrievalResultLocation>,
+    metadata: Option<HashMap<String, Document>>,
+) -> Reference {
+    Reference {
+        uri: location
+            .and_then(|location| location.s3_location)
+            .and_then(|s3_location| s3_location.uri),
+        text: content.map(|content| content.text),
+        metadata: metadata
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, to_json(value)))
+            .collect(),
+    }
+}
+
+fn build_filter(filters: &[AttributeFilter]) -> Result<Option<RetrievalFilter>, Error> {
+    let mut equals = filters
+        .iter()
+        .map(|filter| {
+            Ok(RetrievalFilter::Equals(
+                FilterAttribute::builder()
+                    .key(&filter.key)
+                    .value(filter.value.clone().into())
+                    .build()?,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(match equals.len() {
+        0 => None,
+        1 => equals.pop(),
+        _ => Some(RetrievalFilter::AndAll(equals)),
+    })
+}
+
+fn to_json(document: Document) -> Value {
+    match document {
+        Document::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, to_json(value)))
+                .collect(),
+        ),
+        Document::Array(array) => Value::Array(array.into_iter().map(to_json).collect()),
+        Document::Number(number) => Number::from_f64(number.to_f64_lossy())
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        Document::String(string) => Value::String(string),
+        Document::Bool(bool) => Value::Bool(bool),
+        Document::Null => Value::Null,
+    }
+}
+
+/// Answers with the stored documents matching every filter, quoting them instead of
+/// generating text. No matching document means no answer.
+#[derive(Default)]
+pub struct InMemoryRetrieval {
+    documents: Vec<Reference>,
+    queries: Mutex<Vec<RetrievalQuery>>,
+}
+
+impl InMemoryRetrieval {
+    pub fn new(documents: Vec<Reference>) -> Self {
+        InMemoryRetrieval {
+            documents,
+            queries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn queries(&self) -> Vec<RetrievalQuery> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    fn matching<'a>(&'a self, query: &'a RetrievalQuery) -> impl Iterator<Item = &'a Reference> {
+        self.documents.iter().filter(|document| {
+            query.filters.iter().all(|filter| {
+                document.metadata.get(&filter.key).and_then(Value::as_str)
+                    == Some(filter.value.as_str())
+            })
+        })
+    }
+}
+
+#[async_trait]
+impl Retrieval for InMemoryRetrieval {
+    fn model(&self) -> &str {
+        "in-memory"
+    }
+
+    async fn retrieve_and_generate(
+        &self,
+        query: &RetrievalQuery,
+    ) -> Result<Option<GeneratedAnswer>, Error> {
+        self.queries.lock().unwrap().push(query.clone());
+
+        let references: Vec<Reference> = self.matching(query).cloned().collect();
+
+        if references.is_empty() {
+            return Ok(None);
+        }
+
+        let text = references
+            .iter()
+            .filter_map(|reference| reference.text.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(Some(GeneratedAnswer {
+            usage: TokenUsage::estimate(&query.input, &references, &text),
+            text,
+            references,
+        }))
+    }
+
+    /// Scores the matching documents by the share of the question's words they contain.
+    async fn retrieve(&self, query: &RetrievalQuery) -> Result<Vec<Passage>, Error> {
+        self.queries.lock().unwrap().push(query.clone());
+
+        let words: Vec<String> = query
+            .input
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        Ok(self
+            .matching(query)
+            .map(|document| {
+                let text = document.text.as_deref().unwrap_or_default().to_lowercase();
+                let found = words.iter().filter(|word| text.contains(word.as_str())).count();
+
+                Passage {
+                    reference: document.clone(),
+                    score: found as f64 / words.len().max(1) as f64,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,580 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::ApiError;
+use crate::Error;
+
+/// One page of keys, in key order.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct KeyPage {
+    pub keys: Vec<String>,
+    pub is_truncated: bool,
+}
+
+/// The object storage operations the handlers rely on.
+#[async_trait]
+pub trait ObjectStorage: Send + Sync {
+    /// Returns `None` when there is no object under `key`.
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), Error>;
+
+    /// The object and its ETag, for a later [`ObjectStorage::put_object_if`].
+    async fn get_object_versioned(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(Vec<u8>, String)>, Error>;
+
+    /// Writes the object only while it still has the ETag `if_match`, or while there is none
+    /// when `if_match` is `None`. Returns `false` when another write came first.
+    async fn put_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        if_match: Option<&str>,
+    ) -> Result<bool, Error>;
+
+    /// Streams the object to the file at `path` without holding it in memory. Returns `false`
+    /// when there is no object under `key`.
+    async fn download(&self, bucket: &str, key: &str, path: &Path) -> Result<bool, Error>;
+
+    /// Streams the file at `path` to the object.
+    async fn upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        content_type: &str,
+    ) -> Result<(), Error>;
+
+    async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool, Error>;
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), Error>;
+
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<KeyPage, Error>;
+
+    /// URL a client can PUT the object to without credentials.
+    async fn presign_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        task_id: &str,
+        expires_in: Duration,
+    ) -> Result<String, Error>;
+}
+
+pub async fn get_json<T: DeserializeOwned>(
+    storage: &dyn ObjectStorage,
+    bucket: &str,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    match storage.get_object(bucket, key).await? {
+        Some(body) => Ok(Some(serde_json::from_slice(&body)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn put_json<T: Serialize + ?Sized>(
+    storage: &dyn ObjectStorage,
+    bucket: &str,
+    key: &str,
+    value: &T,
+) -> Result<(), Error> {
+    storage
+        .put_object(bucket, key, serde_json::to_vec(value)?, "application/json")
+        .await
+}
+
+/// The value and the ETag to update it with [`put_json_if`].
+pub async fn get_json_versioned<T: DeserializeOwned>(
+    storage: &dyn ObjectStorage,
+    bucket: &str,
+    key: &str,
+) -> Result<Option<(T, String)>, Error> {
+    match storage.get_object_versioned(bucket, key).await? {
+        Some((body, etag)) => Ok(Some((serde_json::from_slice(&body)?, etag))),
+        None => Ok(None),
+    }
+}
+
+pub async fn put_json_if<T: Serialize + ?Sized>(
+    storage: &dyn ObjectStorage,
+    bucket: &str,
+    key: &str,
+    value: &T,
+    if_match: Option<&str>,
+) -> Result<bool, Error> {
+    storage
+        .put_object_if(
+            bucket,
+            key,
+            serde_json::to_vec(value)?,
+            "application/json",
+            if_match,
+        )
+        .await
+}
+
+/// Every key under `prefix`, following pagination.
+pub async fn list_all_keys(
+    storage: &dyn ObjectStorage,
+    bucket: &str,
+    prefix: &str,
+) -> Result<Vec<String>, Error> {
+    let mut keys: Vec<String> = Vec::new();
+
+    loop {
+        let page = storage
+            .list_keys(bucket, prefix, keys.last().map(String::as_str), 1000)
+            .await?;
+        keys.extend(page.keys);
+
+        if !page.is_truncated {
+            return Ok(keys);
+        }
+    }
+}
+
+pub struct S3Storage {
+    s3_client: aws_sdk_s3::Client,
+}
+
+impl S3Storage {
+    pub fn new(s3_client: aws_sdk_s3::Client) -> Self {
+        S3Storage { s3_client }
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for S3Storage {
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let object = match self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(err) => {
+                if err.as_service_error().is_some_and(|err| err.is_no_such_key()) {
+                    return Ok(None);
+                }
+                return Err(Box::new(ApiError::from(err)));
+            }
+        };
+
+        let data = object.body.collect().await?;
+
+        Ok(Some(data.into_bytes().to_vec()))
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), Error> {
+        self.s3_client
+            .put_object()
+            .bucket(bucket)
+            .content_type(content_type)
+            .key(key)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+
+    async fn get_object_versioned(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(Vec<u8>, String)>, Error> {
+        let object = match self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(err) => {
+                if err.as_service_error().is_some_and(|err| err.is_no_such_key()) {
+                    return Ok(None);
+                }
+                return Err(Box::new(ApiError::from(err)));
+            }
+        };
+
+        let etag = object
+            .e_tag
+            .clone()
+            .ok_or_else(|| Error::from(format!("No ETag for object {}", key)))?;
+        let data = object.body.collect().await?;
+
+        Ok(Some((data.into_bytes().to_vec(), etag)))
+    }
+
+    async fn put_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        if_match: Option<&str>,
+    ) -> Result<bool, Error> {
+        let request = self
+            .s3_client
+            .put_object()
+            .bucket(bucket)
+            .content_type(content_type)
+            .key(key)
+            .body(ByteStream::from(body));
+
+        let request = match if_match {
+            Some(etag) => request.if_match(etag),
+            None => request.if_none_match("*"),
+        };
+
+        match request.send().await {
+            Ok(_) => Ok(true),
+            // 412 when the ETag changed, 409 when a concurrent conditional write is in flight.
+            Err(err) => match err.raw_response().map(|response| response.status().as_u16()) {
+                Some(409 | 412) => Ok(false),
+                _ => Err(Box::new(ApiError::from(err))),
+            },
+        }
+    }
+
+    async fn download(&self, bucket: &str, key: &str, path: &Path) -> Result<bool, Error> {
+        let object = match self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(err) => {
+                if err.as_service_error().is_some_and(|err| err.is_no_such_key()) {
+                    return Ok(false);
+                }
+                return Err(Box::new(ApiError::from(err)));
+            }
+        };
+
+        let mut file = tokio::fs::File::create(path).await?;
+        tokio::io::copy(&mut object.body.into_async_read(), &mut file).await?;
+        file.flush().await?;
+
+        Ok(true)
+    }
+
+    async fn upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        content_type: &str,
+    ) -> Result<(), Error> {
+        self.s3_client
+            .put_object()
+            .bucket(bucket)
+            .content_type(content_type)
+            .key(key)
+            .body(ByteStream::from_path(path).await?)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+
+    async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool, Error> {
+        match self
+            .s3_client
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                if err.as_service_error().is_some_and(|err| err.is_not_found()) {
+                    return Ok(false);
+                }
+                Err(Box::new(ApiError::from(err)))
+            }
+        }
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), Error> {
+        self.s3_client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<KeyPage, Error> {
+        let page = self
+            .s3_client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_start_after(start_after.map(str::to_string))
+            .max_keys(max_keys as i32)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(KeyPage {
+            keys: page
+                .contents
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|object| object.key)
+                .collect(),
+            is_truncated: page.is_truncated.unwrap_or_default(),
+        })
+    }
+
+    async fn presign_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        task_id: &str,
+        expires_in: Duration,
+    ) -> Result<String, Error> {
+        let presigned_request = self
+            .s3_client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .metadata("task_id", task_id)
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+
+        Ok(presigned_request.uri().to_string())
+    }
+}
+
+/// Objects kept in memory, keyed by bucket and key.
+#[derive(Default)]
+pub struct InMemoryStorage {
+    objects: Mutex<BTreeMap<(String, String), Vec<u8>>>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        InMemoryStorage::default()
+    }
+
+    pub fn keys(&self, bucket: &str) -> Vec<String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(b, _)| b == bucket)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for InMemoryStorage {
+    async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(&(bucket.to_string(), key.to_string())).cloned())
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        _content_type: &str,
+    ) -> Result<(), Error> {
+        let mut objects = self.objects.lock().unwrap();
+        objects.insert((bucket.to_string(), key.to_string()), body);
+        Ok(())
+    }
+
+    /// ETags are digests of the content here.
+    async fn get_object_versioned(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(Vec<u8>, String)>, Error> {
+        Ok(self
+            .get_object(bucket, key)
+            .await?
+            .map(|body| {
+                let etag = hex::encode(Sha256::digest(&body));
+                (body, etag)
+            }))
+    }
+
+    async fn put_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        _content_type: &str,
+        if_match: Option<&str>,
+    ) -> Result<bool, Error> {
+        let mut objects = self.objects.lock().unwrap();
+        let object_key = (bucket.to_string(), key.to_string());
+
+        let etag = objects
+            .get(&object_key)
+            .map(|body| hex::encode(Sha256::digest(body)));
+        if etag.as_deref() != if_match {
+            return Ok(false);
+        }
+
+        objects.insert(object_key, body);
+        Ok(true)
+    }
+
+    async fn download(&self, bucket: &str, key: &str, path: &Path) -> Result<bool, Error> {
+        match self.get_object(bucket, key).await? {
+            Some(body) => {
+                tokio::fs::write(path, body).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        content_type: &str,
+    ) -> Result<(), Error> {
+        let body = tokio::fs::read(path).await?;
+        self.put_object(bucket, key, body, content_type).await
+    }
+
+    async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool, Error> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.contains_key(&(bucket.to_string(), key.to_string())))
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), Error> {
+        let mut objects = self.objects.lock().unwrap();
+        objects.remove(&(bucket.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<KeyPage, Error> {
+        let objects = self.objects.lock().unwrap();
+
+        let start = match start_after {
+            Some(start_after) => Bound::Excluded((bucket.to_string(), start_after.to_string())),
+            None => Bound::Included((bucket.to_string(), String::new())),
+        };
+
+        let mut keys: Vec<String> = objects
+            .range((start, Bound::Unbounded))
+            .take_while(|((b, _), _)| b == bucket)
+            .map(|((_, key), _)| key)
+            .filter(|key| key.starts_with(prefix))
+            .take(max_keys + 1)
+            .cloned()
+            .collect();
+
+        let is_truncated = keys.len() > max_keys;
+        keys.truncate(max_keys);
+
+        Ok(KeyPage { keys, is_truncated })
+    }
+
+    async fn presign_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        _task_id: &str,
+        _expires_in: Duration,
+    ) -> Result<String, Error> {
+        Ok(format!("memory://{}/{}", bucket, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_conditionally_on_the_etag_read() {
+        let storage = InMemoryStorage::new();
+
+        assert!(put_json_if(&storage, "media", "state.json", &1, None).await.unwrap());
+        assert!(!put_json_if(&storage, "media", "state.json", &2, None).await.unwrap());
+
+        let (value, etag) = get_json_versioned::<u32>(&storage, "media", "state.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, 1);
+
+        put_json(&storage, "media", "state.json", &3).await.unwrap();
+        assert!(!put_json_if(&storage, "media", "state.json", &2, Some(&etag)).await.unwrap());
+
+        let (_, etag) = get_json_versioned::<u32>(&storage, "media", "state.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(put_json_if(&storage, "media", "state.json", &4, Some(&etag)).await.unwrap());
+        assert_eq!(get_json::<u32>(&storage, "media", "state.json").await.unwrap(), Some(4));
+    }
+}
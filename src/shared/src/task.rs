@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use lambda_http::tracing::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventPublisher, TaskStatusChanged};
+use crate::models::{staging_metadata_key, MediaDetails, MediaMetadata};
+use crate::storage::{get_json, put_json, ObjectStorage};
+use crate::Error;
+
+pub const TASK_INDEX_PREFIX: &str = "media-index/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Transcribed,
+    Indexed,
+    Failed,
+}
+
+/// Entry of the media library index, one JSON object per task under
+/// `media-index/{tenant_id}/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRecord {
+    pub task_id: String,
+    /// Empty for records written before tenants were introduced, no caller can see those.
+    #[serde(default)]
+    pub tenant_id: String,
+    pub metadata: MediaMetadata,
+    pub status: TaskStatus,
+    #[serde(flatten)]
+    pub details: MediaDetails,
+    /// Generated with the transcript summary, empty when enrichment is disabled or failed.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// Topics the media was moved away from, their cached answers may still cite it until
+    /// the documents are indexed again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stale_topics: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Prefix of the index entries of a tenant, so listing never reads those of other tenants.
+pub fn task_index_prefix(tenant_id: &str) -> String {
+    format!("{}{}/", TASK_INDEX_PREFIX, tenant_id)
+}
+
+pub fn task_record_key(tenant_id: &str, task_id: &str) -> String {
+    format!("{}{}.json", task_index_prefix(tenant_id), task_id)
+}
+
+pub fn task_id_from_record_key<'a>(tenant_id: &str, key: &'a str) -> Option<&'a str> {
+    key.strip_prefix(&task_index_prefix(tenant_id))?
+        .strip_suffix(".json")
+}
+
+/// Marks the task as failed in the media index and notifies subscribers. Without staging
+/// metadata there is no record to write, the event is still published.
+pub async fn record_failure(
+    storage: &dyn ObjectStorage,
+    event_publisher: &dyn EventPublisher,
+    media_bucket_name: &str,
+    tenant_id: &str,
+    task_id: &str,
+    failure_reason: &str,
+) -> Result<(), Error> {
+    let staging_key = staging_metadata_key(tenant_id, task_id);
+
+    match get_json::<MediaMetadata>(storage, media_bucket_name, &staging_key).await? {
+        Some(metadata) => {
+            let task_record = TaskRecord {
+                task_id: task_id.to_string(),
+                tenant_id: tenant_id.to_string(),
+                metadata,
+                status: TaskStatus::Failed,
+                details: MediaDetails::default(),
+                keywords: Vec::new(),
+                failure_reason: Some(failure_reason.to_string()),
+                stale_topics: Vec::new(),
+                updated_at: Utc::now(),
+            };
+
+            put_json(
+                storage,
+                media_bucket_name,
+                &task_record_key(tenant_id, task_id),
+                &task_record,
+            )
+                .await?;
+        }
+        None => warn!(task_id, "no staging metadata to record the failure with"),
+    }
+
+    event_publisher
+        .task_status_changed(&TaskStatusChanged {
+            task_id: task_id.to_string(),
+            status: TaskStatus::Failed,
+            transcript_location: None,
+            failure_reason: Some(failure_reason.to_string()),
+        })
+        .await
+}
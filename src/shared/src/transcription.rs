@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use aws_sdk_transcribe::primitives::DateTime as AwsDateTime;
+use aws_sdk_transcribe::types::{Media, Settings, Tag};
+use chrono::{DateTime, Utc};
+
+use crate::error::ApiError;
+use crate::Error;
+
+pub const TRANSCRIBE_OUTPUT_PREFIX: &str = "transcribe-output/";
+
+/// Where a job's raw Transcribe output lands in the output bucket.
+pub fn transcribe_output_key(job_name: &str) -> String {
+    format!("{}{}.json", TRANSCRIBE_OUTPUT_PREFIX, job_name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptionJobStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptionJob {
+    pub job_name: String,
+    pub media_uri: Option<String>,
+    pub status: TranscriptionJobStatus,
+    pub language_code: Option<String>,
+    /// Set when Transcribe identified the language of the media.
+    pub identified_language_score: Option<f64>,
+    pub failure_reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// The speech to text operations the handlers rely on.
+#[async_trait]
+pub trait Transcription: Send + Sync {
+    /// Starts a job named after the task, with speaker labels and language identification.
+    /// The transcript is written to [`transcribe_output_key`] in `output_bucket`.
+    async fn start_transcription_job(
+        &self,
+        job_name: &str,
+        media_uri: &str,
+        output_bucket: &str,
+    ) -> Result<(), Error>;
+
+    async fn get_transcription_job(&self, job_name: &str) -> Result<TranscriptionJob, Error>;
+}
+
+pub struct AwsTranscription {
+    transcribe_client: aws_sdk_transcribe::Client,
+}
+
+impl AwsTranscription {
+    pub fn new(transcribe_client: aws_sdk_transcribe::Client) -> Self {
+        AwsTranscription { transcribe_client }
+    }
+}
+
+#[async_trait]
+impl Transcription for AwsTranscription {
+    async fn start_transcription_job(
+        &self,
+        job_name: &str,
+        media_uri: &str,
+        output_bucket: &str,
+    ) -> Result<(), Error> {
+        self.transcribe_client
+            .start_transcription_job()
+            .transcription_job_name(job_name)
+            .output_bucket_name(output_bucket)
+            .output_key(transcribe_output_key(job_name))
+            .settings(
+                Settings::builder()
+                    .show_speaker_labels(true)
+                    .max_speaker_labels(5)
+                    .build(),
+            )
+            .identify_language(true)
+            .media(Media::builder().media_file_uri(media_uri).build())
+            .tags(Tag::builder().key("task_id").value(job_name).build()?)
+            .send()
+            .await
+            .map_err(ApiError::from)?;
+        Ok(())
+    }
+
+    async fn get_transcription_job(&self, job_name: &str) -> Result<TranscriptionJob, Error> {
+        let job = self
+            .transcribe_client
+            .get_transcription_job()
+            .transcription_job_name(job_name)
+            .send()
+            .await
+            .map_err(ApiError::from)?
+            .transcription_job
+            .ok_or_else(|| Error::from("Transcription Job error"))?;
+
+        let status = match job.transcription_job_status {
+            Some(aws_sdk_transcribe::types::TranscriptionJobStatus::Completed) => {
+                TranscriptionJobStatus::Completed
+            }
+            Some(aws_sdk_transcribe::types::TranscriptionJobStatus::Failed) => {
+                TranscriptionJobStatus::Failed
+            }
+            _ => TranscriptionJobStatus::InProgress,
+        };
+
+        Ok(TranscriptionJob {
+            job_name: job_name.to_string(),
+            media_uri: job.media.and_then(|media| media.media_file_uri),
+            status,
+            language_code: job.language_code.map(|l| l.as_str().to_string()),
+            identified_language_score: job.identified_language_score.map(f64::from),
+            failure_reason: job.failure_reason,
+            created_at: job.creation_time.as_ref().and_then(to_utc),
+            started_at: job.start_time.as_ref().and_then(to_utc),
+            completed_at: job.completion_time.as_ref().and_then(to_utc),
+        })
+    }
+}
+
+fn to_utc(date_time: &AwsDateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(date_time.secs(), date_time.subsec_nanos())
+}
+
+/// Jobs only progress when [`InMemoryTranscription::complete`] or
+/// [`InMemoryTranscription::fail`] is called. Nothing is written to the output bucket, callers
+/// put the transcript under [`transcribe_output_key`] themselves.
+#[derive(Default)]
+pub struct InMemoryTranscription {
+    jobs: Mutex<BTreeMap<String, TranscriptionJob>>,
+}
+
+impl InMemoryTranscription {
+    pub fn new() -> Self {
+        InMemoryTranscription::default()
+    }
+
+    pub fn jobs(&self) -> Vec<TranscriptionJob> {
+        self.jobs.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn complete(&self, job_name: &str, language_code: &str) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs
+            .entry(job_name.to_string())
+            .or_insert_with(|| in_progress(job_name, None));
+        job.status = TranscriptionJobStatus::Completed;
+        job.language_code = Some(language_code.to_string());
+        job.completed_at = Some(Utc::now());
+    }
+
+    pub fn fail(&self, job_name: &str, failure_reason: &str) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs
+            .entry(job_name.to_string())
+            .or_insert_with(|| in_progress(job_name, None));
+        job.status = TranscriptionJobStatus::Failed;
+        job.failure_reason = Some(failure_reason.to_string());
+        job.completed_at = Some(Utc::now());
+    }
+}
+
+fn in_progress(job_name: &str, media_uri: Option<&str>) -> TranscriptionJob {
+    TranscriptionJob {
+        job_name: job_name.to_string(),
+        media_uri: media_uri.map(str::to_string),
+        status: TranscriptionJobStatus::InProgress,
+        language_code: None,
+        identified_language_score: None,
+        failure_reason: None,
+        created_at: Some(Utc::now()),
+        started_at: Some(Utc::now()),
+        completed_at: None,
+    }
+}
+
+#[async_trait]
+impl Transcription for InMemoryTranscription {
+    async fn start_transcription_job(
+        &self,
+        job_name: &str,
+        media_uri: &str,
+        _output_bucket: &str,
+    ) -> Result<(), Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+
+        if jobs.contains_key(job_name) {
+            return Err(Error::from(format!(
+                "The requested job name {} already exists",
+                job_name
+            )));
+        }
+
+        jobs.insert(job_name.to_string(), in_progress(job_name, Some(media_uri)));
+        Ok(())
+    }
+
+    async fn get_transcription_job(&self, job_name: &str) -> Result<TranscriptionJob, Error> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(job_name)
+            .cloned()
+            .ok_or_else(|| Error::from(format!("Transcription job {} not found", job_name)))
+    }
+}
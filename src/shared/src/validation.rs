@@ -0,0 +1,307 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::error::Category;
+use serde_valid::validation::{Error, Errors};
+
+/// One failing request field, shaped for clients to highlight the matching form input.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// camelCase path of the field, `None` when the whole request is at fault.
+    pub field: Option<String>,
+    pub rule: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: Option<&str>, rule: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.map(str::to_string),
+            rule: rule.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Error for custom validators, reported under `rule` instead of the generic `custom` rule.
+pub fn rule_error(rule: &str, message: impl Into<String>) -> Error {
+    Error::Custom(format!("[{}] {}", rule, message.into()))
+}
+
+/// Error of a struct-level validator about one of the struct's fields, reported under that
+/// camelCase `field` instead of the struct.
+pub fn field_rule_error(field: &str, rule: &str, message: impl Into<String>) -> Error {
+    Error::Custom(format!("[{}@{}] {}", rule, field, message.into()))
+}
+
+/// Flattens `serde_valid` errors into one [`FieldError`] per failing rule.
+///
+/// Fields are reported under their camelCase names, the `rename_all` our request types use.
+pub fn field_errors(errors: &Errors) -> Vec<FieldError> {
+    let mut field_errors = Vec::new();
+    collect(errors, None, &mut field_errors);
+    field_errors
+}
+
+fn collect(errors: &Errors, path: Option<&str>, field_errors: &mut Vec<FieldError>) {
+    match errors {
+        Errors::Object(object) => {
+            push_all(&object.errors, path, field_errors);
+            for (property, errors) in &object.properties {
+                let property = to_camel_case(property);
+                let path = match path {
+                    Some(path) => format!("{}.{}", path, property),
+                    None => property,
+                };
+                collect(errors, Some(&path), field_errors);
+            }
+        }
+        Errors::Array(array) => {
+            push_all(&array.errors, path, field_errors);
+            for (index, errors) in &array.items {
+                let path = format!("{}[{}]", path.unwrap_or_default(), index);
+                collect(errors, Some(&path), field_errors);
+            }
+        }
+        Errors::NewType(errors) => push_all(errors, path, field_errors),
+    }
+}
+
+fn push_all(errors: &[Error], path: Option<&str>, field_errors: &mut Vec<FieldError>) {
+    for error in errors {
+        match error {
+            Error::Items(array) => collect(&Errors::Array(array.clone()), path, field_errors),
+            Error::Properties(object) => {
+                collect(&Errors::Object(object.clone()), path, field_errors)
+            }
+            Error::Custom(message) => {
+                let (rule, message) = match message
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.split_once("] "))
+                {
+                    Some((rule, message)) => (rule, message),
+                    None => ("custom", message.as_str()),
+                };
+                match rule.split_once('@') {
+                    Some((rule, field)) => {
+                        let path = match path {
+                            Some(path) => format!("{}.{}", path, field),
+                            None => field.to_string(),
+                        };
+                        field_errors.push(FieldError::new(Some(&path), rule, message));
+                    }
+                    None => field_errors.push(FieldError::new(path, rule, message)),
+                }
+            }
+            error => field_errors.push(FieldError::new(path, rule_name(error), error.to_string())),
+        }
+    }
+}
+
+fn rule_name(error: &Error) -> &'static str {
+    match error {
+        Error::Minimum(_) => "minimum",
+        Error::Maximum(_) => "maximum",
+        Error::ExclusiveMinimum(_) => "exclusive_minimum",
+        Error::ExclusiveMaximum(_) => "exclusive_maximum",
+        Error::MultipleOf(_) => "multiple_of",
+        Error::MinLength(_) => "min_length",
+        Error::MaxLength(_) => "max_length",
+        Error::Pattern(_) => "pattern",
+        Error::MinItems(_) => "min_items",
+        Error::MaxItems(_) => "max_items",
+        Error::UniqueItems(_) => "unique_items",
+        Error::MinProperties(_) => "min_properties",
+        Error::MaxProperties(_) => "max_properties",
+        Error::Enumerate(_) => "enumerate",
+        _ => "custom",
+    }
+}
+
+/// Parses a JSON request body, reporting a failure as the field it happened at.
+pub fn parse_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, FieldError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        let err = err.into_inner();
+        let message = without_position(&err.to_string());
+
+        match err.classify() {
+            Category::Data => {
+                let rule = if message.starts_with("missing field") {
+                    "required"
+                } else if message.starts_with("unknown field") {
+                    "unknown_field"
+                } else {
+                    "type"
+                };
+
+                // A missing field fails on the enclosing object, so its name only appears in
+                // the message.
+                let missing_field = match rule {
+                    "required" => quoted_name(&message),
+                    _ => None,
+                };
+
+                let field = match (path.as_str(), missing_field) {
+                    (".", missing_field) => missing_field,
+                    (path, Some(missing_field)) => Some(format!("{}.{}", path, missing_field)),
+                    (path, None) => Some(path.to_string()),
+                };
+
+                FieldError::new(field.as_deref(), rule, message)
+            }
+            _ => FieldError::new(None, "json", message),
+        }
+    })
+}
+
+fn without_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message.to_string(),
+    }
+}
+
+fn quoted_name(message: &str) -> Option<String> {
+    let mut parts = message.split('`');
+    parts.next()?;
+    parts.next().map(str::to_string)
+}
+
+fn to_camel_case(name: &str) -> String {
+    let mut camel_case = String::with_capacity(name.len());
+    let mut upper = false;
+
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel_case.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel_case.push(c);
+        }
+    }
+
+    camel_case
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_valid::Validate;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, Validate)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    struct Request {
+        #[validate(min_length = 5)]
+        source_url: String,
+        #[validate(custom = validate_date)]
+        date: String,
+        #[validate]
+        #[serde(default)]
+        tags: Vec<Tag>,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Tag {
+        #[validate(max_length = 3)]
+        name: String,
+    }
+
+    fn validate_date(date: &str) -> Result<(), Error> {
+        match date.len() {
+            10 => Ok(()),
+            _ => Err(rule_error("date_format", "Expected format is yyyy-MM-dd.")),
+        }
+    }
+
+    #[test]
+    fn lists_every_failing_field() {
+        let request: Request = parse_json(
+            br#"{"sourceUrl": "abc", "date": "July", "tags": [{"name": "rust"}]}"#,
+        )
+            .unwrap();
+
+        let mut errors = field_errors(&request.validate().unwrap_err());
+        errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+        assert_eq!(
+            errors,
+            vec![
+                FieldError::new(Some("date"), "date_format", "Expected format is yyyy-MM-dd."),
+                FieldError::new(
+                    Some("sourceUrl"),
+                    "min_length",
+                    "The length of the value must be `>= 5`."
+                ),
+                FieldError::new(
+                    Some("tags[0].name"),
+                    "max_length",
+                    "The length of the value must be `<= 3`."
+                ),
+            ]
+        );
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    #[validate(custom = |c| validate_callback(&c.callback_url, &c.callback_secret))]
+    struct Callback {
+        callback_url: Option<String>,
+        callback_secret: Option<String>,
+    }
+
+    fn validate_callback(url: &Option<String>, secret: &Option<String>) -> Result<(), Error> {
+        match (url, secret) {
+            (Some(_), None) => Err(field_rule_error(
+                "callbackSecret",
+                "required_together",
+                "Both are required.",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    #[test]
+    fn reports_struct_level_errors_at_their_field() {
+        let callback = Callback {
+            callback_url: Some("https://example.com".to_string()),
+            callback_secret: None,
+        };
+
+        assert_eq!(
+            field_errors(&callback.validate().unwrap_err()),
+            vec![FieldError::new(
+                Some("callbackSecret"),
+                "required_together",
+                "Both are required."
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_at_their_field() {
+        let err = parse_json::<Request>(br#"{"sourceUrl": 1, "date": "2024-07-01"}"#).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("sourceUrl"));
+        assert_eq!(err.rule, "type");
+        assert!(!err.message.contains("line"));
+
+        let err = parse_json::<Request>(br#"{"date": "2024-07-01"}"#).unwrap_err();
+        assert_eq!(
+            err,
+            FieldError::new(Some("sourceUrl"), "required", "missing field `sourceUrl`")
+        );
+
+        let err = parse_json::<Request>(br#"{"sourceUrl": "https://example.com", "date": "2024-07-01", "topic": "x"}"#)
+            .unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("topic"));
+        assert_eq!(err.rule, "unknown_field");
+
+        let err = parse_json::<Request>(b"{").unwrap_err();
+        assert_eq!(err.field, None);
+        assert_eq!(err.rule, "json");
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+pub const WEBHOOK_CALLBACK_PREFIX: &str = "media-callbacks/";
+
+/// Where task status changes are pushed for one upload, stored under `media-callbacks/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookCallback {
+    pub url: String,
+    pub secret: String,
+}
+
+pub fn webhook_callback_key(task_id: &str) -> String {
+    format!("{}{}", WEBHOOK_CALLBACK_PREFIX, task_id)
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aws_lambda_events::event::sqs::{BatchItemFailure, SqsBatchResponse, SqsEvent};
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::{info, warn};
+
+use shared::ingestion::{
+    IngestedTask, Ingestion, IngestionJobRecord, IngestionKind, IngestionRequest,
+};
+use shared::storage::{put_json, ObjectStorage};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Time kept in reserve to start the ingestion job and record it before the lambda times out.
+const DEADLINE_MARGIN: Duration = Duration::from_secs(30);
+
+/// Malformed messages are reported back on their own, so SQS only redelivers them and
+/// eventually moves them to the dead-letter queue. Anything else fails the whole batch.
+pub async fn start_ingestion_job(
+    event: LambdaEvent<SqsEvent>,
+    ingestion: &dyn Ingestion,
+    storage: &dyn ObjectStorage,
+    media_bucket_name: &str,
+) -> Result<SqsBatchResponse, Error> {
+    let mut requests = Vec::new();
+    let mut response = SqsBatchResponse::default();
+
+    for record in &event.payload.records {
+        let Some(body) = record.body.as_deref() else {
+            continue;
+        };
+
+        match serde_json::from_str::<IngestionRequest>(body) {
+            Ok(request) => requests.push(request),
+            Err(err) => {
+                let message_id = record.message_id.clone().unwrap_or_default();
+                warn!(message_id, error = %err, "malformed ingestion request");
+                response.batch_item_failures.push(BatchItemFailure {
+                    item_identifier: message_id,
+                });
+            }
+        }
+    }
+
+    if requests.is_empty() {
+        return Ok(response);
+    }
+
+    // The sync drops the documents of deleted tasks, there is no task left to track.
+    let tasks: BTreeMap<_, _> = requests
+        .into_iter()
+        .filter(|request| !request.deleted)
+        .map(|request| {
+            (
+                request.task_id,
+                IngestedTask {
+                    tenant_id: request.tenant_id,
+                    documents: Vec::new(),
+                },
+            )
+        })
+        .collect();
+
+    wait_for_running_ingestion_jobs(ingestion, event.context.deadline).await?;
+
+    let ingestion_job_id = ingestion.start_ingestion_job().await?;
+
+    let record = IngestionJobRecord::pending(ingestion_job_id, IngestionKind::Sync, tasks);
+
+    info!(
+        ingestion_job_id = record.ingestion_job_id,
+        task_count = record.task_ids.len(),
+        "started ingestion job"
+    );
+
+    put_json(storage, media_bucket_name, &record.key(), &record).await?;
+
+    Ok(response)
+}
+
+/// Bedrock rejects a new ingestion job while another one runs on the same data source, so the
+/// batch waits here. Running out of time fails the batch and SQS redelivers it later.
+async fn wait_for_running_ingestion_jobs(
+    ingestion: &dyn Ingestion,
+    deadline_ms: u64,
+) -> Result<(), Error> {
+    let deadline = UNIX_EPOCH + Duration::from_millis(deadline_ms) - DEADLINE_MARGIN;
+
+    loop {
+        let running_jobs = ingestion.running_ingestion_jobs().await?;
+
+        if running_jobs == 0 {
+            return Ok(());
+        }
+
+        if SystemTime::now() + POLL_INTERVAL > deadline {
+            return Err(Error::from("Timed out waiting for running ingestion job"));
+        }
+
+        info!(
+            running_jobs,
+            "waiting for running ingestion job"
+        );
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::ingestion::{
+        IngestionJobDetails, IngestionJobState, IngestionJobStatus, InMemoryIngestion,
+    };
+    use shared::storage::{get_json, InMemoryStorage};
+
+    use super::*;
+
+    fn sqs_event(bodies: &[&str], deadline: SystemTime) -> LambdaEvent<SqsEvent> {
+        let records: Vec<_> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| json!({ "messageId": i.to_string(), "body": body }))
+            .collect();
+
+        let mut context = Context::default();
+        context.deadline = deadline.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+        LambdaEvent::new(
+            serde_json::from_value(json!({ "Records": records })).unwrap(),
+            context,
+        )
+    }
+
+    fn in_fifteen_minutes() -> SystemTime {
+        SystemTime::now() + Duration::from_secs(900)
+    }
+
+    #[tokio::test]
+    async fn starts_one_job_for_the_deduplicated_batch() {
+        let ingestion = InMemoryIngestion::new();
+        let storage = InMemoryStorage::new();
+
+        start_ingestion_job(
+            sqs_event(
+                &[
+                    r#"{"taskId":"task-2"}"#,
+                    r#"{"taskId":"task-1"}"#,
+                    r#"{"taskId":"task-2"}"#,
+                ],
+                in_fifteen_minutes(),
+            ),
+            &ingestion,
+            &storage,
+            "media",
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(ingestion.job_ids(), vec!["job-1".to_string()]);
+
+        let record: IngestionJobRecord =
+            get_json(&storage, "media", "ingestion-jobs/pending/job-1.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(record.kind, IngestionKind::Sync);
+        assert_eq!(record.status, IngestionJobStatus::Pending);
+        assert_eq!(record.task_ids, vec!["task-1".to_string(), "task-2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn syncs_deleted_tasks_without_tracking_them() {
+        let ingestion = InMemoryIngestion::new();
+        let storage = InMemoryStorage::new();
+
+        start_ingestion_job(
+            sqs_event(
+                &[
+                    r#"{"taskId":"task-1","tenantId":"acme","deleted":true}"#,
+                    r#"{"taskId":"task-2","tenantId":"acme"}"#,
+                ],
+                in_fifteen_minutes(),
+            ),
+            &ingestion,
+            &storage,
+            "media",
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(ingestion.job_ids(), vec!["job-1".to_string()]);
+
+        let record: IngestionJobRecord =
+            get_json(&storage, "media", "ingestion-jobs/pending/job-1.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(record.task_ids, vec!["task-2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn does_nothing_for_an_empty_batch() {
+        let ingestion = InMemoryIngestion::new();
+        let storage = InMemoryStorage::new();
+
+        start_ingestion_job(sqs_event(&[], in_fifteen_minutes()), &ingestion, &storage, "media")
+            .await
+            .unwrap();
+
+        assert!(ingestion.job_ids().is_empty());
+        assert!(storage.keys("media").is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_only_malformed_messages_as_failures() {
+        let ingestion = InMemoryIngestion::new();
+        let storage = InMemoryStorage::new();
+
+        let response = start_ingestion_job(
+            sqs_event(
+                &[r#"{"taskId":"task-1"}"#, "not json", r#"{"task":"task-2"}"#],
+                in_fifteen_minutes(),
+            ),
+            &ingestion,
+            &storage,
+            "media",
+        )
+            .await
+            .unwrap();
+
+        let failed: Vec<&str> = response
+            .batch_item_failures
+            .iter()
+            .map(|failure| failure.item_identifier.as_str())
+            .collect();
+        assert_eq!(failed, vec!["1", "2"]);
+
+        let record: IngestionJobRecord =
+            get_json(&storage, "media", "ingestion-jobs/pending/job-1.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(record.task_ids, vec!["task-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn starts_no_job_without_a_valid_message() {
+        let ingestion = InMemoryIngestion::new();
+        let storage = InMemoryStorage::new();
+
+        let response = start_ingestion_job(
+            sqs_event(&["not json"], in_fifteen_minutes()),
+            &ingestion,
+            &storage,
+            "media",
+        )
+            .await
+            .unwrap();
+
+        assert_eq!(response.batch_item_failures.len(), 1);
+        assert!(ingestion.job_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_a_job_is_still_running_at_the_deadline() {
+        let ingestion = InMemoryIngestion::new();
+        ingestion.set_job(
+            "running",
+            IngestionJobDetails {
+                state: IngestionJobState::Running,
+                statistics: None,
+                failure_reasons: Vec::new(),
+            },
+        );
+        let storage = InMemoryStorage::new();
+
+        let result = start_ingestion_job(
+            sqs_event(&[r#"{"taskId":"task-1"}"#], SystemTime::now()),
+            &ingestion,
+            &storage,
+            "media",
+        )
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(ingestion.job_ids(), vec!["running".to_string()]);
+        assert!(storage.keys("media").is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+use std::env;
+
+use aws_config::BehaviorVersion;
+use aws_lambda_events::event::sqs::SqsEvent;
+use lambda_runtime::{Error, LambdaEvent, run, service_fn, tracing};
+
+use shared::ingestion::BedrockIngestion;
+use shared::storage::S3Storage;
+use start_ingestion_job::start_ingestion_job;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let ingestion = BedrockIngestion::new(
+        aws_sdk_bedrockagent::Client::new(&config),
+        env::var("KB_ID").expect("KB_ID not set"),
+        env::var("DATA_SOURCE_ID").expect("DATA_SOURCE_ID not set"),
+    );
+    let storage = S3Storage::new(aws_sdk_s3::Client::new(&config));
+
+    let media_bucket_name = env::var("MEDIA_BUCKET").expect("MEDIA_BUCKET not set");
+
+    run(service_fn(|event: LambdaEvent<SqsEvent>| async {
+        start_ingestion_job(event, &ingestion, &storage, &media_bucket_name).await
+    }))
+        .await
+}
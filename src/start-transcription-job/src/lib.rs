@@ -0,0 +1,115 @@
+use aws_lambda_events::event::s3::S3Event;
+use lambda_runtime::{Error, LambdaEvent};
+
+use shared::auth::tenant_and_task;
+use shared::transcription::Transcription;
+
+pub async fn start_transcription_job(
+    event: LambdaEvent<S3Event>,
+    transcription: &dyn Transcription,
+) -> Result<(), Error> {
+    for record in event.payload.records {
+        let object_key = record
+            .s3
+            .object
+            .key
+            .ok_or_else(|| Error::from("Missing object key"))?;
+        let bucket_name = record
+            .s3
+            .bucket
+            .name
+            .ok_or_else(|| Error::from("Missing bucket name"))?;
+
+        // The tenant stays readable from the job's media uri, where the handler finds it.
+        let (_, task_id) = tenant_and_task(&object_key)
+            .ok_or_else(|| Error::from(format!("No tenant in object key {}", object_key)))?;
+
+        transcription
+            .start_transcription_job(
+                task_id,
+                &format!("s3://{}/{}", bucket_name, &object_key),
+                &bucket_name,
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::transcription::{InMemoryTranscription, TranscriptionJobStatus};
+
+    use super::*;
+
+    fn s3_event(keys: &[&str]) -> LambdaEvent<S3Event> {
+        let records: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                json!({
+                    "eventTime": "2024-07-01T00:00:00Z",
+                    "userIdentity": { "principalId": "test" },
+                    "requestParameters": { "sourceIPAddress": "127.0.0.1" },
+                    "s3": {
+                        "bucket": { "name": "media" },
+                        "object": { "key": key }
+                    }
+                })
+            })
+            .collect();
+
+        LambdaEvent::new(
+            serde_json::from_value(json!({ "Records": records })).unwrap(),
+            Context::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn starts_a_job_named_after_each_task() {
+        let transcription = InMemoryTranscription::new();
+
+        start_transcription_job(
+            s3_event(&["media-uploads/acme/task-1", "media-uploads/globex/task-2"]),
+            &transcription,
+        )
+            .await
+            .unwrap();
+
+        let jobs = transcription.jobs();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].job_name, "task-1");
+        assert_eq!(
+            jobs[0].media_uri.as_deref(),
+            Some("s3://media/media-uploads/acme/task-1")
+        );
+        assert_eq!(jobs[0].status, TranscriptionJobStatus::InProgress);
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_job_already_exists() {
+        let transcription = InMemoryTranscription::new();
+        transcription
+            .start_transcription_job("task-1", "s3://media/media-uploads/acme/task-1", "media")
+            .await
+            .unwrap();
+
+        let result =
+            start_transcription_job(s3_event(&["media-uploads/acme/task-1"]), &transcription).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_uploads_outside_a_tenant_folder() {
+        let transcription = InMemoryTranscription::new();
+
+        let result =
+            start_transcription_job(s3_event(&["media-uploads/task-1"]), &transcription).await;
+
+        assert!(result.is_err());
+        assert!(transcription.jobs().is_empty());
+    }
+}
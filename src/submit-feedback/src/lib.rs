@@ -0,0 +1,163 @@
+use chrono::Utc;
+use lambda_http::{Body, Error, Request, Response};
+use serde::Deserialize;
+use serde_valid::validation::Error as ValidationError;
+use serde_valid::Validate;
+
+use shared::analytics::{log_feedback, FeedbackEntry, Rating};
+use shared::auth::{authorize, AccessPolicy};
+use shared::error::ApiError;
+use shared::storage::ObjectStorage;
+use shared::validation::{parse_json, rule_error};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct Feedback {
+    /// The `response_id` of a query answer.
+    #[validate(custom = validate_response_id)]
+    pub response_id: String,
+    pub rating: Rating,
+    #[validate(max_length = 2000)]
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Response ids end up in object keys, only the characters of generated ids are accepted.
+fn validate_response_id(response_id: &str) -> Result<(), ValidationError> {
+    let valid = (1..=64).contains(&response_id.len())
+        && response_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match valid {
+        true => Ok(()),
+        false => Err(rule_error(
+            "response_id",
+            format!("Invalid response id {}", response_id),
+        )),
+    }
+}
+
+pub async fn submit_feedback(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    analytics_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, Error> {
+    store_feedback(event, storage, analytics_bucket_name, policy)
+        .await
+        .or_else(ApiError::into_response)
+}
+
+/// Feedback is stored under the caller's tenant, whatever response it refers to, so it can
+/// only ever be joined with the tenant's own queries.
+async fn store_feedback(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    analytics_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, ApiError> {
+    let caller = authorize(&event, policy)?;
+
+    let feedback: Feedback = parse_json(event.body())?;
+
+    feedback.validate()?;
+
+    log_feedback(
+        storage,
+        analytics_bucket_name,
+        &FeedbackEntry {
+            response_id: feedback.response_id,
+            tenant_id: caller.tenant_id,
+            subject: caller.subject,
+            timestamp: Utc::now(),
+            rating: feedback.rating,
+            comment: feedback.comment.filter(|comment| !comment.trim().is_empty()),
+        },
+    )
+        .await?;
+
+    Ok(Response::builder().status(204).body(Body::Empty)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use shared::auth::with_claims;
+    use shared::storage::InMemoryStorage;
+
+    use super::*;
+
+    async fn send(storage: &InMemoryStorage, body: Value) -> (u16, Vec<u8>) {
+        let event = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/feedback")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let event = with_claims(
+            event,
+            &[
+                ("sub", "user-1"),
+                ("custom:tenant_id", "acme"),
+                ("scope", "kb:query"),
+            ],
+        );
+        let policy = AccessPolicy {
+            required_scope: Some("kb:query".to_string()),
+            ..Default::default()
+        };
+
+        let response = submit_feedback(event, storage, "media", &policy).await.unwrap();
+        (response.status().as_u16(), response.body().to_vec())
+    }
+
+    #[tokio::test]
+    async fn stores_the_feedback_under_the_callers_tenant() {
+        let storage = InMemoryStorage::new();
+
+        let (status, _) = send(
+            &storage,
+            json!({
+                "responseId": "20240701-V1StGXR8_Z5jdHi6B-myT",
+                "rating": "down",
+                "comment": "Off"
+            }),
+        )
+            .await;
+
+        assert_eq!(status, 204);
+        assert_eq!(
+            storage.keys("media"),
+            vec!["analytics/feedback/tenant_id=acme/dt=2024-07-01/\
+                20240701-V1StGXR8_Z5jdHi6B-myT-user-1.json"]
+        );
+        let keys = storage.keys("media");
+
+        let entry: FeedbackEntry =
+            serde_json::from_slice(&storage.get_object("media", &keys[0]).await.unwrap().unwrap())
+                .unwrap();
+        assert_eq!(entry.rating, Rating::Down);
+        assert_eq!(entry.comment, Some("Off".to_string()));
+        assert_eq!(entry.subject, "user-1");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_feedback() {
+        let storage = InMemoryStorage::new();
+
+        let (status, body) =
+            send(&storage, json!({ "responseId": "../other", "rating": "up" })).await;
+        assert_eq!(status, 400);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "responseId");
+
+        let (status, body) =
+            send(&storage, json!({ "responseId": "response-1", "rating": "meh" })).await;
+        assert_eq!(status, 400);
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "rating");
+
+        assert!(storage.keys("media").is_empty());
+    }
+}
@@ -0,0 +1,781 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use lambda_runtime::tracing::{info, warn};
+use serde_json::Value;
+
+use shared::cache::AnswerCache;
+use shared::events::{EventPublisher, TaskStatusChanged};
+use shared::ingestion::{
+    DocumentState, Ingestion, IngestionJobRecord, IngestionJobState, IngestionJobStatus,
+    IngestionKind, IngestionOutcome, INGESTION_OUTCOME_DETAIL_TYPE, PENDING_INGESTION_JOB_PREFIX,
+};
+use shared::models::DocType;
+use shared::storage::{get_json, list_all_keys, put_json, ObjectStorage};
+use shared::task::{task_record_key, TaskRecord, TaskStatus};
+
+pub struct IngestionTracker {
+    pub ingestion: Arc<dyn Ingestion>,
+    pub storage: Arc<dyn ObjectStorage>,
+    pub event_publisher: Arc<dyn EventPublisher>,
+    pub answer_cache: Arc<dyn AnswerCache>,
+    pub media_bucket_name: String,
+    pub kb_bucket_name: String,
+}
+
+/// Per task outcome: `None` while Bedrock is still working on the document.
+type DocumentOutcome = Option<Result<(), String>>;
+
+pub async fn track_ingestion_jobs(
+    _event: LambdaEvent<Value>,
+    tracker: &IngestionTracker,
+) -> Result<(), Error> {
+    let pending_keys = list_all_keys(
+        tracker.storage.as_ref(),
+        &tracker.media_bucket_name,
+        PENDING_INGESTION_JOB_PREFIX,
+    )
+        .await?;
+
+    for key in pending_keys {
+        let record: IngestionJobRecord =
+            get_json(tracker.storage.as_ref(), &tracker.media_bucket_name, &key)
+                .await?
+                .ok_or_else(|| Error::from("Ingestion job record error"))?;
+
+        let record = match record.kind {
+            IngestionKind::Sync => tracker.track_sync(record).await?,
+            IngestionKind::Documents => tracker.track_documents(record).await?,
+        };
+
+        if let Some(record) = record {
+            tracker.complete(&key, &record).await?;
+        }
+    }
+
+    Ok(())
+}
+
+impl IngestionTracker {
+    /// Returns the finished record, or `None` while the ingestion job is still running.
+    async fn track_sync(
+        &self,
+        mut record: IngestionJobRecord,
+    ) -> Result<Option<IngestionJobRecord>, Error> {
+        let ingestion_job = self
+            .ingestion
+            .get_ingestion_job(&record.ingestion_job_id)
+            .await?;
+
+        record.statistics = ingestion_job.statistics;
+        record.failure_reasons = ingestion_job.failure_reasons;
+
+        let documents_failed = record
+            .statistics
+            .as_ref()
+            .map_or(0, |statistics| statistics.documents_failed);
+
+        let outcomes: HashMap<String, Result<(), String>> = match ingestion_job.state {
+            IngestionJobState::Complete if documents_failed == 0 => record
+                .task_ids
+                .iter()
+                .map(|task_id| (task_id.clone(), Ok(())))
+                .collect(),
+            // Only some documents failed, ask Bedrock which ones. The job is over, documents
+            // it left without an outcome never made it into the knowledge base.
+            IngestionJobState::Complete => self
+                .document_outcomes(&record)
+                .await?
+                .into_iter()
+                .map(|(task_id, outcome)| {
+                    let outcome = outcome.unwrap_or_else(|| {
+                        Err("Documents not indexed by the ingestion job".to_string())
+                    });
+                    (task_id, outcome)
+                })
+                .collect(),
+            IngestionJobState::Failed | IngestionJobState::Stopped => {
+                let reason = if record.failure_reasons.is_empty() {
+                    match ingestion_job.state {
+                        IngestionJobState::Stopped => "Ingestion job STOPPED".to_string(),
+                        _ => "Ingestion job FAILED".to_string(),
+                    }
+                } else {
+                    record.failure_reasons.join("; ")
+                };
+                record
+                    .task_ids
+                    .iter()
+                    .map(|task_id| (task_id.clone(), Err(reason.clone())))
+                    .collect()
+            }
+            IngestionJobState::Running => return Ok(None),
+        };
+
+        record.status = match ingestion_job.state {
+            IngestionJobState::Complete => IngestionJobStatus::Complete,
+            _ => IngestionJobStatus::Failed,
+        };
+
+        self.apply_outcomes(&mut record, outcomes).await?;
+
+        Ok(Some(record))
+    }
+
+    /// Returns the finished record, or `None` while any document is still being ingested.
+    async fn track_documents(
+        &self,
+        mut record: IngestionJobRecord,
+    ) -> Result<Option<IngestionJobRecord>, Error> {
+        let outcomes = self.document_outcomes(&record).await?;
+
+        let outcomes: HashMap<String, Result<(), String>> = match outcomes
+            .into_iter()
+            .map(|(task_id, outcome)| outcome.map(|outcome| (task_id, outcome)))
+            .collect()
+        {
+            Some(outcomes) => outcomes,
+            None => return Ok(None),
+        };
+
+        record.status = if outcomes.values().all(Result::is_ok) {
+            IngestionJobStatus::Complete
+        } else {
+            IngestionJobStatus::Failed
+        };
+
+        self.apply_outcomes(&mut record, outcomes).await?;
+
+        Ok(Some(record))
+    }
+
+    /// A task fails with any of its documents, and is pending while any other one is. Tasks
+    /// without a known tenant fail, their documents cannot be located.
+    async fn document_outcomes(
+        &self,
+        record: &IngestionJobRecord,
+    ) -> Result<HashMap<String, DocumentOutcome>, Error> {
+        let mut outcomes = HashMap::new();
+
+        for task_id in &record.task_ids {
+            let tenant_id = match tenant_id(record, task_id) {
+                Some(tenant_id) => tenant_id,
+                None => {
+                    warn!(task_id, "no tenant to locate the task documents with");
+                    outcomes.insert(
+                        task_id.clone(),
+                        Some(Err("No tenant to locate the documents with".to_string())),
+                    );
+                    continue;
+                }
+            };
+
+            let mut pending = false;
+            let mut failure_reasons = Vec::new();
+
+            for doc_type in self.task_documents(record, tenant_id, task_id).await? {
+                let location = format!(
+                    "s3://{}/{}",
+                    self.kb_bucket_name,
+                    doc_type.kb_key(tenant_id, task_id)
+                );
+
+                match self.ingestion.document_state(&location).await? {
+                    DocumentState::Indexed => {}
+                    DocumentState::Pending => pending = true,
+                    DocumentState::Failed(reason) => failure_reasons.push(reason),
+                }
+            }
+
+            let outcome = match (failure_reasons.is_empty(), pending) {
+                (false, _) => Some(Err(failure_reasons.join("; "))),
+                (true, true) => None,
+                (true, false) => Some(Ok(())),
+            };
+
+            outcomes.insert(task_id.clone(), outcome);
+        }
+
+        Ok(outcomes)
+    }
+
+    /// The documents pushed for the task, or for syncs those the knowledge base bucket holds.
+    /// The transcript stands in when there are none left to look at.
+    async fn task_documents(
+        &self,
+        record: &IngestionJobRecord,
+        tenant_id: &str,
+        task_id: &str,
+    ) -> Result<Vec<DocType>, Error> {
+        if let Some(task) = record.tasks.get(task_id).filter(|task| !task.documents.is_empty()) {
+            return Ok(task.documents.clone());
+        }
+
+        let mut documents = Vec::new();
+        for doc_type in DocType::ALL {
+            let key = doc_type.kb_key(tenant_id, task_id);
+            if self.storage.object_exists(&self.kb_bucket_name, &key).await? {
+                documents.push(doc_type);
+            }
+        }
+
+        if documents.is_empty() {
+            documents.push(DocType::Transcript);
+        }
+        Ok(documents)
+    }
+
+    async fn apply_outcomes(
+        &self,
+        record: &mut IngestionJobRecord,
+        outcomes: HashMap<String, Result<(), String>>,
+    ) -> Result<(), Error> {
+        for (task_id, outcome) in outcomes {
+            let (status, failure_reason) = match outcome {
+                Ok(()) => (TaskStatus::Indexed, None),
+                Err(reason) => (TaskStatus::Failed, Some(reason)),
+            };
+
+            match status {
+                TaskStatus::Indexed => record.indexed_task_ids.push(task_id.clone()),
+                _ => record.failed_task_ids.push(task_id.clone()),
+            }
+
+            let tenant_id = tenant_id(record, &task_id).map(str::to_string);
+            match &tenant_id {
+                Some(tenant_id) => {
+                    self.update_task_record(tenant_id, &task_id, status, failure_reason.clone())
+                        .await?;
+                }
+                None => warn!(task_id, "no tenant to locate the task record with"),
+            }
+
+            self.event_publisher
+                .task_status_changed(&TaskStatusChanged {
+                    transcript_location: tenant_id
+                        .map(|tenant_id| self.transcript_location(&tenant_id, &task_id)),
+                    task_id,
+                    status,
+                    failure_reason,
+                })
+                .await?;
+        }
+
+        record.indexed_task_ids.sort();
+        record.failed_task_ids.sort();
+        record.completed_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Sets the status and failure reason of the task record, if the task has one yet.
+    async fn update_task_record(
+        &self,
+        tenant_id: &str,
+        task_id: &str,
+        status: TaskStatus,
+        failure_reason: Option<String>,
+    ) -> Result<(), Error> {
+        let key = task_record_key(tenant_id, task_id);
+
+        let mut task_record: TaskRecord =
+            match get_json(self.storage.as_ref(), &self.media_bucket_name, &key).await? {
+                Some(task_record) => task_record,
+                None => {
+                    warn!(task_id, "no task record to update");
+                    return Ok(());
+                }
+            };
+
+        // Cached answers about the topic predate the new documents, those of topics the
+        // media moved away from still cite it. Stale topics are only dropped once invalidated.
+        if status == TaskStatus::Indexed {
+            for topic in std::iter::once(&task_record.metadata.topic)
+                .chain(&task_record.stale_topics)
+            {
+                self.answer_cache
+                    .invalidate_topic(&task_record.tenant_id, topic)
+                    .await?;
+            }
+            task_record.stale_topics.clear();
+        }
+
+        task_record.status = status;
+        task_record.failure_reason = failure_reason;
+        task_record.updated_at = Utc::now();
+
+        put_json(self.storage.as_ref(), &self.media_bucket_name, &key, &task_record).await?;
+        Ok(())
+    }
+
+    async fn complete(&self, pending_key: &str, record: &IngestionJobRecord) -> Result<(), Error> {
+        put_json(
+            self.storage.as_ref(),
+            &self.media_bucket_name,
+            &record.key(),
+            record,
+        )
+            .await?;
+
+        self.event_publisher
+            .publish(
+                INGESTION_OUTCOME_DETAIL_TYPE,
+                serde_json::to_value(IngestionOutcome::from(record))?,
+            )
+            .await?;
+
+        self.storage
+            .delete_object(&self.media_bucket_name, pending_key)
+            .await?;
+
+        info!(
+            ingestion_job_id = record.ingestion_job_id,
+            indexed = record.indexed_task_ids.len(),
+            failed = record.failed_task_ids.len(),
+            "ingestion job finished"
+        );
+        Ok(())
+    }
+
+    fn transcript_location(&self, tenant_id: &str, task_id: &str) -> String {
+        format!(
+            "s3://{}/{}",
+            self.kb_bucket_name,
+            DocType::Transcript.kb_key(tenant_id, task_id)
+        )
+    }
+}
+
+/// Documents and task records are stored per tenant. `None` for records written before
+/// tenants were recorded in them, those tasks cannot be located.
+fn tenant_id<'a>(record: &'a IngestionJobRecord, task_id: &str) -> Option<&'a str> {
+    record
+        .tasks
+        .get(task_id)
+        .map(|task| task.tenant_id.as_str())
+        .filter(|tenant_id| !tenant_id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use shared::cache::{AnswerCacheKey, CachedAnswer, InMemoryAnswerCache};
+    use shared::events::{InMemoryEventPublisher, TASK_STATUS_CHANGED_DETAIL_TYPE};
+    use shared::ingestion::{
+        IngestedTask, IngestionJobDetails, IngestionStatistics, InMemoryIngestion,
+    };
+    use shared::models::{MediaDetails, MediaMetadata};
+    use shared::storage::InMemoryStorage;
+
+    use super::*;
+
+    struct Fixture {
+        ingestion: Arc<InMemoryIngestion>,
+        storage: Arc<InMemoryStorage>,
+        event_publisher: Arc<InMemoryEventPublisher>,
+        answer_cache: Arc<InMemoryAnswerCache>,
+        tracker: IngestionTracker,
+    }
+
+    impl Fixture {
+        async fn new(record: IngestionJobRecord) -> Self {
+            let task_ids = record.task_ids.clone();
+            Fixture::with_task_records(record, &task_ids).await
+        }
+
+        async fn with_task_records(record: IngestionJobRecord, task_ids: &[String]) -> Self {
+            let ingestion = Arc::new(InMemoryIngestion::new());
+            let storage = Arc::new(InMemoryStorage::new());
+            let event_publisher = Arc::new(InMemoryEventPublisher::new());
+            let answer_cache = Arc::new(InMemoryAnswerCache::new());
+
+            put_json(storage.as_ref(), "media", &record.key(), &record)
+                .await
+                .unwrap();
+
+            for task_id in task_ids {
+                let task_record = TaskRecord {
+                    task_id: task_id.clone(),
+                    tenant_id: "acme".to_string(),
+                    metadata: MediaMetadata {
+                        topic: "serverless".to_string(),
+                        ..Default::default()
+                    },
+                    status: TaskStatus::Transcribed,
+                    details: MediaDetails::default(),
+                    keywords: Vec::new(),
+                    failure_reason: None,
+                    stale_topics: Vec::new(),
+                    updated_at: Utc::now(),
+                };
+                put_json(
+                    storage.as_ref(),
+                    "media",
+                    &task_record_key("acme", task_id),
+                    &task_record,
+                )
+                    .await
+                    .unwrap();
+            }
+
+            Fixture {
+                tracker: IngestionTracker {
+                    ingestion: ingestion.clone(),
+                    storage: storage.clone(),
+                    event_publisher: event_publisher.clone(),
+                    answer_cache: answer_cache.clone(),
+                    media_bucket_name: "media".to_string(),
+                    kb_bucket_name: "kb".to_string(),
+                },
+                ingestion,
+                storage,
+                event_publisher,
+                answer_cache,
+            }
+        }
+
+        async fn track(&self) -> Result<(), Error> {
+            track_ingestion_jobs(LambdaEvent::new(json!({}), Context::default()), &self.tracker)
+                .await
+        }
+
+        async fn task_status(&self, task_id: &str) -> (TaskStatus, Option<String>) {
+            let task_record: TaskRecord =
+                get_json(self.storage.as_ref(), "media", &task_record_key("acme", task_id))
+                    .await
+                    .unwrap()
+                    .unwrap();
+            (task_record.status, task_record.failure_reason)
+        }
+
+        async fn finished_record(&self, ingestion_job_id: &str) -> Option<IngestionJobRecord> {
+            get_json(
+                self.storage.as_ref(),
+                "media",
+                &format!("ingestion-jobs/{}.json", ingestion_job_id),
+            )
+                .await
+                .unwrap()
+        }
+    }
+
+    async fn cache_answers(answer_cache: &InMemoryAnswerCache) {
+        for topic in ["serverless", "containers"] {
+            let key = AnswerCacheKey::new("acme", topic, "What is new?", &[], &[], "claude");
+            let answer = CachedAnswer {
+                output: "Nothing".to_string(),
+                sources: Vec::new(),
+                citation_count: 0,
+                sources_by_knowledge_base: Default::default(),
+            };
+            answer_cache.put(&key, "0", &answer).await.unwrap();
+        }
+    }
+
+    fn cached_topics(answer_cache: &InMemoryAnswerCache) -> Vec<String> {
+        answer_cache.keys().into_iter().map(|key| key.topic).collect()
+    }
+
+    fn tasks(task_ids: &[&str]) -> BTreeMap<String, IngestedTask> {
+        task_ids
+            .iter()
+            .map(|task_id| {
+                (
+                    task_id.to_string(),
+                    IngestedTask {
+                        tenant_id: "acme".to_string(),
+                        documents: Vec::new(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn sync_record() -> IngestionJobRecord {
+        IngestionJobRecord::pending(
+            "job-1".to_string(),
+            IngestionKind::Sync,
+            tasks(&["task-1", "task-2"]),
+        )
+    }
+
+    fn job(state: IngestionJobState, documents_failed: i64) -> IngestionJobDetails {
+        IngestionJobDetails {
+            state,
+            statistics: Some(IngestionStatistics {
+                documents_scanned: 2,
+                documents_failed,
+                ..Default::default()
+            }),
+            failure_reasons: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn leaves_running_jobs_pending() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Running, 0));
+
+        fixture.track().await.unwrap();
+
+        assert!(fixture
+            .storage
+            .object_exists("media", "ingestion-jobs/pending/job-1.json")
+            .await
+            .unwrap());
+        assert!(fixture.finished_record("job-1").await.is_none());
+        assert!(fixture.event_publisher.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn marks_every_task_indexed_when_the_sync_completes() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Complete, 0));
+        cache_answers(&fixture.answer_cache).await;
+
+        fixture.track().await.unwrap();
+
+        let record = fixture.finished_record("job-1").await.unwrap();
+        assert_eq!(record.status, IngestionJobStatus::Complete);
+        assert_eq!(record.indexed_task_ids, vec!["task-1", "task-2"]);
+        assert_eq!(cached_topics(&fixture.answer_cache), vec!["containers"]);
+        assert!(record.completed_at.is_some());
+        assert!(!fixture
+            .storage
+            .object_exists("media", "ingestion-jobs/pending/job-1.json")
+            .await
+            .unwrap());
+
+        assert_eq!(fixture.task_status("task-1").await.0, TaskStatus::Indexed);
+        assert_eq!(fixture.task_status("task-2").await.0, TaskStatus::Indexed);
+
+        let detail_types: Vec<String> = fixture
+            .event_publisher
+            .events()
+            .into_iter()
+            .map(|(detail_type, _)| detail_type)
+            .collect();
+        assert_eq!(
+            detail_types,
+            vec![
+                TASK_STATUS_CHANGED_DETAIL_TYPE,
+                TASK_STATUS_CHANGED_DETAIL_TYPE,
+                INGESTION_OUTCOME_DETAIL_TYPE
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn asks_for_document_outcomes_when_some_documents_failed() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Complete, 1));
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-1", DocumentState::Indexed);
+        fixture.ingestion.set_document_state(
+            "s3://kb/transcripts/acme/task-2",
+            DocumentState::Failed("Unsupported content".to_string()),
+        );
+
+        fixture.track().await.unwrap();
+
+        let record = fixture.finished_record("job-1").await.unwrap();
+        assert_eq!(record.status, IngestionJobStatus::Complete);
+        assert_eq!(record.indexed_task_ids, vec!["task-1"]);
+        assert_eq!(record.failed_task_ids, vec!["task-2"]);
+        assert_eq!(
+            fixture.task_status("task-2").await,
+            (TaskStatus::Failed, Some("Unsupported content".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn invalidates_the_topics_media_moved_away_from() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Complete, 0));
+        cache_answers(&fixture.answer_cache).await;
+
+        let key = task_record_key("acme", "task-1");
+        let mut task_record: TaskRecord = get_json(fixture.storage.as_ref(), "media", &key)
+            .await
+            .unwrap()
+            .unwrap();
+        task_record.stale_topics = vec!["containers".to_string()];
+        put_json(fixture.storage.as_ref(), "media", &key, &task_record)
+            .await
+            .unwrap();
+
+        fixture.track().await.unwrap();
+
+        assert!(cached_topics(&fixture.answer_cache).is_empty());
+        let task_record: TaskRecord = get_json(fixture.storage.as_ref(), "media", &key)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(task_record.stale_topics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_every_task_when_the_sync_fails() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture.ingestion.set_job(
+            "job-1",
+            IngestionJobDetails {
+                failure_reasons: vec!["Access denied".to_string()],
+                ..job(IngestionJobState::Failed, 0)
+            },
+        );
+        cache_answers(&fixture.answer_cache).await;
+
+        fixture.track().await.unwrap();
+
+        let record = fixture.finished_record("job-1").await.unwrap();
+        assert_eq!(record.status, IngestionJobStatus::Failed);
+        assert_eq!(record.failed_task_ids, vec!["task-1", "task-2"]);
+        assert_eq!(
+            fixture.task_status("task-1").await,
+            (TaskStatus::Failed, Some("Access denied".to_string()))
+        );
+        assert_eq!(cached_topics(&fixture.answer_cache).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn waits_for_every_pushed_document() {
+        let record = IngestionJobRecord::pending(
+            "task-1".to_string(),
+            IngestionKind::Documents,
+            tasks(&["task-1"]),
+        );
+        let fixture = Fixture::new(record).await;
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-1", DocumentState::Pending);
+
+        fixture.track().await.unwrap();
+        assert!(fixture.finished_record("task-1").await.is_none());
+
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-1", DocumentState::Indexed);
+
+        fixture.track().await.unwrap();
+        let record = fixture.finished_record("task-1").await.unwrap();
+        assert_eq!(record.status, IngestionJobStatus::Complete);
+        assert_eq!(fixture.task_status("task-1").await.0, TaskStatus::Indexed);
+    }
+
+    #[tokio::test]
+    async fn fails_tasks_the_completed_sync_left_without_an_outcome() {
+        let fixture = Fixture::new(sync_record()).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Complete, 1));
+        fixture
+            .storage
+            .put_object("kb", "transcripts/acme/task-1.summary", b"hi".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-1", DocumentState::Indexed);
+        fixture.ingestion.set_document_state(
+            "s3://kb/transcripts/acme/task-1.summary",
+            DocumentState::Failed("Unsupported content".to_string()),
+        );
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-2", DocumentState::Pending);
+
+        fixture.track().await.unwrap();
+
+        let record = fixture.finished_record("job-1").await.unwrap();
+        assert_eq!(record.failed_task_ids, vec!["task-1", "task-2"]);
+        assert_eq!(
+            fixture.task_status("task-1").await,
+            (TaskStatus::Failed, Some("Unsupported content".to_string()))
+        );
+        assert_eq!(
+            fixture.task_status("task-2").await,
+            (
+                TaskStatus::Failed,
+                Some("Documents not indexed by the ingestion job".to_string())
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn checks_every_pushed_document() {
+        let mut tasks = tasks(&["task-1"]);
+        tasks.get_mut("task-1").unwrap().documents = vec![DocType::Transcript, DocType::Summary];
+        let record =
+            IngestionJobRecord::pending("task-1".to_string(), IngestionKind::Documents, tasks);
+        let fixture = Fixture::new(record).await;
+        fixture
+            .ingestion
+            .set_document_state("s3://kb/transcripts/acme/task-1", DocumentState::Indexed);
+        fixture.ingestion.set_document_state(
+            "s3://kb/transcripts/acme/task-1.summary",
+            DocumentState::Pending,
+        );
+
+        fixture.track().await.unwrap();
+        assert!(fixture.finished_record("task-1").await.is_none());
+
+        fixture.ingestion.set_document_state(
+            "s3://kb/transcripts/acme/task-1.summary",
+            DocumentState::Failed("Too large".to_string()),
+        );
+
+        fixture.track().await.unwrap();
+        let record = fixture.finished_record("task-1").await.unwrap();
+        assert_eq!(record.status, IngestionJobStatus::Failed);
+        assert_eq!(
+            fixture.task_status("task-1").await,
+            (TaskStatus::Failed, Some("Too large".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn tracks_tasks_without_a_task_record() {
+        // The visuals of a video can be queued before its transcript is recorded.
+        let fixture =
+            Fixture::with_task_records(sync_record(), &["task-1".to_string()]).await;
+        fixture
+            .ingestion
+            .set_job("job-1", job(IngestionJobState::Complete, 0));
+
+        fixture.track().await.unwrap();
+
+        let record = fixture.finished_record("job-1").await.unwrap();
+        assert_eq!(record.indexed_task_ids, vec!["task-1", "task-2"]);
+        assert_eq!(fixture.task_status("task-1").await.0, TaskStatus::Indexed);
+
+        let events = fixture.event_publisher.events();
+        let task_2 = events
+            .iter()
+            .find(|(_, detail)| detail["taskId"] == "task-2")
+            .unwrap();
+        assert_eq!(task_2.1["transcriptLocation"], "s3://kb/transcripts/acme/task-2");
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_ingestion_job_is_unknown() {
+        let fixture = Fixture::new(sync_record()).await;
+
+        assert!(fixture.track().await.is_err());
+        assert!(fixture.finished_record("job-1").await.is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use std::env;
+use std::sync::Arc;
+
+use aws_config::BehaviorVersion;
+use lambda_runtime::{Error, LambdaEvent, run, service_fn, tracing};
+use serde_json::Value;
+
+use shared::cache::S3AnswerCache;
+use shared::events::EventBridgePublisher;
+use shared::ingestion::BedrockIngestion;
+use shared::storage::S3Storage;
+use track_ingestion_jobs::{track_ingestion_jobs, IngestionTracker};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+
+    let storage = Arc::new(S3Storage::new(aws_sdk_s3::Client::new(&config)));
+    let media_bucket_name = env::var("MEDIA_BUCKET").expect("MEDIA_BUCKET not set");
+
+    let tracker = IngestionTracker {
+        ingestion: Arc::new(BedrockIngestion::new(
+            aws_sdk_bedrockagent::Client::new(&config),
+            env::var("KB_ID").expect("KB_ID not set"),
+            env::var("DATA_SOURCE_ID").expect("DATA_SOURCE_ID not set"),
+        )),
+        storage: storage.clone(),
+        event_publisher: Arc::new(EventBridgePublisher::new(
+            aws_sdk_eventbridge::Client::new(&config),
+            env::var("EVENT_BUS_NAME").unwrap_or_else(|_| "default".to_string()),
+        )),
+        answer_cache: Arc::new(S3AnswerCache::new(storage, media_bucket_name.clone())),
+        media_bucket_name,
+        kb_bucket_name: env::var("KB_BUCKET").expect("KB_BUCKET not set"),
+    };
+
+    run(service_fn(|event: LambdaEvent<Value>| async {
+        track_ingestion_jobs(event, &tracker).await
+    }))
+        .await
+}
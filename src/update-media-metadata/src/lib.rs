@@ -0,0 +1,432 @@
+use chrono::Utc;
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+use serde_json::json;
+use serde_valid::Validate;
+
+use shared::auth::{authorize, AccessPolicy};
+use shared::error::ApiError;
+use shared::ingestion::{IngestionQueue, IngestionRequest};
+use shared::models::{staging_metadata_key, DocType, MediaMetadata, MediaMetadataPatch};
+use shared::storage::{get_json, put_json, ObjectStorage};
+use shared::task::{task_record_key, TaskRecord};
+use shared::validation::parse_json;
+
+pub async fn update_media_metadata(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    ingestion_queue: &dyn IngestionQueue,
+    media_bucket_name: &str,
+    kb_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, Error> {
+    update_metadata(
+        event,
+        storage,
+        ingestion_queue,
+        media_bucket_name,
+        kb_bucket_name,
+        policy,
+    )
+        .await
+        .or_else(ApiError::into_response)
+}
+
+async fn update_metadata(
+    event: Request,
+    storage: &dyn ObjectStorage,
+    ingestion_queue: &dyn IngestionQueue,
+    media_bucket_name: &str,
+    kb_bucket_name: &str,
+    policy: &AccessPolicy,
+) -> Result<Response<Body>, ApiError> {
+    let caller = authorize(&event, policy)?;
+    let tenant_id = caller.tenant_id.as_str();
+
+    let task_id = event
+        .path_parameters_ref()
+        .and_then(|p| p.first("task_id"))
+        .ok_or_else(|| ApiError::BadRequest("Missing task_id".to_string()))?
+        .to_string();
+
+    let patch: MediaMetadataPatch = parse_json(event.body())?;
+
+    // Staging metadata is kept per tenant, other tenants' tasks are simply not found.
+    let staging_key = staging_metadata_key(tenant_id, &task_id);
+
+    let mut metadata: MediaMetadata = get_json(storage, media_bucket_name, &staging_key)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No media for task {}", task_id)))?;
+
+    if !caller.can_manage(metadata.owner.as_deref()) {
+        return Err(ApiError::AccessDenied(format!(
+            "Only the owner of task {} or an admin can update it",
+            task_id
+        )));
+    }
+
+    let previous_topic = metadata.topic.clone();
+    metadata.apply(patch);
+
+    metadata.validate()?;
+
+    put_json(storage, media_bucket_name, &staging_key, &metadata).await?;
+
+    let task_record: Option<TaskRecord> =
+        get_json(storage, media_bucket_name, &task_record_key(tenant_id, &task_id))
+            .await?;
+    let details = task_record
+        .as_ref()
+        .map(|task_record| task_record.details.clone())
+        .unwrap_or_default();
+
+    // Media that is still being transcribed picks up the new staging metadata once the
+    // transcript lands, so only already-ingested documents need re-syncing here. Their text
+    // never names the topic, rewriting the metadata sidecars is enough to move them.
+    let mut reingested = false;
+
+    for doc_type in DocType::ALL {
+        let key = doc_type.kb_key(tenant_id, &task_id);
+        if !storage.object_exists(kb_bucket_name, &key).await? {
+            continue;
+        }
+
+        put_json(
+            storage,
+            kb_bucket_name,
+            &format!("{}.metadata.json", key),
+            &metadata.to_kb_metadata(tenant_id, &task_id, doc_type, &details),
+        )
+            .await?;
+        reingested = true;
+    }
+
+    if reingested {
+        ingestion_queue
+            .enqueue(&IngestionRequest {
+                task_id: task_id.clone(),
+                tenant_id: tenant_id.to_string(),
+                deleted: false,
+            })
+            .await?;
+    }
+
+    if let Some(mut task_record) = task_record {
+        // The tracker invalidates the answers of the old topic once the move is indexed.
+        if reingested
+            && previous_topic != metadata.topic
+            && !task_record.stale_topics.contains(&previous_topic)
+        {
+            task_record.stale_topics.push(previous_topic);
+        }
+        task_record.metadata = metadata.clone();
+        task_record.updated_at = Utc::now();
+
+        put_json(
+            storage,
+            media_bucket_name,
+            &task_record_key(tenant_id, &task_id),
+            &task_record,
+        )
+            .await?;
+    }
+
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(
+            json!({
+                "task_id": task_id,
+                "metadata": metadata,
+                "reingested": reingested
+            })
+                .to_string()
+                .into(),
+        )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::Value;
+
+    use shared::auth::with_claims;
+    use shared::ingestion::InMemoryIngestionQueue;
+    use shared::models::MediaDetails;
+    use shared::storage::InMemoryStorage;
+    use shared::task::TaskStatus;
+
+    use super::*;
+
+    const OWNER: &[(&str, &str)] = &[("sub", "user-1"), ("custom:tenant_id", "acme")];
+
+    fn request(claims: &[(&str, &str)], task_id: Option<&str>, body: Value) -> Request {
+        let request = lambda_http::http::Request::builder()
+            .method("PATCH")
+            .uri("/media")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let request = with_claims(request, claims);
+
+        match task_id {
+            Some(task_id) => request.with_path_parameters(HashMap::from([(
+                "task_id".to_string(),
+                task_id.to_string(),
+            )])),
+            None => request,
+        }
+    }
+
+    async fn storage_with_metadata() -> InMemoryStorage {
+        let storage = InMemoryStorage::new();
+        put_json(
+            &storage,
+            "media",
+            &staging_metadata_key("acme", "task-1"),
+            &json!({
+                "topic": "serverless",
+                "sourceUrl": "https://example.com/episode-1",
+                "date": "2024-07-01",
+                "owner": "user-1"
+            }),
+        )
+            .await
+            .unwrap();
+        storage
+    }
+
+    async fn patch(
+        storage: &InMemoryStorage,
+        queue: &InMemoryIngestionQueue,
+        task_id: Option<&str>,
+        body: Value,
+    ) -> Response<Body> {
+        patch_as(storage, queue, OWNER, task_id, body).await
+    }
+
+    async fn patch_as(
+        storage: &InMemoryStorage,
+        queue: &InMemoryIngestionQueue,
+        claims: &[(&str, &str)],
+        task_id: Option<&str>,
+        body: Value,
+    ) -> Response<Body> {
+        let policy = AccessPolicy {
+            required_scope: None,
+            admin_group: "admin".to_string(),
+        };
+
+        update_media_metadata(
+            request(claims, task_id, body),
+            storage,
+            queue,
+            "media",
+            "kb",
+            &policy,
+        )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn updates_staging_metadata_before_transcription() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch(&storage, &queue, Some("task-1"), json!({ "topic": "rustlang" })).await;
+
+        assert_eq!(response.status(), 200);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["reingested"], false);
+
+        let metadata: MediaMetadata =
+            get_json(&storage, "media", &staging_metadata_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(metadata.topic, "rustlang");
+        assert!(queue.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reingests_transcribed_media() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+        storage
+            .put_object("kb", "transcripts/acme/task-1", b"hello".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        storage
+            .put_object("kb", "transcripts/acme/task-1.summary", b"hi".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        put_json(
+            &storage,
+            "media",
+            &task_record_key("acme", "task-1"),
+            &TaskRecord {
+                task_id: "task-1".to_string(),
+                tenant_id: "acme".to_string(),
+                metadata: get_json(&storage, "media", &staging_metadata_key("acme", "task-1"))
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                status: TaskStatus::Indexed,
+                details: MediaDetails {
+                    language_code: Some("en-US".to_string()),
+                    ..Default::default()
+                },
+                keywords: Vec::new(),
+                failure_reason: None,
+                stale_topics: Vec::new(),
+                updated_at: Utc::now(),
+            },
+        )
+            .await
+            .unwrap();
+
+        let response = patch(&storage, &queue, Some("task-1"), json!({ "topic": "rustlang" })).await;
+
+        assert_eq!(response.status(), 200);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["reingested"], true);
+
+        let kb_metadata: Value = get_json(&storage, "kb", "transcripts/acme/task-1.metadata.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kb_metadata["metadataAttributes"]["topic"], "rustlang");
+        assert_eq!(kb_metadata["metadataAttributes"]["tenant_id"], "acme");
+        assert_eq!(kb_metadata["metadataAttributes"]["doc_type"], "transcript");
+        assert_eq!(kb_metadata["metadataAttributes"]["language_code"], "en-US");
+
+        let summary_metadata: Value =
+            get_json(&storage, "kb", "transcripts/acme/task-1.summary.metadata.json")
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(summary_metadata["metadataAttributes"]["topic"], "rustlang");
+        assert_eq!(summary_metadata["metadataAttributes"]["doc_type"], "summary");
+        assert!(!storage
+            .object_exists("kb", "transcripts/acme/task-1.chapters.metadata.json")
+            .await
+            .unwrap());
+
+        let task_record: TaskRecord =
+            get_json(&storage, "media", &task_record_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(task_record.metadata.topic, "rustlang");
+        assert_eq!(task_record.stale_topics, vec!["serverless".to_string()]);
+
+        assert_eq!(
+            queue.drain(),
+            vec![IngestionRequest {
+                task_id: "task-1".to_string(),
+                tenant_id: "acme".to_string(),
+                deleted: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_not_found_for_unknown_task() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch(&storage, &queue, Some("task-2"), json!({ "topic": "rustlang" })).await;
+
+        assert_eq!(response.status(), 404);
+        let problem: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(problem["code"], "not-found");
+        assert_eq!(problem["detail"], "No media for task task-2");
+    }
+
+    #[tokio::test]
+    async fn does_not_find_tasks_of_other_tenants() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch_as(
+            &storage,
+            &queue,
+            &[("sub", "user-1"), ("custom:tenant_id", "globex")],
+            Some("task-1"),
+            json!({ "topic": "rustlang" }),
+        )
+            .await;
+
+        assert_eq!(response.status(), 404);
+        let metadata: MediaMetadata =
+            get_json(&storage, "media", &staging_metadata_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(metadata.topic, "serverless");
+    }
+
+    #[tokio::test]
+    async fn lets_only_the_owner_or_an_admin_update() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch_as(
+            &storage,
+            &queue,
+            &[("sub", "user-2"), ("custom:tenant_id", "acme")],
+            Some("task-1"),
+            json!({ "topic": "rustlang" }),
+        )
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let response = patch_as(
+            &storage,
+            &queue,
+            &[
+                ("sub", "user-2"),
+                ("custom:tenant_id", "acme"),
+                ("cognito:groups", "[admin]"),
+            ],
+            Some("task-1"),
+            json!({ "topic": "rustlang" }),
+        )
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let metadata: MediaMetadata =
+            get_json(&storage, "media", &staging_metadata_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(metadata.topic, "rustlang");
+        assert_eq!(metadata.owner.as_deref(), Some("user-1"));
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_task_id() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch(&storage, &queue, None, json!({ "topic": "rustlang" })).await;
+
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_patch() {
+        let storage = storage_with_metadata().await;
+        let queue = InMemoryIngestionQueue::new();
+
+        let response = patch(&storage, &queue, Some("task-1"), json!({ "date": "July" })).await;
+
+        assert_eq!(response.status(), 400);
+        let metadata: MediaMetadata =
+            get_json(&storage, "media", &staging_metadata_key("acme", "task-1"))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(metadata.date, "2024-07-01");
+    }
+}
@@ -0,0 +1,48 @@
+use std::env;
+
+use aws_config::BehaviorVersion;
+use lambda_http::{Error, Request, run, service_fn, tracing};
+
+use shared::auth::AccessPolicy;
+use shared::ingestion::SqsIngestionQueue;
+use shared::storage::S3Storage;
+use update_media_metadata::update_media_metadata;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let storage = S3Storage::new(aws_sdk_s3::Client::new(&config));
+    let ingestion_queue = SqsIngestionQueue::new(
+        aws_sdk_sqs::Client::new(&config),
+        env::var("INGESTION_QUEUE_URL").expect("INGESTION_QUEUE_URL not set"),
+    );
+
+    let media_bucket_name = env::var("MEDIA_BUCKET").expect("MEDIA_BUCKET not set");
+    let kb_bucket_name = env::var("KB_BUCKET").expect("KB_BUCKET not set");
+    let policy = AccessPolicy {
+        required_scope: env::var("REQUIRED_SCOPE").ok().filter(|scope| !scope.is_empty()),
+        admin_group: env::var("ADMIN_GROUP").unwrap_or_else(|_| "admin".to_string()),
+    };
+
+    run(service_fn(|event: Request| async {
+        update_media_metadata(
+            event,
+            &storage,
+            &ingestion_queue,
+            &media_bucket_name,
+            &kb_bucket_name,
+            &policy,
+        )
+            .await
+    }))
+        .await
+}